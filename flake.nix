@@ -136,6 +136,9 @@
               # Merge user settings with defaults
               services.secmon.settings = lib.mkDefault {
                 socket_path = cfg.socketPath;
+                # Members of the service group can connect, other users can't
+                socket_mode = "0660";
+                socket_group = cfg.group;
                 log_level = "info";
                 watches =
                   lib.optionals cfg.monitors.camera [
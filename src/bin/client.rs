@@ -1,105 +1,194 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::PathBuf;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::net::UnixStream;
 use chrono::{DateTime, Utc, Local};
-use chrono_tz::Tz;
 use log::{info, error, warn};
 use std::os::unix::fs::FileTypeExt;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use regex::Regex;
-use toml::Value;
+use uuid::Uuid;
+
+use secmon_core::{CommandResponse, DaemonStatus, SubsystemStatus, SecurityEvent, EventType, EventDetails, Severity, StreamNotice};
+use secmon_core::config::{client_config_value, resolve_socket_path, Config, ConfigFormat, CLIENT_CONFIG_PATHS};
+use secmon_core::storage::{EventQuery, EventStore};
 
 // For daemon control
 extern crate libc;
 
-// Helper function to format timestamps according to display preference
+// Format a (UTC) event timestamp in local time, or in UTC marked with `Z` when
+// display_local_time is off
 fn format_timestamp(timestamp: &DateTime<Utc>, format_str: &str) -> String {
-    let use_local_time = get_display_local_time_setting();
-    if use_local_time {
+    if *DISPLAY_LOCAL_TIME {
         let local_time: DateTime<Local> = timestamp.with_timezone(&Local);
         local_time.format(format_str).to_string()
-    } else {
+    } else if format_str.contains("%z") || format_str.contains("%:z") {
         timestamp.format(format_str).to_string()
+    } else {
+        format!("{}Z", timestamp.format(format_str))
     }
 }
 
+// A top-level string setting such as socket_path
+fn config_string(key: &str) -> Option<String> {
+    client_config_value(|config| config.get(key).and_then(|value| value.as_str()).map(str::to_string))
+}
+
 // Get display_local_time setting from config file
 fn get_display_local_time_setting() -> bool {
-    let config_paths = [
-        "/etc/secmon/config.toml",
-        "./config.toml",
-        "config.toml"
-    ];
+    // Default to local time if no config found
+    client_config_value(|config| config.get("display_local_time").and_then(|value| value.as_bool())).unwrap_or(true)
+}
 
-    for config_path in &config_paths {
-        if let Ok(content) = std::fs::read_to_string(config_path) {
-            if let Ok(config) = toml::from_str::<Value>(&content) {
-                if let Some(display_local_time) = config.get("display_local_time") {
-                    if let Some(setting) = display_local_time.as_bool() {
-                        return setting;
-                    }
-                }
-            }
+// Get notifications.state_file setting from config file
+fn get_notification_state_file_setting() -> Option<PathBuf> {
+    client_config_value(|config| {
+        config.get("notifications").and_then(|n| n.get("state_file")).and_then(|v| v.as_str()).map(PathBuf::from)
+    })
+}
+
+// Desktop notification settings from the [notifications] section
+struct NotificationSettings {
+    enabled: bool,
+    #[cfg_attr(not(feature = "dbus"), allow(dead_code))]
+    dbus_enabled: bool, // Call org.freedesktop.Notifications directly, falling back to `command`
+    min_severity: Severity,
+    timeout_ms: u32,
+    command: String, // Called as `command TITLE BODY --urgency=critical --expire-time=MS`
+    cooldown: Duration,
+    device_cooldown: Duration, // Camera and microphone access
+    rate_limit_per_minute: usize, // 0 = unlimited
+}
+
+fn get_notification_settings() -> NotificationSettings {
+    let mut settings = NotificationSettings {
+        enabled: true,
+        dbus_enabled: true,
+        min_severity: Severity::Medium,
+        timeout_ms: 5000,
+        command: "notify-send".to_string(),
+        cooldown: Duration::from_secs(30),
+        device_cooldown: Duration::from_secs(60),
+        rate_limit_per_minute: 5,
+    };
+
+    client_config_value(|config| {
+        let notifications = config.get("notifications")?;
+        let setting = |key: &str| notifications.get(key).and_then(|v| v.as_integer()).map(|v| v.max(0) as u64);
+        if let Some(enabled) = notifications.get("enabled").and_then(|v| v.as_bool()) {
+            settings.enabled = enabled;
         }
-    }
+        if let Some(dbus_enabled) = notifications.get("dbus_enabled").and_then(|v| v.as_bool()) {
+            settings.dbus_enabled = dbus_enabled;
+        }
+        if let Some(severity) = notifications.get("min_severity").and_then(|v| v.as_str()).and_then(Severity::from_name) {
+            settings.min_severity = severity;
+        }
+        if let Some(timeout_ms) = setting("timeout_ms") {
+            settings.timeout_ms = timeout_ms.min(i32::MAX as u64) as u32;
+        }
+        if let Some(command) = notifications.get("command").and_then(|v| v.as_str()) {
+            settings.command = command.to_string();
+        }
+        if let Some(seconds) = setting("cooldown_seconds") {
+            settings.cooldown = Duration::from_secs(seconds);
+        }
+        if let Some(seconds) = setting("device_cooldown_seconds") {
+            settings.device_cooldown = Duration::from_secs(seconds);
+        }
+        if let Some(limit) = setting("rate_limit_per_minute") {
+            settings.rate_limit_per_minute = limit as usize;
+        }
+        Some(())
+    });
 
-    // Default to local time if no config found
-    true
+    settings
+}
+
+// The daemon's PID and log files in --daemon mode
+fn get_pid_file() -> String {
+    config_string("pid_file").unwrap_or_else(|| "/tmp/secmon.pid".to_string())
+}
+
+fn get_log_file() -> String {
+    config_string("log_file").unwrap_or_else(|| "/tmp/secmon.log".to_string())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SecurityEvent {
-    pub timestamp: DateTime<Utc>,
-    pub event_type: EventType,
-    pub path: PathBuf,
-    pub details: EventDetails,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum EventType {
-    FileAccess,
-    FileModify,
-    FileCreate,
-    FileDelete,
-    DirectoryAccess,
-    CameraAccess,
-    SshAccess,
-    MicrophoneAccess,
-    NetworkConnection,
-    UsbDeviceInserted,
-    NetworkDiscovery,
-    PingDetected,
-    PortScanDetected,
-    CustomMessage,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EventDetails {
-    pub severity: Severity,
-    pub description: String,
-    pub metadata: HashMap<String, String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Severity {
-    Low,
-    Medium,
-    High,
-    Critical,
+// Alert log location and rotation settings from the config file
+struct AlertLogSettings {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
 }
 
+fn get_alert_log_settings() -> AlertLogSettings {
+    let mut settings = AlertLogSettings {
+        path: PathBuf::from("/tmp/secmon-alerts.log"),
+        max_bytes: 10 * 1024 * 1024,
+        keep: 5,
+    };
+
+    // All three come from the same (first readable) config file
+    client_config_value(|config| {
+        if let Some(path) = config.get("alert_log_path").and_then(|v| v.as_str()) {
+            settings.path = PathBuf::from(path);
+        }
+        if let Some(max_bytes) = config.get("max_alert_log_bytes").and_then(|v| v.as_integer()) {
+            settings.max_bytes = max_bytes.max(0) as u64;
+        }
+        if let Some(keep) = config.get("alert_log_keep").and_then(|v| v.as_integer()) {
+            settings.keep = keep.max(0) as usize;
+        }
+        Some(())
+    });
+
+    settings
+}
+
+// The alert log's size is re-read from disk this often (other clients may write to it too);
+// in between, it is tracked from our own writes
+const ALERT_LOG_STAT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct AlertLogState {
+    size: u64,
+    checked_at: Option<Instant>,
+}
+
+// `logs -f` checks the log for new data, rotation and truncation this often
+const LOG_FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Clients started with --reconnect (and the TUI) retry a lost daemon connection after
+// this long, doubling each failed attempt up to the maximum
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+// `status` gives up on a daemon that doesn't answer its status command within this long
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long a notification cooldown entry is kept before it is considered stale
+const NOTIFICATION_COOLDOWN_RETENTION: Duration = Duration::from_secs(300);
+
 // Global state for notification cooldowns and rate limiting
 lazy_static::lazy_static! {
-    static ref NOTIFICATION_COOLDOWNS: Arc<Mutex<HashMap<String, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref NOTIFICATION_STATE_FILE: Option<PathBuf> = get_notification_state_file_setting();
+    static ref NOTIFICATION_COOLDOWNS: Arc<Mutex<HashMap<String, DateTime<Utc>>>> =
+        Arc::new(Mutex::new(load_notification_cooldowns()));
     static ref NOTIFICATION_RATE_LIMITER: Arc<Mutex<Vec<Instant>>> = Arc::new(Mutex::new(Vec::new()));
+    static ref NOTIFICATION_SETTINGS: NotificationSettings = get_notification_settings();
+    static ref ALERT_LOG_SETTINGS: AlertLogSettings = get_alert_log_settings();
+    static ref ALERT_LOG_STATE: Mutex<AlertLogState> = Mutex::new(AlertLogState::default());
+    // Read once rather than for every timestamp shown
+    static ref DISPLAY_LOCAL_TIME: bool = get_display_local_time_setting();
 }
 
+// Session bus connection for notifications, opened on first use and dropped after a failed call
+#[cfg(feature = "dbus")]
+static NOTIFICATION_BUS: Mutex<Option<zbus::blocking::Connection>> = Mutex::new(None);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
@@ -125,16 +214,57 @@ async fn main() -> Result<()> {
             daemon_restart(config_path).await
         }
         "status" => {
-            daemon_status().await
+            let mut cli_socket_path: Option<String> = None;
+            let mut cli_token: Option<String> = None;
+            let mut i = 2;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--socket" | "-s" => {
+                        if i + 1 < args.len() {
+                            cli_socket_path = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --socket requires a value");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--token" => {
+                        if i + 1 < args.len() {
+                            cli_token = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --token requires a value");
+                            std::process::exit(1);
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            daemon_status(cli_socket_path.as_ref(), cli_token.as_ref()).await
         }
         "logs" => {
-            let lines = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(50);
-            daemon_logs(lines).await
+            let mut lines = 50;
+            let mut follow = false;
+            for arg in &args[2..] {
+                match arg.as_str() {
+                    "--follow" | "-f" => follow = true,
+                    other => match other.parse() {
+                        Ok(count) => lines = count,
+                        Err(_) => {
+                            eprintln!("Error: Unknown logs argument '{}'", other);
+                            std::process::exit(1);
+                        }
+                    },
+                }
+            }
+            daemon_logs(lines, follow).await
         }
         "monitor" => {
             let mut cli_socket_path: Option<String> = None;
-            let mut json_mode = false;
+            let mut cli_token: Option<String> = None;
+            let mut format = OutputFormat::Text;
             let mut filter_severity: Option<Severity> = None;
+            let mut reconnect = false;
 
             // Parse arguments starting from index 2
             let mut i = 2;
@@ -149,24 +279,39 @@ async fn main() -> Result<()> {
                             std::process::exit(1);
                         }
                     }
+                    "--token" => {
+                        if i + 1 < args.len() {
+                            cli_token = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --token requires a value");
+                            std::process::exit(1);
+                        }
+                    }
                     "--json" | "-j" => {
-                        json_mode = true;
+                        format = OutputFormat::Json;
                         i += 1;
                     }
-                    "--severity-low" => {
-                        filter_severity = Some(Severity::Low);
+                    "--reconnect" => {
+                        reconnect = true;
                         i += 1;
                     }
-                    "--severity-medium" => {
-                        filter_severity = Some(Severity::Medium);
-                        i += 1;
+                    "--format" => {
+                        match args.get(i + 1).and_then(|name| OutputFormat::from_name(name)) {
+                            Some(parsed) => format = parsed,
+                            None => {
+                                eprintln!("Error: --format requires one of: text, json, ndjson, csv");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
                     }
-                    "--severity-high" => {
-                        filter_severity = Some(Severity::High);
-                        i += 1;
+                    "--min-severity" => {
+                        filter_severity = Some(parse_min_severity(args.get(i + 1)));
+                        i += 2;
                     }
-                    "--severity-critical" => {
-                        filter_severity = Some(Severity::Critical);
+                    flag @ ("--severity-low" | "--severity-medium" | "--severity-high" | "--severity-critical") => {
+                        filter_severity = deprecated_severity_flag(flag);
                         i += 1;
                     }
                     arg if !arg.starts_with("--") && !arg.starts_with("-") => {
@@ -180,13 +325,16 @@ async fn main() -> Result<()> {
                 }
             }
 
-            let socket_path = resolve_socket_path(cli_socket_path.as_ref());
-            monitor_events(&socket_path, json_mode, filter_severity).await
+            let socket_path = resolve_socket_path(cli_socket_path.as_deref());
+            let token = resolve_token(cli_token.as_ref());
+            monitor_events(&socket_path, token.as_deref(), format, filter_severity, reconnect).await
         }
         "listen" => {
             let mut cli_socket_path: Option<String> = None;
-            let mut json_mode = false;
+            let mut cli_token: Option<String> = None;
+            let mut format = OutputFormat::Text;
             let mut filter_severity: Option<Severity> = None;
+            let mut reconnect = false;
 
             // Parse arguments starting from index 2
             let mut i = 2;
@@ -201,24 +349,39 @@ async fn main() -> Result<()> {
                             std::process::exit(1);
                         }
                     }
+                    "--token" => {
+                        if i + 1 < args.len() {
+                            cli_token = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --token requires a value");
+                            std::process::exit(1);
+                        }
+                    }
                     "--json" | "-j" => {
-                        json_mode = true;
+                        format = OutputFormat::Json;
                         i += 1;
                     }
-                    "--severity-low" => {
-                        filter_severity = Some(Severity::Low);
+                    "--reconnect" => {
+                        reconnect = true;
                         i += 1;
                     }
-                    "--severity-medium" => {
-                        filter_severity = Some(Severity::Medium);
-                        i += 1;
+                    "--format" => {
+                        match args.get(i + 1).and_then(|name| OutputFormat::from_name(name)) {
+                            Some(parsed) => format = parsed,
+                            None => {
+                                eprintln!("Error: --format requires one of: text, json, ndjson, csv");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
                     }
-                    "--severity-high" => {
-                        filter_severity = Some(Severity::High);
-                        i += 1;
+                    "--min-severity" => {
+                        filter_severity = Some(parse_min_severity(args.get(i + 1)));
+                        i += 2;
                     }
-                    "--severity-critical" => {
-                        filter_severity = Some(Severity::Critical);
+                    flag @ ("--severity-low" | "--severity-medium" | "--severity-high" | "--severity-critical") => {
+                        filter_severity = deprecated_severity_flag(flag);
                         i += 1;
                     }
                     arg if !arg.starts_with("--") && !arg.starts_with("-") => {
@@ -232,8 +395,9 @@ async fn main() -> Result<()> {
                 }
             }
 
-            let socket_path = resolve_socket_path(cli_socket_path.as_ref());
-            listen_events(&socket_path, json_mode, filter_severity).await
+            let socket_path = resolve_socket_path(cli_socket_path.as_deref());
+            let token = resolve_token(cli_token.as_ref());
+            listen_events(&socket_path, token.as_deref(), format, filter_severity, reconnect).await
         }
         "config" => {
             if args.len() < 3 {
@@ -258,65 +422,266 @@ async fn main() -> Result<()> {
         }
         "stats" => {
             let mut since = None;
+            let mut until = None;
+            let mut db_path = None;
+            let mut json = false;
             let mut i = 2;
             while i < args.len() {
                 match args[i].as_str() {
-                    "--since" => {
+                    flag @ ("--since" | "--until") => {
+                        let Some(value) = args.get(i + 1) else {
+                            eprintln!("Error: {} requires a value", flag);
+                            std::process::exit(1);
+                        };
+                        let Some(time) = parse_time_duration(value) else {
+                            eprintln!("Error: invalid {} '{}' ({})", flag, value, TIME_FORMAT_HINT);
+                            std::process::exit(1);
+                        };
+                        if flag == "--since" {
+                            since = Some(time);
+                        } else {
+                            until = Some(time);
+                        }
+                        i += 2;
+                    }
+                    "--db" => {
                         if i + 1 < args.len() {
-                            since = Some(args[i + 1].clone());
+                            db_path = Some(args[i + 1].clone());
                             i += 2;
                         } else {
-                            eprintln!("Error: --since requires a value");
+                            eprintln!("Error: --db requires a value");
                             std::process::exit(1);
                         }
                     }
+                    "--format" => {
+                        match args.get(i + 1).map(String::as_str) {
+                            Some("text") => json = false,
+                            Some("json") => json = true,
+                            _ => {
+                                eprintln!("Error: --format requires one of: text, json");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
                     _ => i += 1,
                 }
             }
-            stats_show(since).await
+            stats_show(since, until, db_path, json).await
         }
         "search" => {
-            let mut path_filter = None;
+            let mut options = SearchOptions::default();
+
+            let mut i = 2;
+            while i < args.len() {
+                let flag = args[i].as_str();
+                let takes_value = matches!(
+                    flag,
+                    "--path" | "--path-regex" | "--since" | "--until" | "--type" | "--min-severity" | "--severity" | "--limit" | "--format" | "--db"
+                );
+                if !takes_value {
+                    i += 1;
+                    continue;
+                }
+                let Some(value) = args.get(i + 1).cloned() else {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                };
+                match flag {
+                    "--path" => options.path = Some(PathBuf::from(value)),
+                    "--path-regex" => match Regex::new(&value) {
+                        Ok(regex) => options.path_regex = Some(regex),
+                        Err(e) => {
+                            eprintln!("Error: invalid --path-regex '{}': {}", value, e);
+                            std::process::exit(1);
+                        }
+                    },
+                    "--since" | "--until" => match parse_time_duration(&value) {
+                        Some(time) if flag == "--since" => options.since = Some(time),
+                        Some(time) => options.until = Some(time),
+                        None => {
+                            eprintln!("Error: invalid {} '{}' ({})", flag, value, TIME_FORMAT_HINT);
+                            std::process::exit(1);
+                        }
+                    },
+                    "--type" => match EventType::from_name(&value) {
+                        Some(event_type) => options.event_type = Some(event_type),
+                        None => {
+                            eprintln!("Error: Unknown event type '{}'", value);
+                            std::process::exit(1);
+                        }
+                    },
+                    "--min-severity" => options.min_severity = Some(parse_min_severity(Some(&value))),
+                    // Kept as a deprecated alias: it was always a minimum, not an exact match
+                    "--severity" => {
+                        eprintln!("Warning: --severity is deprecated, use --min-severity");
+                        options.min_severity = Some(parse_min_severity(Some(&value)));
+                    }
+                    "--limit" => match value.parse() {
+                        Ok(limit) => options.limit = Some(limit),
+                        Err(_) => {
+                            eprintln!("Error: --limit requires a number");
+                            std::process::exit(1);
+                        }
+                    },
+                    "--format" => match value.as_str() {
+                        "text" => options.json = false,
+                        "json" => options.json = true,
+                        _ => {
+                            eprintln!("Error: --format requires one of: text, json");
+                            std::process::exit(1);
+                        }
+                    },
+                    _ => options.db_path = Some(value),
+                }
+                i += 2;
+            }
+            search_events(options).await
+        }
+        "export" => {
             let mut since = None;
-            let mut event_type = None;
+            let mut until = None;
+            let mut out = None;
+            let mut format = OutputFormat::Ndjson;
+            let mut db_path = None;
+
+            let mut i = 2;
+            while i < args.len() {
+                let flag = args[i].as_str();
+                let Some(value) = args.get(i + 1).cloned() else {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                };
+                match flag {
+                    "--since" | "--until" => match parse_time_duration(&value) {
+                        Some(time) if flag == "--since" => since = Some(time),
+                        Some(time) => until = Some(time),
+                        None => {
+                            eprintln!("Error: invalid {} '{}' ({})", flag, value, TIME_FORMAT_HINT);
+                            std::process::exit(1);
+                        }
+                    },
+                    "--out" | "-o" => out = Some(value),
+                    "--format" => match value.to_lowercase().as_str() {
+                        "ndjson" => format = OutputFormat::Ndjson,
+                        "csv" => format = OutputFormat::Csv,
+                        "json" => format = OutputFormat::Json,
+                        _ => {
+                            eprintln!("Error: --format requires one of: ndjson, csv, json");
+                            std::process::exit(1);
+                        }
+                    },
+                    "--db" => db_path = Some(value),
+                    _ => {
+                        eprintln!("Error: Unknown export option: {}", flag);
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            let Some(out) = out else {
+                eprintln!("Error: export requires --out FILE (or --out - for stdout)");
+                std::process::exit(1);
+            };
+            export_events(since, until, &out, format, db_path)
+        }
+        "bench" => {
+            let mut cli_socket_path: Option<String> = None;
+            let mut cli_token: Option<String> = None;
+            let mut rate: u64 = 1000;
+            let mut duration = Duration::from_secs(10);
 
             let mut i = 2;
             while i < args.len() {
                 match args[i].as_str() {
-                    "--path" => {
+                    "--socket" | "-s" => {
                         if i + 1 < args.len() {
-                            path_filter = Some(args[i + 1].clone());
+                            cli_socket_path = Some(args[i + 1].clone());
                             i += 2;
                         } else {
-                            eprintln!("Error: --path requires a value");
+                            eprintln!("Error: --socket requires a value");
                             std::process::exit(1);
                         }
                     }
-                    "--since" => {
+                    "--token" => {
                         if i + 1 < args.len() {
-                            since = Some(args[i + 1].clone());
+                            cli_token = Some(args[i + 1].clone());
                             i += 2;
                         } else {
-                            eprintln!("Error: --since requires a value");
+                            eprintln!("Error: --token requires a value");
                             std::process::exit(1);
                         }
                     }
-                    "--type" => {
+                    "--rate" => {
+                        match args.get(i + 1).and_then(|s| s.parse::<u64>().ok()) {
+                            Some(value) if value > 0 => rate = value,
+                            _ => {
+                                eprintln!("Error: --rate requires a positive number of events per second");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
+                    "--duration" => {
+                        match args.get(i + 1).and_then(|s| parse_bench_duration(s)) {
+                            Some(value) => duration = value,
+                            None => {
+                                eprintln!("Error: --duration requires a value like 30s, 2m or 10");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
+                    _ => i += 1,
+                }
+            }
+
+            let socket_path = resolve_socket_path(cli_socket_path.as_deref());
+            let token = resolve_token(cli_token.as_ref());
+            run_bench(&socket_path, token.as_deref(), rate, duration).await
+        }
+        "snapshot" => {
+            let mut out_path: Option<String> = None;
+            let mut i = 2;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--out" | "-o" => {
                         if i + 1 < args.len() {
-                            event_type = Some(args[i + 1].clone());
+                            out_path = Some(args[i + 1].clone());
                             i += 2;
                         } else {
-                            eprintln!("Error: --type requires a value");
+                            eprintln!("Error: --out requires a value");
                             std::process::exit(1);
                         }
                     }
                     _ => i += 1,
                 }
             }
-            search_events(path_filter, since, event_type).await
+            snapshot_take(out_path).await
+        }
+        "diff" => {
+            let mut json_mode = false;
+            let mut files = Vec::new();
+            for arg in &args[2..] {
+                match arg.as_str() {
+                    "--json" | "-j" => json_mode = true,
+                    _ => files.push(arg.clone()),
+                }
+            }
+            if files.len() != 2 {
+                eprintln!("Error: diff requires two snapshot files");
+                eprintln!("Usage: secmon-client diff <BEFORE> <AFTER> [--json]");
+                std::process::exit(1);
+            }
+            snapshot_diff(&files[0], &files[1], json_mode).await
         }
         "tui" => {
             let mut cli_socket_path: Option<String> = None;
+            let mut cli_token: Option<String> = None;
+            let mut from_file: Option<PathBuf> = None;
+            let mut since = None;
+            let mut event_type = None;
+            let mut reconnect = true;
 
             // Parse arguments starting from index 2
             let mut i = 2;
@@ -331,6 +696,46 @@ async fn main() -> Result<()> {
                             std::process::exit(1);
                         }
                     }
+                    "--token" => {
+                        if i + 1 < args.len() {
+                            cli_token = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --token requires a value");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--no-reconnect" => {
+                        reconnect = false;
+                        i += 1;
+                    }
+                    "--from" => {
+                        if i + 1 < args.len() {
+                            from_file = Some(PathBuf::from(&args[i + 1]));
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --from requires a value");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--since" => {
+                        if i + 1 < args.len() {
+                            since = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --since requires a value");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--type" => {
+                        if i + 1 < args.len() {
+                            event_type = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --type requires a value");
+                            std::process::exit(1);
+                        }
+                    }
                     arg if !arg.starts_with("--") && !arg.starts_with("-") => {
                         // Backward compatibility: positional socket path
                         cli_socket_path = Some(arg.to_string());
@@ -342,8 +747,27 @@ async fn main() -> Result<()> {
                 }
             }
 
-            let socket_path = resolve_socket_path(cli_socket_path.as_ref());
-            run_tui_with_socket(&socket_path).await
+            if let Some(path) = from_file {
+                if !path.exists() {
+                    eprintln!("Error: event file not found: {}", path.display());
+                    std::process::exit(1);
+                }
+                let since = match since {
+                    Some(value) => match parse_time_duration(&value) {
+                        Some(timestamp) => Some(timestamp),
+                        None => {
+                            eprintln!("Error: invalid --since value '{}' ({})", value, TIME_FORMAT_HINT);
+                            std::process::exit(1);
+                        }
+                    },
+                    None => None,
+                };
+                run_tui(TuiSource::File { path, since, event_type }).await
+            } else {
+                let socket_path = resolve_socket_path(cli_socket_path.as_deref());
+                let token = resolve_token(cli_token.as_ref());
+                run_tui(TuiSource::Socket { path: socket_path, token, reconnect }).await
+            }
         }
         "--help" | "-h" => {
             print_client_help();
@@ -352,7 +776,8 @@ async fn main() -> Result<()> {
         _ => {
             // Backward compatibility: if first arg looks like a socket path, use old behavior
             if command.starts_with('/') || command.starts_with('.') {
-                monitor_events(command, false, None).await
+                let token = resolve_token(None);
+                monitor_events(command, token.as_deref(), OutputFormat::Text, None, false).await
             } else {
                 eprintln!("Error: Unknown command '{}'", command);
                 print_client_help();
@@ -372,14 +797,23 @@ fn print_client_help() {
     println!("    start [CONFIG]     Start the daemon");
     println!("    stop               Stop the daemon");
     println!("    restart [CONFIG]   Restart the daemon");
-    println!("    status             Show daemon status");
-    println!("    logs [LINES]       Show daemon logs (default: 50 lines)");
-    println!("    monitor [--socket PATH] [--json]  Monitor security events (includes buffered events)");
-    println!("    listen [--socket PATH] [--json]   Listen for new security events only (from connection time)");
+    println!("    status [--socket PATH] [--token T]  Show daemon health: uptime, watches, clients, monitors");
+    println!("    logs [LINES] [-f]  Show daemon logs (default: 50 lines); -f keeps following");
+    println!("    monitor [--socket PATH] [--token T] [--json] [--format F] [--min-severity LEVEL] [--reconnect]");
+    println!("           Monitor security events (includes buffered events)");
+    println!("    listen [--socket PATH] [--token T] [--json] [--format F] [--min-severity LEVEL] [--reconnect]");
+    println!("           Listen for new security events only (from connection time)");
     println!("    config <validate|show|reload>  Configuration management");
-    println!("    stats [--since TIME]       Show event statistics");
-    println!("    search [--path P] [--since T] [--type TYPE]  Search events");
-    println!("    tui [--socket PATH]        Interactive terminal interface");
+    println!("    stats [--since TIME] [--until TIME] [--db PATH] [--format text|json]  Event counts by type and severity, and per hour");
+    println!("    search [--path P] [--path-regex RE] [--since T] [--until T] [--type TYPE] [--min-severity LEVEL]");
+    println!("           [--limit N] [--format text|json] [--db PATH]  Search the event store");
+    println!("    export --out FILE|- [--since T] [--until T] [--format ndjson|csv|json] [--db PATH]");
+    println!("           Write stored events in a time range to a file, for audits");
+    println!("    bench [--rate R] [--duration D]  Stress the daemon event pipeline and report latency");
+    println!("    snapshot [--out FILE]      Capture a security posture snapshot as JSON");
+    println!("    diff BEFORE AFTER [--json] Compare two posture snapshots");
+    println!("    tui [--socket PATH] [--token T] [--no-reconnect]  Interactive terminal interface (reconnects by default)");
+    println!("    tui --from FILE [--since T] [--type TYPE]  Browse a recorded NDJSON event log offline");
     println!("    help, --help, -h   Show this help message");
     println!();
     println!("EXAMPLES:");
@@ -389,19 +823,33 @@ fn print_client_help() {
     println!("    secmon-client status                   # Check daemon status");
     println!("    secmon-client logs                     # Show last 50 log lines");
     println!("    secmon-client logs 100                 # Show last 100 log lines");
+    println!("    secmon-client logs -f                  # Follow the log like tail -f");
     println!("    secmon-client monitor                  # Monitor events (uses config/default socket)");
     println!("    secmon-client monitor --socket /custom/path --json  # Monitor with custom socket");
     println!("    secmon-client listen                   # Listen for new events only");
+    println!("    secmon-client listen --reconnect       # Keep listening across daemon restarts");
+    println!("    secmon-client listen --min-severity high # Only High and Critical events");
     println!("    secmon-client listen --socket /tmp/secmon.sock --json # Listen with JSON output");
+    println!("    secmon-client listen --format csv > events.csv # Record events for a spreadsheet");
     println!("    secmon-client config validate          # Validate config file");
     println!("    secmon-client stats --since 1h         # Show stats from last hour");
+    println!("    secmon-client stats --since 2d --until 1d  # Stats for the 24h before the last 24h");
     println!("    secmon-client search --path /home      # Search events by path");
+    println!("    secmon-client search --type SshAccess --min-severity High --since 7d --limit 20");
+    println!("    secmon-client export --since 2024-01-01 --until 2024-02-01 --out january.ndjson");
+    println!("    secmon-client bench --rate 5000 --duration 30s # Measure fan-out latency under load");
+    println!("    secmon-client snapshot --out before.json  # Capture current posture");
+    println!("    secmon-client diff before.json after.json # Show what changed between snapshots");
     println!("    secmon-client tui --socket /custom/socket # Interactive monitoring with custom socket");
     println!();
     println!("SOCKET PATH RESOLUTION:");
     println!("    1. Command line --socket argument (highest priority)");
-    println!("    2. socket_path setting in config file");
-    println!("    3. Default: /tmp/secmon.sock");
+    println!("    2. SECMON_SOCKET environment variable");
+    println!("    3. socket_path setting in config file (${{VAR}} and ~ expanded, as the daemon does)");
+    println!("    4. Default: $XDG_RUNTIME_DIR/secmon.sock, or /tmp/secmon-$USER.sock, as the daemon does");
+    println!();
+    println!("AUTH TOKEN (daemons with [auth] token set):");
+    println!("    --token argument, then [auth] token in the config file");
     println!();
     println!("CONFIG FILE LOCATIONS (checked in order):");
     println!("    /etc/secmon/config.toml");
@@ -426,151 +874,320 @@ fn print_config_help() {
     println!("    secmon-client config reload");
 }
 
-async fn monitor_events(socket_path: &str, json_mode: bool, filter_severity: Option<Severity>) -> Result<()> {
-    info!("Connecting to secmon daemon at: {}", socket_path);
+/// Delay before each reconnect attempt, doubling from the initial delay up to the cap
+struct ReconnectBackoff {
+    delay: Duration,
+}
 
-    let stream = UnixStream::connect(&socket_path)
-        .await
-        .with_context(|| format!("Failed to connect to socket: {}", socket_path))?;
+impl ReconnectBackoff {
+    fn new() -> Self {
+        Self { delay: RECONNECT_INITIAL_DELAY }
+    }
 
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(RECONNECT_MAX_DELAY);
+        delay
+    }
 
-    if json_mode {
-        info!("Connected! Streaming JSON events...");
-        // In JSON mode, output events directly without headers
-    } else {
-        info!("Connected! Listening for security events...");
-        println!("Timestamp | Severity | Type | Path | Description");
-        println!("---------|----------|------|------|-------------");
+    fn reset(&mut self) {
+        self.delay = RECONNECT_INITIAL_DELAY;
     }
+}
 
-    loop {
-        line.clear();
-        match reader.read_line(&mut line).await {
-            Ok(0) => {
-                info!("Connection closed by daemon");
-                break;
-            }
-            Ok(_) => {
-                match serde_json::from_str::<SecurityEvent>(&line.trim()) {
-                    Ok(event) => {
-                        // Apply severity filter if specified
-                        if let Some(min_severity) = &filter_severity {
-                            let event_severity_level = match event.details.severity {
-                                Severity::Low => 1,
-                                Severity::Medium => 2,
-                                Severity::High => 3,
-                                Severity::Critical => 4,
-                            };
-                            let min_severity_level = match min_severity {
-                                Severity::Low => 1,
-                                Severity::Medium => 2,
-                                Severity::High => 3,
-                                Severity::Critical => 4,
-                            };
+/// Send {"auth":"<token>"} as the first line, for daemons with `[auth] token` set
+async fn send_auth(stream: &mut UnixStream, token: Option<&str>) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
 
-                            // Skip events below the minimum severity
-                            if event_severity_level < min_severity_level {
-                                continue;
-                            }
-                        }
+    if let Some(token) = token {
+        let line = format!("{}\n", serde_json::json!({ "auth": token }));
+        stream.write_all(line.as_bytes()).await.context("Failed to send auth token")?;
+    }
+    Ok(())
+}
 
-                        if json_mode {
-                            handle_json_event(&event);
-                        } else {
-                            handle_security_event(&event);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to parse event: {} - Line: {}", e, line.trim());
-                    }
-                }
+/// The daemon's reply to a missing or wrong token, sent just before it disconnects
+fn is_auth_rejection(line: &str) -> bool {
+    serde_json::from_str::<CommandResponse>(line).is_ok_and(|response| response.cmd == "auth" && !response.ok)
+}
+
+/// Connect to the daemon socket and authenticate. With `reconnect`, failures are retried
+/// with backoff until the daemon is back instead of being returned.
+async fn connect_daemon(socket_path: &str, token: Option<&str>, reconnect: bool, backoff: &mut ReconnectBackoff) -> Result<UnixStream> {
+    loop {
+        match UnixStream::connect(socket_path).await {
+            Ok(mut stream) => {
+                send_auth(&mut stream, token).await?;
+                backoff.reset();
+                return Ok(stream);
+            }
+            Err(e) if reconnect => {
+                let delay = backoff.next_delay();
+                eprintln!("Cannot connect to {} ({}), retrying in {:.1}s...", socket_path, e, delay.as_secs_f64());
+                tokio::time::sleep(delay).await;
             }
             Err(e) => {
-                error!("Failed to read from socket: {}", e);
-                break;
+                return Err(e).with_context(|| format!("Failed to connect to socket: {}", socket_path));
             }
         }
     }
-
-    Ok(())
 }
 
-async fn listen_events(socket_path: &str, json_mode: bool, filter_severity: Option<Severity>) -> Result<()> {
+async fn monitor_events(socket_path: &str, token: Option<&str>, format: OutputFormat, filter_severity: Option<Severity>, reconnect: bool) -> Result<()> {
     info!("Connecting to secmon daemon at: {}", socket_path);
 
-    let stream = UnixStream::connect(&socket_path)
-        .await
-        .with_context(|| format!("Failed to connect to socket: {}", socket_path))?;
+    let mut backoff = ReconnectBackoff::new();
+    let mut first_connection = true;
 
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
+    loop {
+        let stream = connect_daemon(socket_path, token, reconnect, &mut backoff).await?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
 
-    // Get connection timestamp to filter out old events
-    let connection_time = chrono::Utc::now();
+        info!("Connected! Listening for security events...");
+        if first_connection {
+            print_event_header(format);
+            first_connection = false;
+        }
 
-    if json_mode {
-        info!("Connected! Listening for new JSON events (from connection time)...");
-        // In JSON mode, output events directly without headers
+        loop {
+            line.clear();
+            match reader.read_line(&mut line).await {
+                Ok(0) => {
+                    info!("Connection closed by daemon");
+                    break;
+                }
+                Ok(_) => {
+                    if let Ok(notice) = serde_json::from_str::<StreamNotice>(line.trim()) {
+                        handle_stream_notice(&notice, line.trim(), format);
+                        continue;
+                    }
+                    if is_auth_rejection(line.trim()) {
+                        anyhow::bail!("Daemon rejected the auth token (set --token or [auth] token)");
+                    }
+                    match serde_json::from_str::<SecurityEvent>(line.trim()) {
+                        Ok(event) => {
+                            // Apply severity filter if specified
+                            if let Some(min_severity) = &filter_severity {
+                                let event_severity_level = match event.details.severity {
+                                    Severity::Low => 1,
+                                    Severity::Medium => 2,
+                                    Severity::High => 3,
+                                    Severity::Critical => 4,
+                                };
+                                let min_severity_level = match min_severity {
+                                    Severity::Low => 1,
+                                    Severity::Medium => 2,
+                                    Severity::High => 3,
+                                    Severity::Critical => 4,
+                                };
+
+                                // Skip events below the minimum severity
+                                if event_severity_level < min_severity_level {
+                                    continue;
+                                }
+                            }
+
+                            match format {
+                                OutputFormat::Text => handle_security_event(&event),
+                                OutputFormat::Json => handle_json_event(&event),
+                                OutputFormat::Ndjson | OutputFormat::Csv => {
+                                    print_event(&event, format);
+                                    alert_critical_event(&event);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!("Failed to parse event: {} - Line: {}", e, line.trim());
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to read from socket: {}", e);
+                    break;
+                }
+            }
+        }
+
+        if !reconnect {
+            break;
+        }
+        eprintln!("Connection to daemon lost, reconnecting...");
+    }
+
+    Ok(())
+}
+
+/// How `monitor` and `listen` print events
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,   // Colorized table
+    Json,   // Events enriched with formatted timestamps, severity level and category
+    Ndjson, // The raw SecurityEvent, one compact object per line
+    Csv,    // timestamp,severity,type,path,description after a header row
+}
+
+impl OutputFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "ndjson" => Some(OutputFormat::Ndjson),
+            "csv" => Some(OutputFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+const CSV_HEADER: &str = "timestamp,severity,type,path,description,id";
+
+fn print_event_header(format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {
+            println!("Timestamp | Severity | Type | Path | Description");
+            println!("---------|----------|------|------|-------------");
+        }
+        OutputFormat::Csv => println!("{}", CSV_HEADER),
+        OutputFormat::Json | OutputFormat::Ndjson => {}
+    }
+}
+
+/// Print an event as NDJSON or CSV
+fn print_event(event: &SecurityEvent, format: OutputFormat) {
+    if let Err(e) = write_event(&mut std::io::stdout().lock(), event, format) {
+        error!("Failed to write event: {}", e);
+    }
+}
+
+/// Write an event as an NDJSON or CSV line
+fn write_event(out: &mut impl std::io::Write, event: &SecurityEvent, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Ndjson => {
+            serde_json::to_writer(&mut *out, event)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            event.timestamp.to_rfc3339(),
+            csv_field(&format!("{:?}", event.details.severity)),
+            csv_field(event.event_type.name()),
+            csv_field(&event.path.to_string_lossy()),
+            csv_field(&event.details.description),
+            event.id,
+        )?,
+        OutputFormat::Text | OutputFormat::Json => {}
+    }
+    Ok(())
+}
+
+/// Quote a CSV field when it contains a comma, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
     } else {
-        info!("Connected! Listening for new security events (from connection time)...");
-        println!("Timestamp | Severity | Type | Path | Description");
-        println!("---------|----------|------|------|-------------");
+        value.to_string()
+    }
+}
+
+/// JSON output passes notices through for scripts; otherwise say what happened on stderr
+fn handle_stream_notice(notice: &StreamNotice, line: &str, format: OutputFormat) {
+    if matches!(format, OutputFormat::Json | OutputFormat::Ndjson) {
+        println!("{}", line);
+        return;
+    }
+    match notice {
+        StreamNotice::Lagged { skipped } => {
+            eprintln!("Warning: {} events were dropped because this client fell behind the daemon", skipped);
+        }
     }
+}
+
+async fn listen_events(socket_path: &str, token: Option<&str>, format: OutputFormat, filter_severity: Option<Severity>, reconnect: bool) -> Result<()> {
+    info!("Connecting to secmon daemon at: {}", socket_path);
+
+    let mut backoff = ReconnectBackoff::new();
+    let mut first_connection = true;
 
     loop {
-        line.clear();
-        match reader.read_line(&mut line).await {
-            Ok(0) => {
-                info!("Connection closed by daemon");
-                break;
-            }
-            Ok(_) => {
-                match serde_json::from_str::<SecurityEvent>(&line.trim()) {
-                    Ok(event) => {
-                        // Filter out events that occurred before we connected
-                        if event.timestamp <= connection_time {
-                            continue;
-                        }
+        let stream = connect_daemon(socket_path, token, reconnect, &mut backoff).await?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
 
-                        // Apply severity filter if specified
-                        if let Some(min_severity) = &filter_severity {
-                            let event_severity_level = match event.details.severity {
-                                Severity::Low => 1,
-                                Severity::Medium => 2,
-                                Severity::High => 3,
-                                Severity::Critical => 4,
-                            };
-                            let min_severity_level = match min_severity {
-                                Severity::Low => 1,
-                                Severity::Medium => 2,
-                                Severity::High => 3,
-                                Severity::Critical => 4,
-                            };
+        // Get connection timestamp to filter out old events; a reconnect starts a new baseline
+        let connection_time = chrono::Utc::now();
 
-                            // Skip events below the minimum severity
-                            if event_severity_level < min_severity_level {
+        info!("Connected! Listening for new security events (from connection time)...");
+        if first_connection {
+            print_event_header(format);
+            first_connection = false;
+        }
+
+        loop {
+            line.clear();
+            match reader.read_line(&mut line).await {
+                Ok(0) => {
+                    info!("Connection closed by daemon");
+                    break;
+                }
+                Ok(_) => {
+                    if let Ok(notice) = serde_json::from_str::<StreamNotice>(line.trim()) {
+                        handle_stream_notice(&notice, line.trim(), format);
+                        continue;
+                    }
+                    if is_auth_rejection(line.trim()) {
+                        anyhow::bail!("Daemon rejected the auth token (set --token or [auth] token)");
+                    }
+                    match serde_json::from_str::<SecurityEvent>(line.trim()) {
+                        Ok(event) => {
+                            // Filter out events that occurred before we connected
+                            if event.timestamp <= connection_time {
                                 continue;
                             }
-                        }
 
-                        if json_mode {
-                            handle_json_event_listen(&event);
-                        } else {
-                            handle_security_event_listen(&event);
+                            // Apply severity filter if specified
+                            if let Some(min_severity) = &filter_severity {
+                                let event_severity_level = match event.details.severity {
+                                    Severity::Low => 1,
+                                    Severity::Medium => 2,
+                                    Severity::High => 3,
+                                    Severity::Critical => 4,
+                                };
+                                let min_severity_level = match min_severity {
+                                    Severity::Low => 1,
+                                    Severity::Medium => 2,
+                                    Severity::High => 3,
+                                    Severity::Critical => 4,
+                                };
+
+                                // Skip events below the minimum severity
+                                if event_severity_level < min_severity_level {
+                                    continue;
+                                }
+                            }
+
+                            match format {
+                                OutputFormat::Text => handle_security_event_listen(&event),
+                                OutputFormat::Json => handle_json_event_listen(&event),
+                                OutputFormat::Ndjson | OutputFormat::Csv => print_event(&event, format),
+                            }
+                        }
+                        Err(e) => {
+                            error!("Failed to parse event: {} - Line: {}", e, line.trim());
                         }
-                    }
-                    Err(e) => {
-                        error!("Failed to parse event: {} - Line: {}", e, line.trim());
                     }
                 }
-            }
-            Err(e) => {
-                error!("Failed to read from socket: {}", e);
-                break;
+                Err(e) => {
+                    error!("Failed to read from socket: {}", e);
+                    break;
+                }
             }
         }
+
+        if !reconnect {
+            break;
+        }
+        eprintln!("Connection to daemon lost, reconnecting...");
     }
 
     Ok(())
@@ -665,28 +1282,48 @@ async fn daemon_restart(config_path: Option<String>) -> Result<()> {
     daemon_start(config_path).await
 }
 
-async fn daemon_status() -> Result<()> {
+async fn daemon_status(cli_socket: Option<&String>, cli_token: Option<&String>) -> Result<()> {
+    let socket_path = resolve_socket_path(cli_socket.map(String::as_str));
+    let token = resolve_token(cli_token);
+
+    // A daemon that answers on the socket is running, whether or not it wrote a PID file
+    let query_error = match query_daemon_status(&socket_path, token.as_deref()).await {
+        Ok(status) => {
+            println!("Daemon is running (PID: {}, version {})", status.pid, status.version);
+            println!("Socket: {} (active)", socket_path);
+            let log_path = get_log_file();
+            if let Ok(log_metadata) = tokio::fs::metadata(&log_path).await {
+                println!("Log file: {} ({} bytes)", log_path, log_metadata.len());
+            }
+            print_daemon_status(&status);
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+
     match read_daemon_pid().await? {
         Some(pid) => {
             if is_process_running(pid) {
                 println!("Daemon is running (PID: {})", pid);
+                println!("Status unavailable: {:#}", query_error);
 
                 // Show additional info if available
-                if let Ok(socket_exists) = tokio::fs::metadata("/tmp/secmon.sock").await {
+                if let Ok(socket_exists) = tokio::fs::metadata(&socket_path).await {
                     if socket_exists.file_type().is_socket() {
-                        println!("Socket: /tmp/secmon.sock (active)");
+                        println!("Socket: {} (active)", socket_path);
                     }
                 } else {
-                    println!("Socket: /tmp/secmon.sock (not found)");
+                    println!("Socket: {} (not found)", socket_path);
                 }
 
-                if let Ok(log_metadata) = tokio::fs::metadata("/tmp/secmon.log").await {
-                    println!("Log file: /tmp/secmon.log ({} bytes)", log_metadata.len());
+                let log_path = get_log_file();
+                if let Ok(log_metadata) = tokio::fs::metadata(&log_path).await {
+                    println!("Log file: {} ({} bytes)", log_path, log_metadata.len());
                 }
             } else {
                 println!("Daemon is not running (stale PID file)");
                 // Clean up stale PID file
-                let _ = tokio::fs::remove_file("/tmp/secmon.pid").await;
+                let _ = tokio::fs::remove_file(get_pid_file()).await;
             }
         }
         None => {
@@ -696,10 +1333,65 @@ async fn daemon_status() -> Result<()> {
     Ok(())
 }
 
-async fn daemon_logs(lines: usize) -> Result<()> {
-    let log_path = "/tmp/secmon.log";
+/// Ask the daemon for its `status`, skipping any events that arrive before the reply
+async fn query_daemon_status(socket_path: &str, token: Option<&str>) -> Result<DaemonStatus> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut stream = connect_daemon(socket_path, token, false, &mut ReconnectBackoff::new()).await?;
+    stream.write_all(b"{\"cmd\":\"status\"}\n").await.context("Failed to send status command")?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let response = tokio::time::timeout(STATUS_TIMEOUT, async {
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                anyhow::bail!("Daemon closed the connection");
+            }
+            if is_auth_rejection(line.trim()) {
+                anyhow::bail!("Daemon rejected the auth token (set --token or [auth] token)");
+            }
+            if let Ok(response) = serde_json::from_str::<CommandResponse>(line.trim()) {
+                if response.cmd == "status" {
+                    return Ok(response);
+                }
+            }
+        }
+    })
+    .await
+    .context("Daemon did not answer the status command")??;
+
+    if !response.ok {
+        // Daemons from before the status command reply with "Invalid command"
+        anyhow::bail!("{}", response.error.unwrap_or_else(|| "status command failed".to_string()));
+    }
+    let data = response.data.context("Status response has no data")?;
+    serde_json::from_value(data).context("Failed to parse status response")
+}
 
-    match tokio::fs::read_to_string(log_path).await {
+fn print_daemon_status(status: &DaemonStatus) {
+    let uptime = status.uptime_seconds;
+    println!("Uptime: {}d {:02}h {:02}m {:02}s", uptime / 86400, uptime / 3600 % 24, uptime / 60 % 60, uptime % 60);
+    println!("Inotify watches: {}", status.inotify_watches);
+    println!("Clients connected: {}", status.clients_connected);
+    println!("Events processed: {}", status.events_total);
+
+    if !status.subsystems.is_empty() {
+        println!("Monitors:");
+        for (name, subsystem) in &status.subsystems {
+            match subsystem {
+                SubsystemStatus::Running => println!("  {:12} ✓ running", name),
+                SubsystemStatus::Disabled => println!("  {:12} - disabled", name),
+                SubsystemStatus::Failed { error } => println!("  {:12} ✗ failed: {}", name, error),
+            }
+        }
+    }
+}
+
+async fn daemon_logs(lines: usize, follow: bool) -> Result<()> {
+    let log_path = get_log_file();
+
+    match tokio::fs::read_to_string(&log_path).await {
         Ok(content) => {
             let log_lines: Vec<&str> = content.lines().collect();
             let start_line = if log_lines.len() > lines {
@@ -713,6 +1405,10 @@ async fn daemon_logs(lines: usize) -> Result<()> {
             for line in &log_lines[start_line..] {
                 println!("{}", line);
             }
+
+            if follow {
+                return follow_log(&log_path, content.len() as u64).await;
+            }
         }
         Err(e) => {
             eprintln!("Failed to read log file {}: {}", log_path, e);
@@ -722,6 +1418,60 @@ async fn daemon_logs(lines: usize) -> Result<()> {
     Ok(())
 }
 
+/// Print lines appended to `path` after `offset` until interrupted, like `tail -F`. A file
+/// replaced by rotation (new inode) is reopened and a truncated one re-read from the start.
+async fn follow_log(path: &str, mut offset: u64) -> Result<()> {
+    use std::io::{SeekFrom, Write};
+    use std::os::unix::fs::MetadataExt;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut file = tokio::fs::File::open(path).await
+        .with_context(|| format!("Failed to open log file: {}", path))?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut inode = file.metadata().await?.ino();
+    let mut partial = Vec::new(); // Bytes after the last newline, printed once the line ends
+    let mut buffer = vec![0u8; 8192];
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read > 0 {
+            offset += read as u64;
+            partial.extend_from_slice(&buffer[..read]);
+            if let Some(end) = partial.iter().rposition(|&byte| byte == b'\n') {
+                let complete: Vec<u8> = partial.drain(..=end).collect();
+                print!("{}", String::from_utf8_lossy(&complete));
+                std::io::stdout().flush()?;
+            }
+            continue;
+        }
+
+        tokio::time::sleep(LOG_FOLLOW_POLL_INTERVAL).await;
+
+        // The path may briefly not exist while the log is being rotated
+        let Ok(metadata) = tokio::fs::metadata(path).await else {
+            continue;
+        };
+        if metadata.ino() != inode {
+            match tokio::fs::File::open(path).await {
+                Ok(reopened) => {
+                    if !partial.is_empty() {
+                        println!("{}", String::from_utf8_lossy(&partial));
+                        partial.clear();
+                    }
+                    file = reopened;
+                    inode = metadata.ino();
+                    offset = 0;
+                }
+                Err(e) => warn!("Failed to reopen rotated log {}: {}", path, e),
+            }
+        } else if metadata.len() < offset {
+            file.seek(SeekFrom::Start(0)).await?;
+            offset = 0;
+            partial.clear();
+        }
+    }
+}
+
 async fn is_daemon_running() -> Result<bool> {
     match read_daemon_pid().await? {
         Some(pid) => Ok(is_process_running(pid)),
@@ -730,13 +1480,14 @@ async fn is_daemon_running() -> Result<bool> {
 }
 
 async fn read_daemon_pid() -> Result<Option<u32>> {
-    match tokio::fs::read_to_string("/tmp/secmon.pid").await {
+    let pid_file = get_pid_file();
+    match tokio::fs::read_to_string(&pid_file).await {
         Ok(content) => {
             match content.trim().parse::<u32>() {
                 Ok(pid) => Ok(Some(pid)),
                 Err(_) => {
                     // Invalid PID file
-                    let _ = tokio::fs::remove_file("/tmp/secmon.pid").await;
+                    let _ = tokio::fs::remove_file(&pid_file).await;
                     Ok(None)
                 }
             }
@@ -771,33 +1522,48 @@ fn get_daemon_path() -> Result<String> {
 async fn config_validate(config_path: &str) -> Result<()> {
     println!("Validating configuration file: {}", config_path);
 
-    match std::fs::read_to_string(config_path) {
-        Ok(content) => {
-            match toml::from_str::<toml::Value>(&content) {
-                Ok(_) => {
-                    println!("✓ Configuration file syntax is valid");
-                    Ok(())
-                }
-                Err(e) => {
-                    eprintln!("✗ Configuration file has syntax errors:");
-                    eprintln!("  {}", e);
-                    std::process::exit(1);
-                }
-            }
-        }
+    let content = match std::fs::read_to_string(config_path) {
+        Ok(content) => content,
         Err(e) => {
             eprintln!("✗ Failed to read configuration file: {}", e);
             std::process::exit(1);
         }
+    };
+
+    // Deserializing into Config (not just a generic value) also catches missing fields and
+    // wrong types. The extension picks TOML, YAML or JSON, as the daemon does.
+    let mut config: Config = match ConfigFormat::from_path(config_path).parse(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("✗ Configuration file has errors:");
+            eprintln!("  {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = config.merge_includes(config_path) {
+        eprintln!("✗ Included configuration has errors:");
+        eprintln!("  {:#}", e);
+        std::process::exit(1);
+    }
+    println!("✓ Configuration file syntax is valid");
+
+    let issues = config.validate();
+    if issues.is_empty() {
+        println!("✓ Configuration values are valid ({} watches, {} triggers)", config.watches.len(), config.triggers.len());
+        return Ok(());
+    }
+
+    eprintln!("✗ Found {} problem{}:", issues.len(), if issues.len() == 1 { "" } else { "s" });
+    for issue in &issues {
+        eprintln!("  {}", issue);
     }
+    std::process::exit(1);
 }
 
 async fn config_show() -> Result<()> {
     println!("Current daemon configuration:");
 
-    let config_paths = ["/etc/secmon/config.toml", "./config.toml"];
-
-    for path in &config_paths {
+    for path in &CLIENT_CONFIG_PATHS {
         if let Ok(content) = std::fs::read_to_string(path) {
             println!("Configuration from {}:", path);
             println!("{}", content);
@@ -810,139 +1576,665 @@ async fn config_show() -> Result<()> {
 }
 
 async fn config_reload() -> Result<()> {
+    let pid = match read_daemon_pid().await? {
+        Some(pid) if is_process_running(pid) => pid,
+        _ => {
+            eprintln!("Daemon is not running");
+            std::process::exit(1);
+        }
+    };
+
     println!("Reloading daemon configuration...");
-    println!("Note: Config reload requires daemon support (not yet implemented)");
-    println!("Recommendation: Use 'secmon-client restart' for now");
+    unsafe {
+        if libc::kill(pid as i32, libc::SIGHUP) != 0 {
+            eprintln!("Failed to send reload signal to daemon: {}", std::io::Error::last_os_error());
+            std::process::exit(1);
+        }
+    }
+    println!("Reload signal sent to daemon (PID: {})", pid);
+    println!("Check the daemon log for the result; an invalid config is rejected and the current one kept");
     Ok(())
 }
 
 // Statistics and reporting functions
-async fn stats_show(since: Option<String>) -> Result<()> {
+async fn stats_show(since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>, db_path: Option<String>, json: bool) -> Result<()> {
+    let store = open_event_store(db_path)?;
+    let counts = store.count_by_type_and_severity(since, until)?;
+
+    // One row per hour of the --since/--until window (the day before --until, or before
+    // now, without --since), empty hours included
+    let histogram_end = until.unwrap_or_else(Utc::now);
+    let histogram_start = since.unwrap_or(histogram_end - chrono::Duration::hours(24));
+    let first_hour = histogram_start.timestamp().div_euclid(3600);
+    let last_hour = (histogram_end.timestamp_millis() - 1).div_euclid(3_600_000);
+    let hourly_counts: HashMap<DateTime<Utc>, u64> =
+        store.count_by_hour(histogram_start, histogram_end)?.into_iter().collect();
+    let hourly: Vec<(DateTime<Utc>, u64)> = (first_hour..=last_hour)
+        .filter_map(|hour| DateTime::from_timestamp(hour * 3600, 0))
+        .map(|hour| (hour, hourly_counts.get(&hour).copied().unwrap_or(0)))
+        .collect();
+
+    let total: u64 = counts.iter().map(|(_, _, count)| count).sum();
+
+    if json {
+        let report = serde_json::json!({
+            "since": since,
+            "until": until,
+            "total": total,
+            "counts": counts.iter().map(|(event_type, severity, count)| serde_json::json!({
+                "event_type": event_type,
+                "severity": severity,
+                "count": count,
+            })).collect::<Vec<_>>(),
+            "hourly": hourly.iter().map(|(hour, count)| serde_json::json!({
+                "hour": hour,
+                "count": count,
+            })).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
     println!("Event Statistics");
-    if let Some(time) = &since {
-        println!("Since: {}", time);
+    if let Some(since) = &since {
+        println!("Since: {}", format_timestamp(since, "%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(until) = &until {
+        println!("Until: {}", format_timestamp(until, "%Y-%m-%d %H:%M:%S"));
     }
     println!("==================");
 
-    match std::fs::read_to_string("/tmp/secmon-alerts.log") {
-        Ok(content) => {
-            let mut stats = std::collections::HashMap::new();
-            let lines: Vec<&str> = content.lines().collect();
+    if counts.is_empty() {
+        println!("No events found");
+        return Ok(());
+    }
+
+    // Types in the store's order (most frequent first), severities as columns
+    const SEVERITIES: [&str; 4] = ["Low", "Medium", "High", "Critical"];
+    let mut rows: Vec<(&str, [u64; 4])> = Vec::new();
+    for (event_type, severity, count) in &counts {
+        let index = match rows.iter().position(|(name, _)| name == event_type) {
+            Some(index) => index,
+            None => {
+                rows.push((event_type, [0; 4]));
+                rows.len() - 1
+            }
+        };
+        if let Some(column) = SEVERITIES.iter().position(|name| name == severity) {
+            rows[index].1[column] += count;
+        }
+    }
+
+    println!("{:20}   {:>8} {:>8} {:>8} {:>8} {:>8}", "Event type", "Low", "Medium", "High", "Critical", "Total");
+    for (event_type, by_severity) in &rows {
+        let [low, medium, high, critical] = by_severity;
+        println!(
+            "{:20}   {:>8} {:>8} {:>8} {:>8} {:>8}",
+            event_type, low, medium, high, critical, by_severity.iter().sum::<u64>()
+        );
+    }
+    println!("{:20}   {:>44}", "Total", total);
+
+    println!();
+    println!("Events per hour{}:", if since.is_some() { "" } else { " (24h)" });
+    let busiest = hourly.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+    for (hour, count) in &hourly {
+        let bar = "█".repeat((count * 40).div_ceil(busiest) as usize);
+        let line = format!("{}  {:>6} {}", format_timestamp(hour, "%Y-%m-%d %H:00"), count, bar);
+        println!("{}", line.trim_end());
+    }
+
+    if let Some(oldest) = store.oldest()? {
+        println!();
+        println!("History starts {}", format_timestamp(&oldest, "%Y-%m-%d %H:%M:%S"));
+    }
+
+    Ok(())
+}
+
+// Search and filtering functions
+/// Filters and output for `secmon-client search`
+#[derive(Default)]
+struct SearchOptions {
+    path: Option<PathBuf>, // The path itself or anything beneath it
+    path_regex: Option<Regex>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    event_type: Option<EventType>,
+    min_severity: Option<Severity>,
+    limit: Option<usize>, // Keep only the most recent matches
+    json: bool,
+    db_path: Option<String>,
+}
+
+async fn search_events(options: SearchOptions) -> Result<()> {
+    let store = open_event_store(options.db_path.clone())?;
+
+    let query = EventQuery {
+        since: options.since,
+        until: options.until,
+        event_type: options.event_type.clone(),
+        min_severity: options.min_severity.clone(),
+    };
+    let mut events: Vec<SecurityEvent> = store
+        .query(&query)?
+        .into_iter()
+        .filter(|event| options.path.as_ref().is_none_or(|path| event.path.starts_with(path)))
+        .filter(|event| options.path_regex.as_ref().is_none_or(|regex| regex.is_match(&event.path.to_string_lossy())))
+        .collect();
+    let total = events.len();
+    if let Some(limit) = options.limit {
+        events.drain(..events.len().saturating_sub(limit));
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&events)?);
+        return Ok(());
+    }
+
+    println!("Searching events...");
+    if let Some(path) = &options.path {
+        println!("Path: {}", path.display());
+    }
+    if let Some(regex) = &options.path_regex {
+        println!("Path regex: {}", regex);
+    }
+    if let Some(since) = &options.since {
+        println!("Since: {}", format_timestamp(since, "%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(until) = &options.until {
+        println!("Until: {}", format_timestamp(until, "%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(event_type) = &options.event_type {
+        println!("Event type: {}", event_type.name());
+    }
+    if let Some(severity) = &options.min_severity {
+        println!("Minimum severity: {:?}", severity);
+    }
+
+    println!("Results:");
+    println!("========");
+
+    for event in &events {
+        println!("[{}] {:?} {}: {} - {}",
+            format_timestamp(&event.timestamp, "%Y-%m-%d %H:%M:%S"),
+            event.details.severity,
+            event.event_type.name(),
+            event.path.display(),
+            event.details.description
+        );
+    }
+
+    println!();
+    if events.len() < total {
+        println!("Showing the {} most recent of {} matching events", events.len(), total);
+    } else {
+        println!("Found {} matching events", total);
+    }
+
+    Ok(())
+}
+
+// The event store the daemon writes to ([storage] path), or the --db override
+/// Stream stored events in `since <= t < until` to `out` ("-" for stdout), one at a
+/// time so large ranges don't have to fit in memory
+fn export_events(
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    out: &str,
+    format: OutputFormat,
+    db_path: Option<String>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let store = open_event_store(db_path)?;
+    let mut writer: Box<dyn Write> = if out == "-" {
+        Box::new(std::io::BufWriter::new(std::io::stdout().lock()))
+    } else {
+        let file = std::fs::File::create(out).with_context(|| format!("Failed to create {}", out))?;
+        Box::new(std::io::BufWriter::new(file))
+    };
+
+    match format {
+        OutputFormat::Csv => writeln!(writer, "{}", CSV_HEADER)?,
+        OutputFormat::Json => write!(writer, "[")?,
+        OutputFormat::Text | OutputFormat::Ndjson => {}
+    }
+
+    let query = EventQuery { since, until, event_type: None, min_severity: None };
+    let mut count: u64 = 0;
+    store.for_each_event(&query, |event| {
+        if format == OutputFormat::Json {
+            // A JSON array written element by element
+            write!(writer, "{}\n  ", if count == 0 { "" } else { "," })?;
+            serde_json::to_writer(&mut writer, &event)?;
+        } else {
+            write_event(&mut writer, &event, format)?;
+        }
+        count += 1;
+        Ok(())
+    })?;
+
+    if format == OutputFormat::Json {
+        writeln!(writer, "{}]", if count == 0 { "" } else { "\n" })?;
+    }
+    writer.flush().with_context(|| format!("Failed to write {}", out))?;
+
+    eprintln!("Exported {} events{}", count, if out == "-" { String::new() } else { format!(" to {}", out) });
+    Ok(())
+}
+
+fn open_event_store(db_path: Option<String>) -> Result<EventStore> {
+    let Some(path) = db_path.or_else(get_storage_path_from_config) else {
+        eprintln!("No event store configured. Set [storage] path in the daemon config or pass --db PATH.");
+        std::process::exit(1);
+    };
+
+    if !std::path::Path::new(&path).exists() {
+        eprintln!("Event store {} not found. Make sure the daemon is running with [storage] enabled.", path);
+        std::process::exit(1);
+    }
+
+    EventStore::open_read_only(&path)
+}
+
+// Pipeline benchmark: one connection floods synthetic events, a second one measures them
+fn parse_bench_duration(value: &str) -> Option<Duration> {
+    let (number, multiplier) = if let Some(minutes) = value.strip_suffix('m') {
+        (minutes, 60)
+    } else if let Some(seconds) = value.strip_suffix('s') {
+        (seconds, 1)
+    } else {
+        (value, 1)
+    };
+
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 => Some(Duration::from_secs(n * multiplier)),
+        _ => None,
+    }
+}
+
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+async fn run_bench(socket_path: &str, token: Option<&str>, rate: u64, duration: Duration) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let run_id = format!("{}-{}", std::process::id(), Utc::now().timestamp_millis());
+
+    // Subscribe first so nothing we send is missed
+    let mut subscriber = UnixStream::connect(socket_path)
+        .await
+        .with_context(|| format!("Failed to connect to socket: {}", socket_path))?;
+    send_auth(&mut subscriber, token).await?;
+    let mut producer = UnixStream::connect(socket_path)
+        .await
+        .with_context(|| format!("Failed to connect to socket: {}", socket_path))?;
+    send_auth(&mut producer, token).await?;
+
+    println!("Benchmarking {} at {} events/s for {}s", socket_path, rate, duration.as_secs());
+
+    let received = Arc::new(Mutex::new(Vec::<(u64, f64)>::new()));
+    let reader_task = {
+        let received = Arc::clone(&received);
+        let run_id = run_id.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(subscriber);
+            let mut line = String::new();
+            loop {
+                line.clear();
+                match reader.read_line(&mut line).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        let Ok(event) = serde_json::from_str::<SecurityEvent>(line.trim()) else {
+                            continue;
+                        };
+                        let metadata = &event.details.metadata;
+                        if metadata.get("bench_run") != Some(&run_id) {
+                            continue;
+                        }
+
+                        let seq = metadata.get("bench_seq").and_then(|s| s.parse::<u64>().ok());
+                        let sent_us = metadata.get("bench_sent_us").and_then(|s| s.parse::<i64>().ok());
+                        if let (Some(seq), Some(sent_us)) = (seq, sent_us) {
+                            let latency_ms = (Utc::now().timestamp_micros() - sent_us) as f64 / 1000.0;
+                            received.lock().unwrap().push((seq, latency_ms));
+                        }
+                    }
+                }
+            }
+        })
+    };
+
+    // Pace sends against the wall clock so slow ticks are caught up in bursts
+    let start = Instant::now();
+    let mut sent: u64 = 0;
+    let mut ticker = tokio::time::interval(Duration::from_millis(1));
+    while start.elapsed() < duration {
+        ticker.tick().await;
+        let due = (start.elapsed().as_secs_f64() * rate as f64) as u64;
+
+        let mut batch = String::new();
+        while sent < due {
+            let mut metadata = HashMap::new();
+            metadata.insert("bench_run".to_string(), run_id.clone());
+            metadata.insert("bench_seq".to_string(), sent.to_string());
+            metadata.insert("bench_sent_us".to_string(), Utc::now().timestamp_micros().to_string());
+
+            let event = SecurityEvent {
+                id: Uuid::new_v4(),
+                timestamp: Utc::now(),
+                event_type: EventType::CustomMessage,
+                path: PathBuf::from("/secmon/bench"),
+                details: EventDetails {
+                    severity: Severity::Low,
+                    description: format!("Benchmark event {}", sent),
+                    metadata,
+                },
+            };
+            batch.push_str(&serde_json::to_string(&event)?);
+            batch.push('\n');
+            sent += 1;
+        }
+
+        if !batch.is_empty() {
+            producer.write_all(batch.as_bytes()).await
+                .context("Failed to send benchmark events")?;
+        }
+    }
+    let send_elapsed = start.elapsed();
+
+    // Give the fan-out a moment to deliver the tail of the run
+    let drain_deadline = Instant::now() + Duration::from_secs(2);
+    while Instant::now() < drain_deadline && (received.lock().unwrap().len() as u64) < sent {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    reader_task.abort();
+
+    let samples = received.lock().unwrap().clone();
+    let delivered = samples.len() as u64;
+
+    // Sequence gaps show where the broadcast channel dropped events for a lagging reader
+    let mut seqs: Vec<u64> = samples.iter().map(|(seq, _)| *seq).collect();
+    seqs.sort_unstable();
+    let gaps = seqs.windows(2).filter(|pair| pair[1] > pair[0] + 1).count();
+
+    let mut latencies: Vec<f64> = samples.iter().map(|(_, latency)| *latency).collect();
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    println!();
+    println!("Benchmark Results");
+    println!("==================");
+    println!("Sent:        {} events in {:.2}s ({:.0} events/s)",
+        sent, send_elapsed.as_secs_f64(), sent as f64 / send_elapsed.as_secs_f64());
+    println!("Delivered:   {} events ({:.0} events/s)",
+        delivered, delivered as f64 / send_elapsed.as_secs_f64());
+    println!("Dropped:     {} ({:.2}%), {} gaps in sequence",
+        sent.saturating_sub(delivered),
+        if sent > 0 { sent.saturating_sub(delivered) as f64 * 100.0 / sent as f64 } else { 0.0 },
+        gaps);
+    println!("Latency p50: {:.3} ms", percentile(&latencies, 50.0));
+    println!("Latency p95: {:.3} ms", percentile(&latencies, 95.0));
+    println!("Latency p99: {:.3} ms", percentile(&latencies, 99.0));
+    println!("Latency max: {:.3} ms", latencies.last().copied().unwrap_or(0.0));
+
+    Ok(())
+}
+
+// Posture snapshots: a point-in-time view of the system that can be diffed later
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PostureSnapshot {
+    taken_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    listening_ports: BTreeSet<String>,
+    #[serde(default)]
+    external_connections: BTreeSet<String>,
+    #[serde(default)]
+    av_devices_in_use: BTreeSet<String>,
+    #[serde(default)]
+    removable_mounts: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SetChange {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl SetChange {
+    fn between(before: &BTreeSet<String>, after: &BTreeSet<String>) -> Self {
+        Self {
+            added: after.difference(before).cloned().collect(),
+            removed: before.difference(after).cloned().collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+async fn snapshot_take(out_path: Option<String>) -> Result<()> {
+    let snapshot = capture_posture();
+    let json = serde_json::to_string_pretty(&snapshot)
+        .context("Failed to serialize snapshot")?;
+
+    match out_path {
+        Some(path) => {
+            std::fs::write(&path, format!("{}\n", json))
+                .with_context(|| format!("Failed to write snapshot: {}", path))?;
+            println!("Snapshot written to {}", path);
+            println!("  Listening ports:      {}", snapshot.listening_ports.len());
+            println!("  External connections: {}", snapshot.external_connections.len());
+            println!("  Camera/mic in use:    {}", snapshot.av_devices_in_use.len());
+            println!("  Removable mounts:     {}", snapshot.removable_mounts.len());
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+fn capture_posture() -> PostureSnapshot {
+    let mut snapshot = PostureSnapshot {
+        taken_at: Some(Utc::now()),
+        ..Default::default()
+    };
+
+    let tables = [("tcp", procfs::net::tcp()), ("tcp6", procfs::net::tcp6())];
+    for (protocol, table) in tables {
+        let entries = match table {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read /proc/net/{}: {}", protocol, e);
+                continue;
+            }
+        };
+
+        for entry in entries {
+            match entry.state {
+                procfs::net::TcpState::Listen => {
+                    snapshot.listening_ports.insert(format!("{} {}", protocol, entry.local_address));
+                }
+                procfs::net::TcpState::Established if !entry.remote_address.ip().is_loopback() => {
+                    snapshot.external_connections.insert(format!(
+                        "{} {} -> {}", protocol, entry.local_address, entry.remote_address
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
 
-            let since_timestamp = if let Some(time_str) = since {
-                parse_time_duration(&time_str)
-            } else {
-                None
+    // Which processes currently hold camera or microphone devices open
+    if let Ok(processes) = procfs::process::all_processes() {
+        for process in processes.flatten() {
+            let Ok(fds) = process.fd() else {
+                continue; // Not permitted to inspect this process
             };
 
-            for line in lines {
-                if let Some(event_type) = extract_event_type_from_log(line) {
-                    if let Some(since_ts) = since_timestamp {
-                        if let Some(log_time) = extract_timestamp_from_log(line) {
-                            if log_time < since_ts {
-                                continue;
-                            }
-                        }
+            let comm = process.stat().map(|stat| stat.comm).unwrap_or_else(|_| "?".to_string());
+            for fd in fds.flatten() {
+                if let procfs::process::FDTarget::Path(path) = fd.target {
+                    let path_str = path.to_string_lossy();
+                    if path_str.starts_with("/dev/video") || path_str.starts_with("/dev/snd/pcm") {
+                        snapshot.av_devices_in_use.insert(format!(
+                            "{} (pid {} {})", path_str, process.pid, comm
+                        ));
                     }
-
-                    *stats.entry(event_type).or_insert(0) += 1;
                 }
             }
+        }
+    }
 
-            if stats.is_empty() {
-                println!("No events found");
-            } else {
-                for (event_type, count) in stats.iter() {
-                    println!("{:20} : {}", event_type, count);
+    match std::fs::read_to_string("/proc/mounts") {
+        Ok(mounts) => {
+            for line in mounts.lines() {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 3 || !fields[0].starts_with("/dev/") {
+                    continue;
+                }
+                if is_removable_block_device(fields[0]) {
+                    snapshot.removable_mounts.insert(format!(
+                        "{} on {} ({})", fields[0], fields[1], fields[2]
+                    ));
                 }
             }
         }
-        Err(_) => {
-            println!("No event log found. Make sure the daemon is running and has generated events.");
-        }
+        Err(e) => warn!("Failed to read /proc/mounts: {}", e),
     }
 
-    Ok(())
+    snapshot
 }
 
-// Search and filtering functions
-async fn search_events(path_filter: Option<String>, since: Option<String>, event_type: Option<String>) -> Result<()> {
-    println!("Searching events...");
+fn is_removable_block_device(device: &str) -> bool {
+    let Ok(resolved) = std::fs::canonicalize(device) else {
+        return false;
+    };
+    let Some(name) = resolved.file_name() else {
+        return false;
+    };
 
-    if let Some(path) = &path_filter {
-        println!("Path filter: {}", path);
-    }
-    if let Some(time) = &since {
-        println!("Since: {}", time);
-    }
-    if let Some(evt_type) = &event_type {
-        println!("Event type: {}", evt_type);
+    let sys_path = std::path::Path::new("/sys/class/block").join(name);
+    let Ok(sys_device) = std::fs::canonicalize(&sys_path) else {
+        return false;
+    };
+
+    // USB sticks often report removable=0, so also trust the bus they hang off
+    if sys_device.to_string_lossy().contains("/usb") {
+        return true;
     }
 
-    println!("Results:");
-    println!("========");
+    // Partitions inherit the removable flag from their parent disk
+    [sys_device.join("removable"), sys_device.join("../removable")]
+        .iter()
+        .any(|flag| std::fs::read_to_string(flag).map(|v| v.trim() == "1").unwrap_or(false))
+}
 
-    match std::fs::read_to_string("/tmp/secmon-alerts.log") {
-        Ok(content) => {
-            let lines: Vec<&str> = content.lines().collect();
-            let mut matches = 0;
+async fn snapshot_diff(before_path: &str, after_path: &str, json_mode: bool) -> Result<()> {
+    let load = |path: &str| -> Result<PostureSnapshot> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read snapshot: {}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse snapshot: {}", path))
+    };
 
-            let path_regex = if let Some(path) = path_filter {
-                Some(Regex::new(&path).unwrap_or_else(|_| Regex::new(&regex::escape(&path)).unwrap()))
-            } else {
-                None
-            };
+    let before = load(before_path)?;
+    let after = load(after_path)?;
+
+    let sections = [
+        ("Listening ports", "listening_ports",
+            SetChange::between(&before.listening_ports, &after.listening_ports)),
+        ("External connections", "external_connections",
+            SetChange::between(&before.external_connections, &after.external_connections)),
+        ("Camera/microphone in use", "av_devices_in_use",
+            SetChange::between(&before.av_devices_in_use, &after.av_devices_in_use)),
+        ("Removable mounts", "removable_mounts",
+            SetChange::between(&before.removable_mounts, &after.removable_mounts)),
+    ];
 
-            let since_timestamp = if let Some(time_str) = since {
-                parse_time_duration(&time_str)
-            } else {
-                None
-            };
+    if json_mode {
+        let mut report = serde_json::Map::new();
+        report.insert("before".to_string(), serde_json::json!(before.taken_at));
+        report.insert("after".to_string(), serde_json::json!(after.taken_at));
+        for (_, key, change) in &sections {
+            report.insert(key.to_string(), serde_json::to_value(change)?);
+        }
+        println!("{}", serde_json::Value::Object(report));
+        return Ok(());
+    }
 
-            for line in lines {
-                let mut should_include = true;
+    let describe = |taken_at: &Option<DateTime<Utc>>| {
+        taken_at
+            .map(|ts| format_timestamp(&ts, "%Y-%m-%d %H:%M:%S"))
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+    println!("Posture diff: {} -> {}", describe(&before.taken_at), describe(&after.taken_at));
+    println!("==================");
 
-                if let Some(ref regex) = path_regex {
-                    if !regex.is_match(line) {
-                        should_include = false;
-                    }
-                }
+    let mut changed = false;
+    for (title, _, change) in &sections {
+        if change.is_empty() {
+            continue;
+        }
+        changed = true;
 
-                if let Some(since_ts) = since_timestamp {
-                    if let Some(log_time) = extract_timestamp_from_log(line) {
-                        if log_time < since_ts {
-                            should_include = false;
-                        }
-                    }
-                }
+        println!("{}:", title);
+        for item in &change.added {
+            println!("  \x1b[31m+ {}\x1b[0m", item);
+        }
+        for item in &change.removed {
+            println!("  \x1b[32m- {}\x1b[0m", item);
+        }
+    }
 
-                if let Some(ref filter_type) = event_type {
-                    if let Some(log_event_type) = extract_event_type_from_log(line) {
-                        if !log_event_type.to_lowercase().contains(&filter_type.to_lowercase()) {
-                            should_include = false;
-                        }
-                    }
-                }
+    if !changed {
+        println!("No changes");
+    }
 
-                if should_include {
-                    println!("{}", line);
-                    matches += 1;
-                }
-            }
+    Ok(())
+}
 
-            println!();
-            println!("Found {} matching events", matches);
-        }
-        Err(_) => {
-            println!("No event log found. Make sure the daemon is running and has generated events.");
+// --min-severity's value, case-insensitive; exits on a missing or unknown one
+fn parse_min_severity(value: Option<&String>) -> Severity {
+    match value.and_then(|name| Severity::from_name(name)) {
+        Some(severity) => severity,
+        None => {
+            eprintln!(
+                "Error: --min-severity requires one of: Low, Medium, High, Critical (got {})",
+                value.map_or("nothing".to_string(), |value| format!("'{}'", value))
+            );
+            std::process::exit(1);
         }
     }
+}
 
-    Ok(())
+// --severity-low and friends, kept as deprecated aliases of --min-severity
+fn deprecated_severity_flag(flag: &str) -> Option<Severity> {
+    let severity = Severity::from_name(flag.strip_prefix("--severity-")?)?;
+    eprintln!("Warning: {} is deprecated, use --min-severity {:?}", flag, severity);
+    Some(severity)
 }
 
+// Accepted by --since and --until
+const TIME_FORMAT_HINT: &str = "expected e.g. 30m, 24h, 7d, 2w, 2024-01-15 or 2024-01-15T08:00:00Z";
+
 // Helper functions for parsing
+// A time relative to now ("2h" ago), an RFC 3339 time or a date (midnight UTC).
+// Callers report None as an error rather than dropping the filter.
 fn parse_time_duration(time_str: &str) -> Option<chrono::DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(time_str) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(time_str, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc());
+    }
+
     let now = Utc::now();
 
     if time_str.ends_with('h') {
@@ -957,85 +2249,42 @@ fn parse_time_duration(time_str: &str) -> Option<chrono::DateTime<Utc>> {
         if let Ok(days) = time_str.trim_end_matches('d').parse::<i64>() {
             return Some(now - chrono::Duration::days(days));
         }
+    } else if time_str.ends_with('w') {
+        if let Ok(weeks) = time_str.trim_end_matches('w').parse::<i64>() {
+            return Some(now - chrono::Duration::weeks(weeks));
+        }
     }
 
     None
 }
 
-fn extract_event_type_from_log(line: &str) -> Option<String> {
-    if line.contains("Camera") || line.contains("camera") {
-        Some("CameraAccess".to_string())
-    } else if line.contains("Microphone") || line.contains("microphone") || line.contains("audio") {
-        Some("MicrophoneAccess".to_string())
-    } else if line.contains("SSH") || line.contains("ssh") {
-        Some("SshAccess".to_string())
-    } else if line.contains("USB") || line.contains("usb") {
-        Some("UsbDeviceInserted".to_string())
-    } else if line.contains("Port scan") || line.contains("port scan") {
-        Some("PortScanDetected".to_string())
-    } else if line.contains("Network discovery") || line.contains("network discovery") {
-        Some("NetworkDiscovery".to_string())
-    } else if line.contains("ICMP ping") || line.contains("ping") {
-        Some("PingDetected".to_string())
-    } else if line.contains("Network") || line.contains("network") {
-        Some("NetworkConnection".to_string())
-    } else {
-        Some("FileSystem".to_string())
-    }
-}
-
-fn extract_timestamp_from_log(line: &str) -> Option<chrono::DateTime<Utc>> {
-    if let Some(start) = line.find('[') {
-        if let Some(end) = line.find(']') {
-            let timestamp_str = &line[start+1..end];
-            if let Ok(timestamp) = chrono::DateTime::parse_from_str(timestamp_str, "%Y-%m-%d %H:%M:%S") {
-                return Some(timestamp.with_timezone(&Utc));
-            }
-        }
-    }
-    None
+fn get_storage_path_from_config() -> Option<String> {
+    client_config_value(|config| config.get("storage").and_then(|storage| storage.get("path")).and_then(|path| path.as_str()).map(str::to_string))
 }
 
-// Socket path resolution with priority: CLI argument > config file > default
-fn resolve_socket_path(cli_socket: Option<&String>) -> String {
-    // 1. Command line argument takes highest priority
-    if let Some(socket) = cli_socket {
-        return socket.clone();
-    }
-
-    // 2. Try to read from config file
-    if let Some(config_socket) = get_socket_from_config() {
-        return config_socket;
-    }
-
-    // 3. Default fallback
-    "/tmp/secmon.sock".to_string()
+// Auth token: --token, then [auth] token in the config file
+fn resolve_token(cli_token: Option<&String>) -> Option<String> {
+    cli_token.cloned().or_else(|| {
+        client_config_value(|config| config.get("auth").and_then(|auth| auth.get("token")).and_then(|token| token.as_str()).map(str::to_string))
+    })
 }
 
-fn get_socket_from_config() -> Option<String> {
-    let config_paths = [
-        "/etc/secmon/config.toml",
-        "./config.toml",
-        "config.toml"
-    ];
-
-    for config_path in &config_paths {
-        if let Ok(content) = std::fs::read_to_string(config_path) {
-            if let Ok(config) = toml::from_str::<Value>(&content) {
-                if let Some(socket_path) = config.get("socket_path") {
-                    if let Some(path_str) = socket_path.as_str() {
-                        return Some(path_str.to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    None
+// Where the TUI gets its events from
+enum TuiSource {
+    Socket {
+        path: String,
+        token: Option<String>,
+        reconnect: bool, // Retry with backoff when the daemon goes away, instead of exiting
+    },
+    File {
+        path: PathBuf,
+        since: Option<DateTime<Utc>>,
+        event_type: Option<String>,
+    },
 }
 
 // Terminal UI implementation
-async fn run_tui_with_socket(socket_path: &str) -> Result<()> {
+async fn run_tui(source: TuiSource) -> Result<()> {
     use crossterm::{
         event::{DisableMouseCapture, EnableMouseCapture},
         execute,
@@ -1055,34 +2304,74 @@ async fn run_tui_with_socket(socket_path: &str) -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state
+    let offline = matches!(source, TuiSource::File { .. });
     let mut app = App {
         events: Vec::new(),
         list_state: ratatui::widgets::ListState::default(),
         should_quit: false,
         connected: false,
+        offline,
+        reconnect: matches!(source, TuiSource::Socket { reconnect: true, .. }),
         _error_message: None,
-        auto_scroll: true,
+        auto_scroll: !offline, // Recorded sessions are read from the top
         show_details: false,
         selected_event_details: None,
+        filter_text: String::new(),
+        filter_input: false,
+        min_severity: None,
+        severity_counts: [0; 4],
+        type_counts: HashMap::new(),
+        paused: false,
+        paused_new: 0,
     };
 
     // Create channels for events and connection status
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<SecurityEvent>();
     let (status_tx, mut status_rx) = tokio::sync::mpsc::unbounded_channel::<bool>();
 
+    let (socket_path, token, reconnect) = match source {
+        TuiSource::Socket { path, token, reconnect } => (path, token, reconnect),
+        TuiSource::File { path, since, event_type } => {
+            // Stream the file in the background so large logs don't block startup
+            tokio::spawn(async move {
+                if let Err(e) = load_events_from_file(event_tx, status_tx, &path, since, event_type).await {
+                    error!("Failed to load events from {}: {}", path.display(), e);
+                }
+            });
+
+            let res = run_tui_loop(&mut terminal, &mut app, &mut event_rx, &mut status_rx).await;
+
+            disable_raw_mode()?;
+            execute!(
+                terminal.backend_mut(),
+                LeaveAlternateScreen,
+                DisableMouseCapture
+            )?;
+            terminal.show_cursor()?;
+
+            return res;
+        }
+    };
+
     // Spawn task to connect to daemon and receive events
     let event_task = {
         let event_tx_clone = event_tx.clone();
         let status_tx_clone = status_tx.clone();
         let socket_path = socket_path.to_string();
         tokio::spawn(async move {
-            let status_tx_for_error = status_tx_clone.clone();
-            match connect_and_receive_events_with_status(event_tx_clone, status_tx_clone, &socket_path).await {
-                Ok(_) => {},
-                Err(e) => {
-                    error!("Failed to connect to daemon: {}", e);
-                    let _ = status_tx_for_error.send(false);
+            let mut backoff = ReconnectBackoff::new();
+            loop {
+                match connect_and_receive_events_with_status(event_tx_clone.clone(), status_tx_clone.clone(), &socket_path, token.as_deref()).await {
+                    Ok(_) => backoff.reset(), // Connected, then the daemon closed the connection
+                    Err(e) => {
+                        error!("Failed to connect to daemon: {}", e);
+                        let _ = status_tx_clone.send(false);
+                    }
+                }
+                if !reconnect || event_tx_clone.is_closed() {
+                    break;
                 }
+                tokio::time::sleep(backoff.next_delay()).await;
             }
         })
     };
@@ -1105,13 +2394,58 @@ async fn run_tui_with_socket(socket_path: &str) -> Result<()> {
     res
 }
 
+// Offline TUI source: `status_tx` reports `true` once the whole file has been read
+async fn load_events_from_file(
+    event_tx: tokio::sync::mpsc::UnboundedSender<SecurityEvent>,
+    status_tx: tokio::sync::mpsc::UnboundedSender<bool>,
+    path: &std::path::Path,
+    since: Option<DateTime<Utc>>,
+    event_type: Option<String>,
+) -> Result<()> {
+    let file = tokio::fs::File::open(path).await
+        .with_context(|| format!("Failed to open event file: {}", path.display()))?;
+
+    let type_filter = event_type.map(|t| t.to_lowercase());
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            break;
+        }
+
+        let Ok(event) = serde_json::from_str::<SecurityEvent>(line.trim()) else {
+            continue;
+        };
+
+        if since.is_some_and(|since| event.timestamp < since) {
+            continue;
+        }
+        if let Some(ref filter_type) = type_filter {
+            if !format!("{:?}", event.event_type).to_lowercase().contains(filter_type) {
+                continue;
+            }
+        }
+
+        if event_tx.send(event).is_err() {
+            return Ok(()); // TUI closed
+        }
+    }
+
+    let _ = status_tx.send(true);
+    Ok(())
+}
+
 async fn connect_and_receive_events_with_status(
     event_tx: tokio::sync::mpsc::UnboundedSender<SecurityEvent>,
     status_tx: tokio::sync::mpsc::UnboundedSender<bool>,
-    socket_path: &str
+    socket_path: &str,
+    token: Option<&str>,
 ) -> Result<()> {
-    let stream = UnixStream::connect(socket_path).await
+    let mut stream = UnixStream::connect(socket_path).await
         .with_context(|| format!("Failed to connect to socket: {}", socket_path))?;
+    send_auth(&mut stream, token).await?;
 
     // Send connection success status immediately
     let _ = status_tx.send(true);
@@ -1128,7 +2462,11 @@ async fn connect_and_receive_events_with_status(
                 break;
             }
             Ok(_) => {
-                if let Ok(event) = serde_json::from_str::<SecurityEvent>(&line.trim()) {
+                if is_auth_rejection(line.trim()) {
+                    let _ = status_tx.send(false);
+                    anyhow::bail!("Daemon rejected the auth token (set --token or [auth] token)");
+                }
+                if let Ok(event) = serde_json::from_str::<SecurityEvent>(line.trim()) {
                     if event_tx.send(event).is_err() {
                         break; // Receiver dropped
                     }
@@ -1149,11 +2487,89 @@ struct App {
     events: Vec<SecurityEvent>,
     list_state: ratatui::widgets::ListState,
     should_quit: bool,
-    connected: bool,
+    connected: bool, // In offline mode: the file has been fully loaded
+    offline: bool,
+    reconnect: bool,
     _error_message: Option<String>,
     auto_scroll: bool,
     show_details: bool,
     selected_event_details: Option<String>,
+    filter_text: String, // `/` filter: case-insensitive substring of path or description
+    filter_input: bool,  // Keys go to filter_text while the filter is being typed
+    min_severity: Option<Severity>, // Keys 1-4
+    // Session totals, unaffected by the 1000-event cap and by `c`
+    severity_counts: [u64; 4], // Indexed by Severity::level() - 1
+    type_counts: HashMap<&'static str, u64>,
+    paused: bool,      // `p`: keep the selection and viewport still while events keep arriving
+    paused_new: usize, // Events received since pausing
+}
+
+impl App {
+    fn count_event(&mut self, event: &SecurityEvent) {
+        self.severity_counts[event.details.severity.level() as usize - 1] += 1;
+        *self.type_counts.entry(event.event_type.name()).or_insert(0) += 1;
+    }
+
+    /// The most frequent event types this session, most frequent first
+    fn top_event_types(&self, count: usize) -> Vec<(&'static str, u64)> {
+        let mut types: Vec<(&'static str, u64)> = self.type_counts.iter().map(|(name, n)| (*name, *n)).collect();
+        types.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        types.truncate(count);
+        types
+    }
+
+    fn filter_active(&self) -> bool {
+        !self.filter_text.is_empty() || self.min_severity.is_some()
+    }
+
+    fn matches_filter(&self, event: &SecurityEvent) -> bool {
+        if self.min_severity.as_ref().is_some_and(|min| event.details.severity.level() < min.level()) {
+            return false;
+        }
+        if self.filter_text.is_empty() {
+            return true;
+        }
+        let needle = self.filter_text.to_lowercase();
+        event.path.to_string_lossy().to_lowercase().contains(&needle)
+            || event.details.description.to_lowercase().contains(&needle)
+    }
+
+    /// Indices into `events` of the events shown; the list selection indexes this
+    fn visible_events(&self) -> Vec<usize> {
+        (0..self.events.len()).filter(|&i| self.matches_filter(&self.events[i])).collect()
+    }
+
+    /// Keep the selection on screen after the filter changes
+    fn refresh_selection(&mut self) {
+        let visible = self.visible_events().len();
+        if visible == 0 {
+            self.list_state.select(None);
+        } else if self.auto_scroll {
+            self.list_state.select(Some(visible - 1));
+        } else {
+            let selected = self.list_state.selected().unwrap_or(0).min(visible - 1);
+            self.list_state.select(Some(selected));
+        }
+    }
+}
+
+/// Keys while the `/` filter is being typed: Enter keeps the filter, Esc clears it
+fn handle_filter_input(app: &mut App, code: crossterm::event::KeyCode) {
+    use crossterm::event::KeyCode;
+
+    match code {
+        KeyCode::Enter => app.filter_input = false,
+        KeyCode::Esc => {
+            app.filter_input = false;
+            app.filter_text.clear();
+        }
+        KeyCode::Backspace => {
+            app.filter_text.pop();
+        }
+        KeyCode::Char(c) => app.filter_text.push(c),
+        _ => return,
+    }
+    app.refresh_selection();
 }
 
 async fn run_tui_loop<B>(
@@ -1176,8 +2592,16 @@ where
         let timeout = Duration::from_millis(100);
         if crossterm::event::poll(timeout)? {
             if let Event::Key(key) = crossterm::event::read()? {
-                if key.kind == KeyEventKind::Press {
+                if key.kind == KeyEventKind::Press && app.filter_input {
+                    handle_filter_input(app, key.code);
+                } else if key.kind == KeyEventKind::Press {
+                    let visible = app.visible_events();
                     match key.code {
+                        KeyCode::Esc if !app.show_details && app.filter_active() => {
+                            app.filter_text.clear();
+                            app.min_severity = None;
+                            app.refresh_selection();
+                        }
                         KeyCode::Char('q') | KeyCode::Esc => {
                             if app.show_details {
                                 app.show_details = false;
@@ -1186,37 +2610,45 @@ where
                                 app.should_quit = true;
                             }
                         }
-                        KeyCode::Down | KeyCode::Char('j') => {
-                            if !app.show_details && !app.events.is_empty() {
-                                app.auto_scroll = false; // Disable auto-scroll when manually navigating
-                                let i = match app.list_state.selected() {
-                                    Some(i) => {
-                                        if i >= app.events.len() - 1 {
-                                            0
-                                        } else {
-                                            i + 1
-                                        }
+                        KeyCode::Char('/') if !app.show_details => {
+                            app.filter_input = true;
+                        }
+                        KeyCode::Char(digit @ '1'..='4') if !app.show_details => {
+                            app.min_severity = match digit {
+                                '1' => None, // Low is everything
+                                '2' => Some(Severity::Medium),
+                                '3' => Some(Severity::High),
+                                _ => Some(Severity::Critical),
+                            };
+                            app.refresh_selection();
+                        }
+                        KeyCode::Down | KeyCode::Char('j') if !app.show_details && !visible.is_empty() => {
+                            app.auto_scroll = false; // Disable auto-scroll when manually navigating
+                            let i = match app.list_state.selected() {
+                                Some(i) => {
+                                    if i >= visible.len() - 1 {
+                                        0
+                                    } else {
+                                        i + 1
                                     }
-                                    None => 0,
-                                };
-                                app.list_state.select(Some(i));
-                            }
+                                }
+                                None => 0,
+                            };
+                            app.list_state.select(Some(i));
                         }
-                        KeyCode::Up | KeyCode::Char('k') => {
-                            if !app.show_details && !app.events.is_empty() {
-                                app.auto_scroll = false; // Disable auto-scroll when manually navigating
-                                let i = match app.list_state.selected() {
-                                    Some(i) => {
-                                        if i == 0 {
-                                            app.events.len() - 1
-                                        } else {
-                                            i - 1
-                                        }
+                        KeyCode::Up | KeyCode::Char('k') if !app.show_details && !visible.is_empty() => {
+                            app.auto_scroll = false; // Disable auto-scroll when manually navigating
+                            let i = match app.list_state.selected() {
+                                Some(i) => {
+                                    if i == 0 {
+                                        visible.len() - 1
+                                    } else {
+                                        i - 1
                                     }
-                                    None => 0,
-                                };
-                                app.list_state.select(Some(i));
-                            }
+                                }
+                                None => 0,
+                            };
+                            app.list_state.select(Some(i));
                         }
                         KeyCode::Char('c') => {
                             app.events.clear();
@@ -1226,18 +2658,26 @@ where
                             app.selected_event_details = None;
                         }
                         KeyCode::Char(' ') => {
-                            if let Some(selected_index) = app.list_state.selected() {
-                                if selected_index < app.events.len() {
-                                    let event = &app.events[selected_index];
-                                    app.selected_event_details = Some(format_event_details(event));
-                                    app.show_details = true;
+                            if let Some(&index) = app.list_state.selected().and_then(|selected| visible.get(selected)) {
+                                app.selected_event_details = Some(format_event_details(&app.events[index]));
+                                app.show_details = true;
+                            }
+                        }
+                        KeyCode::Char('p') if !app.offline => {
+                            app.paused = !app.paused;
+                            app.paused_new = 0;
+                            if !app.paused {
+                                // Resume following the newest event
+                                app.auto_scroll = true;
+                                if !visible.is_empty() {
+                                    app.list_state.select(Some(visible.len() - 1));
                                 }
                             }
                         }
                         KeyCode::Char('a') => {
                             app.auto_scroll = !app.auto_scroll;
-                            if app.auto_scroll && !app.events.is_empty() {
-                                app.list_state.select(Some(app.events.len() - 1));
+                            if app.auto_scroll && !visible.is_empty() {
+                                app.list_state.select(Some(visible.len() - 1));
                             }
                         }
                         _ => {}
@@ -1252,23 +2692,37 @@ where
         }
 
         // Check for new events from daemon
+        let mut received = false;
         while let Ok(event) = event_rx.try_recv() {
+            received = true;
+            app.count_event(&event);
+            if app.paused {
+                app.paused_new += 1;
+            }
             app.events.push(event);
-            // Keep only last 1000 events
-            if app.events.len() > 1000 {
-                app.events.remove(0);
-                // Adjust selected index if needed
-                if let Some(selected) = app.list_state.selected() {
-                    if selected > 0 {
-                        app.list_state.select(Some(selected - 1));
+            // Keep only last 1000 events (a recorded session is kept whole)
+            if !app.offline && app.events.len() > 1000 {
+                let removed = app.events.remove(0);
+                // Adjust selected index and scroll position if the removed event was in the
+                // (filtered) list, so the same events stay on screen
+                if app.matches_filter(&removed) {
+                    if let Some(selected) = app.list_state.selected() {
+                        if selected > 0 {
+                            app.list_state.select(Some(selected - 1));
+                        }
                     }
+                    let offset = app.list_state.offset_mut();
+                    *offset = offset.saturating_sub(1);
                 }
             }
+        }
 
+        if received && !app.paused {
+            let visible = app.visible_events().len();
             // Auto-scroll behavior: always select newest event if auto-scroll is on
-            if app.auto_scroll && !app.events.is_empty() {
-                app.list_state.select(Some(app.events.len() - 1));
-            } else if app.list_state.selected().is_none() && !app.events.is_empty() {
+            if app.auto_scroll && visible > 0 {
+                app.list_state.select(Some(visible - 1));
+            } else if app.list_state.selected().is_none() && visible > 0 {
                 // Select first event if nothing is selected and we have events
                 app.list_state.select(Some(0));
             }
@@ -1286,8 +2740,8 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
     use ratatui::{
         layout::{Constraint, Direction, Layout},
         style::{Color, Modifier, Style},
-        text::{Line, Span, Text},
-        widgets::{Block, Borders, List, ListItem, Paragraph, Wrap, Clear},
+        text::{Line, Span},
+        widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
     };
 
     if app.show_details {
@@ -1302,6 +2756,7 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
         .constraints([
             Constraint::Length(3),
             Constraint::Min(0),
+            Constraint::Length(3),
             Constraint::Length(4),
         ])
         .split(f.size());
@@ -1313,11 +2768,11 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
     f.render_widget(header, chunks[0]);
 
     // Event list
-    let events: Vec<ListItem> = app
-        .events
+    let visible = app.visible_events();
+    let events: Vec<ListItem> = visible
         .iter()
-        .enumerate()
-        .map(|(_i, event)| {
+        .map(|&index| {
+            let event = &app.events[index];
             let severity_color = match event.details.severity {
                 Severity::Low => Color::Green,
                 Severity::Medium => Color::Yellow,
@@ -1347,47 +2802,102 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
         })
         .collect();
 
+    let mut title = "Security Events".to_string();
+    if app.filter_active() {
+        title.push_str(&format!(" ({} of {}", visible.len(), app.events.len()));
+        if !app.filter_text.is_empty() {
+            title.push_str(&format!(", matching \"{}\"", app.filter_text));
+        }
+        if let Some(min) = &app.min_severity {
+            title.push_str(&format!(", {:?} and above", min));
+        }
+        title.push(')');
+    }
+
     let event_list = List::new(events)
-        .block(Block::default().borders(Borders::ALL).title("Security Events"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(Style::default().bg(Color::DarkGray))
         .highlight_symbol("> ");
 
     f.render_stateful_widget(event_list, chunks[1], &mut app.list_state);
 
-    // Footer with controls (now takes 4 lines)
-    let status = if app.connected {
-        "🟢 Connected to daemon"
+    // Footer with controls (4 lines), below the session summary
+    let status = if app.offline {
+        if app.connected {
+            format!("📁 offline: {} events", app.events.len())
+        } else {
+            format!("📁 offline: loading {} events...", app.events.len())
+        }
+    } else if app.connected {
+        "🟢 Connected to daemon".to_string()
     } else if app.events.is_empty() {
-        "🟡 Connecting to daemon..."
+        "🟡 Connecting to daemon...".to_string()
+    } else if app.reconnect {
+        "🔴 Disconnected from daemon, reconnecting...".to_string()
     } else {
-        "🔴 Disconnected from daemon"
+        "🔴 Disconnected from daemon".to_string()
     };
 
-    let scroll_status = if app.auto_scroll {
-        "🔄 Auto-scroll: ON"
+    let scroll_status = if app.paused {
+        format!("⏸️ PAUSED ({} new)", app.paused_new)
+    } else if app.auto_scroll {
+        "🔄 Auto-scroll: ON".to_string()
     } else {
-        "⏸️ Auto-scroll: OFF"
+        "⏸️ Auto-scroll: OFF".to_string()
+    };
+
+    let controls = if app.filter_input {
+        format!("Filter: {}_  (Enter=apply, Esc=clear)", app.filter_text)
+    } else {
+        "Controls: j/k=navigate, space=details, /=filter, 1-4=min severity, Esc=clear filter, p=pause, c=clear, a=toggle auto-scroll, q=quit".to_string()
     };
 
     let footer_text = format!(
-        "{} | Events: {} | {}\nControls: j/k=navigate, space=details, c=clear, a=toggle auto-scroll, q=quit",
+        "{} | Events: {} | {}\n{}",
         status,
         app.events.len(),
-        scroll_status
+        scroll_status,
+        controls
     );
 
+    // Session counters by severity, then the top event types
+    let mut summary = vec![Span::styled("Session: ", Style::default().fg(Color::Gray))];
+    for (label, count, color) in [
+        ("Low", app.severity_counts[0], Color::Green),
+        ("Med", app.severity_counts[1], Color::Yellow),
+        ("High", app.severity_counts[2], Color::Red),
+        ("Crit", app.severity_counts[3], Color::Magenta),
+    ] {
+        summary.push(Span::styled(
+            format!("{} {}", label, count),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+        summary.push(Span::raw("  "));
+    }
+    let top_types = app.top_event_types(4);
+    if !top_types.is_empty() {
+        summary.push(Span::styled("| Top: ", Style::default().fg(Color::Gray)));
+        let top_types = top_types.iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        summary.push(Span::styled(top_types, Style::default().fg(Color::Blue)));
+    }
+    let summary_bar = Paragraph::new(Line::from(summary))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(summary_bar, chunks[2]);
+
     let footer = Paragraph::new(footer_text)
         .style(Style::default().fg(Color::White))
         .block(Block::default().borders(Borders::ALL))
         .wrap(Wrap { trim: true });
-    f.render_widget(footer, chunks[2]);
+    f.render_widget(footer, chunks[3]);
 }
 
 fn render_details_view(f: &mut ratatui::Frame, app: &mut App) {
     use ratatui::{
         layout::{Constraint, Direction, Layout, Alignment},
         style::{Color, Modifier, Style},
-        text::{Line, Span, Text},
         widgets::{Block, Borders, Paragraph, Wrap, Clear},
     };
 
@@ -1456,6 +2966,7 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: ratatui::layout::Rect) -> ra
 fn format_event_details(event: &SecurityEvent) -> String {
     let mut details = String::new();
 
+    details.push_str(&format!("Event ID: {}\n", event.id));
     details.push_str(&format!("Timestamp: {}\n", format_timestamp(&event.timestamp, "%Y-%m-%d %H:%M:%S")));
     details.push_str(&format!("Event Type: {:?}\n", event.event_type));
     details.push_str(&format!("Path: {}\n", event.path.display()));
@@ -1475,11 +2986,19 @@ fn format_event_details(event: &SecurityEvent) -> String {
 
     // Add event category
     let category = match event.event_type {
-        EventType::FileAccess | EventType::FileModify | EventType::FileCreate | EventType::FileDelete | EventType::DirectoryAccess => "Filesystem",
-        EventType::CameraAccess | EventType::MicrophoneAccess => "Privacy",
+        EventType::FileAccess | EventType::FileModify | EventType::FileCreate | EventType::FileDelete | EventType::FileMove | EventType::DirectoryCreate | EventType::DirectoryAccess => "Filesystem",
+        EventType::CameraAccess | EventType::MicrophoneAccess | EventType::AvCaptureCorrelated => "Privacy",
         EventType::SshAccess | EventType::NetworkConnection | EventType::NetworkDiscovery | EventType::PingDetected => "Network",
+        EventType::PortOpened | EventType::PortClosed => "Network",
         EventType::PortScanDetected => "Security",
+        EventType::SynFloodDetected => "Security",
+        EventType::ConnectionFlood => "Security",
         EventType::UsbDeviceInserted => "Hardware",
+        EventType::UsbDeviceRemoved => "Hardware",
+        EventType::UsbDeviceBlocked => "Hardware",
+        EventType::StorageMounted | EventType::StorageUnmounted => "Hardware",
+        EventType::MonitorOverflow | EventType::MonitorDegraded => "Monitor",
+        EventType::DaemonStarted | EventType::DaemonStopped => "Monitor",
         EventType::CustomMessage => "Custom",
     };
     details.push_str(&format!("Category: {}\n", category));
@@ -1490,6 +3009,7 @@ fn format_event_details(event: &SecurityEvent) -> String {
 fn handle_json_event_listen(event: &SecurityEvent) {
     // Output raw JSON with additional metadata for streaming (no notifications)
     let json_event = serde_json::json!({
+        "id": event.id,
         "timestamp": event.timestamp,
         "event_type": event.event_type,
         "path": event.path,
@@ -1505,16 +3025,29 @@ fn handle_json_event_listen(event: &SecurityEvent) {
             Severity::Critical => 4,
         },
         "event_category": match event.event_type {
-            EventType::FileAccess | EventType::FileModify | EventType::FileCreate | EventType::FileDelete => "filesystem",
+            EventType::FileAccess | EventType::FileModify | EventType::FileCreate | EventType::FileDelete | EventType::FileMove | EventType::DirectoryCreate => "filesystem",
             EventType::DirectoryAccess => "filesystem",
             EventType::CameraAccess => "privacy",
             EventType::MicrophoneAccess => "privacy",
+            EventType::AvCaptureCorrelated => "privacy",
             EventType::SshAccess => "network",
             EventType::NetworkConnection => "network",
+            EventType::PortOpened => "network",
+            EventType::PortClosed => "network",
             EventType::NetworkDiscovery => "network",
             EventType::PingDetected => "network",
             EventType::PortScanDetected => "security",
+            EventType::SynFloodDetected => "security",
+            EventType::ConnectionFlood => "security",
             EventType::UsbDeviceInserted => "hardware",
+            EventType::UsbDeviceRemoved => "hardware",
+            EventType::UsbDeviceBlocked => "hardware",
+            EventType::StorageMounted => "hardware",
+            EventType::StorageUnmounted => "hardware",
+            EventType::MonitorOverflow => "monitor",
+            EventType::MonitorDegraded => "monitor",
+            EventType::DaemonStarted => "monitor",
+            EventType::DaemonStopped => "monitor",
             EventType::CustomMessage => "custom",
         }
     });
@@ -1551,6 +3084,7 @@ fn handle_security_event_listen(event: &SecurityEvent) {
 fn handle_json_event(event: &SecurityEvent) {
     // Output raw JSON with additional metadata for streaming
     let json_event = serde_json::json!({
+        "id": event.id,
         "timestamp": event.timestamp,
         "event_type": event.event_type,
         "path": event.path,
@@ -1566,28 +3100,45 @@ fn handle_json_event(event: &SecurityEvent) {
             Severity::Critical => 4,
         },
         "event_category": match event.event_type {
-            EventType::FileAccess | EventType::FileModify | EventType::FileCreate | EventType::FileDelete => "filesystem",
+            EventType::FileAccess | EventType::FileModify | EventType::FileCreate | EventType::FileDelete | EventType::FileMove | EventType::DirectoryCreate => "filesystem",
             EventType::DirectoryAccess => "filesystem",
             EventType::CameraAccess => "privacy",
             EventType::MicrophoneAccess => "privacy",
+            EventType::AvCaptureCorrelated => "privacy",
             EventType::SshAccess => "network",
             EventType::NetworkConnection => "network",
+            EventType::PortOpened => "network",
+            EventType::PortClosed => "network",
             EventType::NetworkDiscovery => "network",
             EventType::PingDetected => "network",
             EventType::PortScanDetected => "security",
+            EventType::SynFloodDetected => "security",
+            EventType::ConnectionFlood => "security",
             EventType::UsbDeviceInserted => "hardware",
+            EventType::UsbDeviceRemoved => "hardware",
+            EventType::UsbDeviceBlocked => "hardware",
+            EventType::StorageMounted => "hardware",
+            EventType::StorageUnmounted => "hardware",
+            EventType::MonitorOverflow => "monitor",
+            EventType::MonitorDegraded => "monitor",
+            EventType::DaemonStarted => "monitor",
+            EventType::DaemonStopped => "monitor",
             EventType::CustomMessage => "custom",
         }
     });
 
     println!("{}", json_event);
+    alert_critical_event(event);
+}
 
-    // Still log critical events to alert file in JSON mode
+/// Machine-readable output modes skip the console warnings but still log critical events
+/// to the alert file
+fn alert_critical_event(event: &SecurityEvent) {
     match (&event.event_type, &event.details.severity) {
         (EventType::CameraAccess, _) |
         (EventType::MicrophoneAccess, _) |
         (_, Severity::Critical) => {
-            send_alert(&event);
+            send_alert(event);
         }
         _ => {}
     }
@@ -1618,17 +3169,28 @@ fn handle_security_event(event: &SecurityEvent) {
 
     // Take actions based on event type and severity
     match (&event.event_type, &event.details.severity) {
+        (EventType::AvCaptureCorrelated, _) => {
+            warn!("🎥🎤 CAMERA AND MICROPHONE IN USE TOGETHER: {}", event.details.description);
+            send_alert(event);
+        }
         (EventType::CameraAccess, _) => {
             warn!("🎥 CAMERA ACCESS DETECTED: {}", event.details.description);
-            send_alert(&event);
+            send_alert(event);
         }
         (EventType::MicrophoneAccess, _) => {
             warn!("🎤 MICROPHONE ACCESS DETECTED: {}", event.details.description);
-            send_alert(&event);
+            send_alert(event);
         }
         (EventType::UsbDeviceInserted, Severity::Critical) => {
             warn!("🚨 SUSPICIOUS USB DEVICE: {}", event.details.description);
-            send_alert(&event);
+            send_alert(event);
+        }
+        (EventType::UsbDeviceBlocked, _) => {
+            warn!("⛔ USB DEVICE BLOCKED: {}", event.details.description);
+            send_alert(event);
+        }
+        (EventType::StorageMounted, _) => {
+            warn!("💾 USB STORAGE MOUNTED: {}", event.details.description);
         }
         (EventType::UsbDeviceInserted, Severity::High) => {
             warn!("🔌 HIGH-RISK USB DEVICE: {}", event.details.description);
@@ -1636,23 +3198,40 @@ fn handle_security_event(event: &SecurityEvent) {
         (EventType::NetworkConnection, Severity::High) => {
             warn!("🌐 SUSPICIOUS NETWORK CONNECTION: {}", event.details.description);
         }
+        (EventType::PortOpened, Severity::High) => {
+            warn!("👂 NEW LISTENING PORT: {}", event.details.description);
+        }
         (EventType::PortScanDetected, _) => {
             warn!("🚨 PORT SCAN DETECTED: {}", event.details.description);
-            send_alert(&event);
+            send_alert(event);
+        }
+        (EventType::SynFloodDetected, _) => {
+            warn!("🌊 SYN FLOOD DETECTED: {}", event.details.description);
+            send_alert(event);
+        }
+        (EventType::ConnectionFlood, _) => {
+            warn!("🚰 CONNECTION FLOOD DETECTED: {}", event.details.description);
+            send_alert(event);
         }
         (EventType::NetworkDiscovery, _) => {
             warn!("🔍 NETWORK DISCOVERY DETECTED: {}", event.details.description);
         }
+        (EventType::MonitorDegraded, _) => {
+            warn!("🩺 MONITORING DEGRADED: {}", event.details.description);
+        }
+        (EventType::DaemonStarted | EventType::DaemonStopped, _) => {
+            info!("🛡️  {}", event.details.description);
+        }
         (EventType::PingDetected, Severity::Medium | Severity::High) => {
             warn!("📡 SUSPICIOUS PING ACTIVITY: {}", event.details.description);
         }
         (_, Severity::Critical) => {
             warn!("🚨 CRITICAL SECURITY EVENT: {}", event.details.description);
-            send_alert(&event);
+            send_alert(event);
         }
-        (EventType::CustomMessage, Severity::High | Severity::Critical) => {
+        (EventType::CustomMessage, Severity::High) => {
             warn!("📢 CUSTOM ALERT: {}", event.details.description);
-            send_alert(&event);
+            send_alert(event);
         }
         (EventType::CustomMessage, Severity::Medium) => {
             info!("📢 Custom message: {}", event.details.description);
@@ -1664,36 +3243,164 @@ fn handle_security_event(event: &SecurityEvent) {
     }
 }
 
+// A line of the alert log. The timestamp carries its offset, so it can be pasted into --since
+fn alert_log_line(event: &SecurityEvent) -> String {
+    format!("[{}] CRITICAL: {} - {}\n",
+        format_timestamp(&event.timestamp, "%Y-%m-%dT%H:%M:%S%:z"),
+        event.path.display(),
+        event.details.description
+    )
+}
+
 fn send_alert(event: &SecurityEvent) {
     // Log critical events to a separate file
-    if let Err(e) = std::fs::OpenOptions::new()
+    let line = alert_log_line(event);
+    if let Err(e) = write_alert_log(&line) {
+        error!("Failed to write alert log {}: {}", ALERT_LOG_SETTINGS.path.display(), e);
+    }
+
+    // Check if we should send a desktop notification (with cooldown and rate limiting)
+    let settings = &*NOTIFICATION_SETTINGS;
+    if settings.enabled
+        && event.details.severity.level() >= settings.min_severity.level()
+        && should_send_notification(event)
+    {
+        send_notification(settings, "Security Alert", &format!("Critical event: {}", event.details.description));
+    }
+}
+
+fn send_notification(settings: &NotificationSettings, summary: &str, body: &str) {
+    #[cfg(feature = "dbus")]
+    if settings.dbus_enabled {
+        match send_dbus_notification(summary, body, settings.timeout_ms) {
+            Ok(()) => return,
+            Err(e) => {
+                warn!("D-Bus notification failed, falling back to {}: {}", settings.command, e);
+                *NOTIFICATION_BUS.lock().unwrap() = None;
+            }
+        }
+    }
+
+    let _ = std::process::Command::new(&settings.command)
+        .arg(summary)
+        .arg(body)
+        .arg("--urgency=critical")
+        .arg(format!("--expire-time={}", settings.timeout_ms))
+        .spawn();
+}
+
+// org.freedesktop.Notifications.Notify on the session bus, as notify-send does
+#[cfg(feature = "dbus")]
+fn send_dbus_notification(summary: &str, body: &str, timeout_ms: u32) -> zbus::Result<()> {
+    let mut bus = NOTIFICATION_BUS.lock().unwrap();
+    let connection = match bus.as_ref() {
+        Some(connection) => connection,
+        None => bus.insert(zbus::blocking::Connection::session()?),
+    };
+
+    let hints = HashMap::from([("urgency", zbus::zvariant::Value::U8(2))]); // critical
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &("secmon", 0u32, "dialog-warning", summary, body, Vec::<&str>::new(), hints, timeout_ms as i32),
+    )?;
+    Ok(())
+}
+
+fn write_alert_log(line: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let settings = &*ALERT_LOG_SETTINGS;
+    let mut state = ALERT_LOG_STATE.lock().unwrap();
+
+    if settings.max_bytes > 0 {
+        if state.checked_at.is_none_or(|at| at.elapsed() >= ALERT_LOG_STAT_INTERVAL) {
+            state.size = std::fs::metadata(&settings.path).map(|m| m.len()).unwrap_or(0);
+            state.checked_at = Some(Instant::now());
+        }
+        if state.size + line.len() as u64 > settings.max_bytes && state.size > 0 {
+            rotate_alert_log(settings)?;
+            state.size = 0;
+        }
+    }
+
+    std::fs::OpenOptions::new()
         .create(true)
         .append(true)
-        .open("/tmp/secmon-alerts.log")
-        .and_then(|mut file| {
-            use std::io::Write;
-            writeln!(file, "[{}] CRITICAL: {} - {}",
-                format_timestamp(&event.timestamp, "%Y-%m-%d %H:%M:%S"),
-                event.path.display(),
-                event.details.description
-            )
-        })
-    {
-        error!("Failed to write alert log: {}", e);
+        .open(&settings.path)?
+        .write_all(line.as_bytes())?;
+    state.size += line.len() as u64;
+    Ok(())
+}
+
+// path.(keep-1) -> path.keep, ..., path -> path.1; the oldest falls off the end
+fn rotate_alert_log(settings: &AlertLogSettings) -> std::io::Result<()> {
+    let rotated = |n: usize| {
+        let mut name = settings.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+
+    if settings.keep == 0 {
+        return std::fs::remove_file(&settings.path);
     }
 
-    // Check if we should send a desktop notification (with cooldown and rate limiting)
-    if should_send_notification(event) {
-        let _ = std::process::Command::new("notify-send")
-            .arg("Security Alert")
-            .arg(format!("Critical event: {}", event.details.description))
-            .arg("--urgency=critical")
-            .spawn();
+    let _ = std::fs::remove_file(rotated(settings.keep));
+    for n in (1..settings.keep).rev() {
+        let from = rotated(n);
+        if from.exists() {
+            std::fs::rename(&from, rotated(n + 1))?;
+        }
+    }
+    std::fs::rename(&settings.path, rotated(1))
+}
+
+// Cooldowns are stored as wall-clock times so they survive a restart of the client
+fn load_notification_cooldowns() -> HashMap<String, DateTime<Utc>> {
+    let Some(state_file) = NOTIFICATION_STATE_FILE.as_ref() else {
+        return HashMap::new();
+    };
+
+    let mut cooldowns: HashMap<String, DateTime<Utc>> = match std::fs::read_to_string(state_file) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable notification state {}: {}", state_file.display(), e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    };
+
+    let now = Utc::now();
+    cooldowns.retain(|_, last_time| {
+        (now - *last_time).to_std().map(|age| age < NOTIFICATION_COOLDOWN_RETENTION).unwrap_or(true)
+    });
+    cooldowns
+}
+
+fn save_notification_cooldowns(cooldowns: &HashMap<String, DateTime<Utc>>) {
+    let Some(state_file) = NOTIFICATION_STATE_FILE.as_ref() else {
+        return;
+    };
+
+    // Write to a sibling file and rename so a crash never leaves a truncated state file
+    let tmp_file = state_file.with_extension("tmp");
+    let result = serde_json::to_string(cooldowns)
+        .map_err(std::io::Error::other)
+        .and_then(|json| std::fs::write(&tmp_file, json))
+        .and_then(|_| std::fs::rename(&tmp_file, state_file));
+
+    if let Err(e) = result {
+        warn!("Failed to save notification state to {}: {}", state_file.display(), e);
     }
 }
 
 fn should_send_notification(event: &SecurityEvent) -> bool {
     let now = Instant::now();
+    let wall_now = Utc::now();
+    let settings = &*NOTIFICATION_SETTINGS;
+    // Entries must outlive the longest cooldown, or it would end early
+    let retention = NOTIFICATION_COOLDOWN_RETENTION.max(settings.cooldown).max(settings.device_cooldown);
 
     // Create a cooldown key based on event type and path
     let cooldown_key = format!("{:?}:{}", event.event_type, event.path.display());
@@ -1702,35 +3409,39 @@ fn should_send_notification(event: &SecurityEvent) -> bool {
     {
         let mut cooldowns = NOTIFICATION_COOLDOWNS.lock().unwrap();
 
-        // Clean up old entries (older than 5 minutes)
-        cooldowns.retain(|_, &mut last_time| now.duration_since(last_time) < Duration::from_secs(300));
+        // Clean up old entries (older than 5 minutes, or the longest cooldown)
+        cooldowns.retain(|_, last_time| {
+            (wall_now - *last_time).to_std().map(|age| age < retention).unwrap_or(true)
+        });
 
         // Check if we're still in cooldown for this specific event
         if let Some(&last_notification) = cooldowns.get(&cooldown_key) {
             let cooldown_duration = match event.event_type {
-                EventType::MicrophoneAccess => Duration::from_secs(60), // 1 minute for microphone
-                EventType::CameraAccess => Duration::from_secs(60),     // 1 minute for camera
-                _ => Duration::from_secs(30),                           // 30 seconds for others
+                EventType::MicrophoneAccess | EventType::CameraAccess => settings.device_cooldown,
+                _ => settings.cooldown,
             };
 
-            if now.duration_since(last_notification) < cooldown_duration {
+            // A clock that went backwards counts as still in cooldown
+            let elapsed = (wall_now - last_notification).to_std().unwrap_or(Duration::ZERO);
+            if elapsed < cooldown_duration {
                 return false; // Still in cooldown
             }
         }
 
         // Update the cooldown time
-        cooldowns.insert(cooldown_key, now);
+        cooldowns.insert(cooldown_key, wall_now);
+        save_notification_cooldowns(&cooldowns);
     }
 
-    // Check global rate limiting (max 5 notifications per minute)
-    {
+    // Check global rate limiting (rate_limit_per_minute, 0 disables it)
+    if settings.rate_limit_per_minute > 0 {
         let mut rate_limiter = NOTIFICATION_RATE_LIMITER.lock().unwrap();
 
         // Remove notifications older than 1 minute
         rate_limiter.retain(|&notification_time| now.duration_since(notification_time) < Duration::from_secs(60));
 
         // Check if we've exceeded the rate limit
-        if rate_limiter.len() >= 5 {
+        if rate_limiter.len() >= settings.rate_limit_per_minute {
             warn!("Notification rate limit exceeded, skipping notification for: {}", event.details.description);
             return false;
         }
@@ -1740,4 +3451,84 @@ fn should_send_notification(event: &SecurityEvent) -> bool {
     }
 
     true
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_event(timestamp: &str) -> SecurityEvent {
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc),
+            event_type: EventType::SshAccess,
+            path: PathBuf::from("/home/user/.ssh/id_rsa"),
+            details: EventDetails { severity: Severity::Critical, description: "SSH key read".to_string(), metadata: HashMap::new() },
+        }
+    }
+
+    #[test]
+    fn alert_log_timestamps_parse_back_with_since() {
+        let event = test_event("2025-09-25T14:30:45.678Z");
+        let line = alert_log_line(&event);
+        assert!(line.ends_with("] CRITICAL: /home/user/.ssh/id_rsa - SSH key read\n"));
+
+        // Whichever timezone it is displayed in, the offset makes the time unambiguous
+        let timestamp = &line[1..line.find(']').unwrap()];
+        let parsed = parse_time_duration(timestamp).unwrap();
+        assert_eq!(parsed, DateTime::parse_from_rfc3339("2025-09-25T14:30:45Z").unwrap());
+    }
+
+    #[test]
+    fn since_accepts_offsets_and_dates() {
+        let expected = DateTime::parse_from_rfc3339("2025-09-25T12:00:00Z").unwrap();
+        assert_eq!(parse_time_duration("2025-09-25T12:00:00Z").unwrap(), expected);
+        assert_eq!(parse_time_duration("2025-09-25T14:00:00+02:00").unwrap(), expected);
+        assert_eq!(parse_time_duration("2025-09-25").unwrap(), DateTime::parse_from_rfc3339("2025-09-25T00:00:00Z").unwrap());
+
+        // The pre-RFC 3339 alert log format has no offset and is rejected rather than guessed
+        assert!(parse_time_duration("2025-09-25 12:00:00").is_none());
+        assert!(parse_time_duration("yesterday").is_none());
+    }
+
+    #[test]
+    fn since_accepts_relative_durations() {
+        let before = Utc::now();
+        let parsed = parse_time_duration("2h").unwrap();
+        let after = Utc::now();
+        assert!(parsed >= before - chrono::Duration::hours(2) && parsed <= after - chrono::Duration::hours(2));
+
+        for (input, duration) in [("30m", chrono::Duration::minutes(30)), ("7d", chrono::Duration::days(7)), ("2w", chrono::Duration::weeks(2))] {
+            let parsed = parse_time_duration(input).unwrap();
+            let age = Utc::now() - parsed;
+            assert!(age >= duration && age < duration + chrono::Duration::seconds(5), "{}", input);
+        }
+        assert!(parse_time_duration("5y").is_none());
+    }
+
+    #[test]
+    fn until_is_exclusive_and_dates_mean_midnight_utc() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("events.db");
+        let mut store = EventStore::open(db_path.to_str().unwrap()).unwrap();
+        let times = ["2024-01-15T07:59:59.999Z", "2024-01-15T08:00:00Z", "2024-01-15T23:59:59.999Z", "2024-01-16T00:00:00Z"];
+        store.insert_batch(&times.map(test_event)).unwrap();
+
+        // since <= t < until, with a date-only --until meaning the start of that day
+        let since = parse_time_duration("2024-01-15T08:00:00Z");
+        let until = parse_time_duration("2024-01-16");
+        let query = EventQuery { since, until, event_type: None, min_severity: None };
+        let found: Vec<DateTime<Utc>> = store.query(&query).unwrap().iter().map(|event| event.timestamp).collect();
+        assert_eq!(found, [times[1], times[2]].map(|time| DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)));
+
+        // stats uses the same bounds
+        assert_eq!(store.count_by_type_and_severity(since, until).unwrap(), [("SshAccess".to_string(), "Critical".to_string(), 2)]);
+        let hourly = store.count_by_hour(since.unwrap(), until.unwrap()).unwrap();
+        assert_eq!(hourly.iter().map(|(_, count)| count).sum::<u64>(), 2);
+
+        // An --until equal to an event's time leaves that event out
+        let until = parse_time_duration("2024-01-15T23:59:59.999Z");
+        assert_eq!(store.query(&EventQuery { since, until, event_type: None, min_severity: None }).unwrap().len(), 1);
+        assert_eq!(store.query(&EventQuery { since: None, until, event_type: None, min_severity: None }).unwrap().len(), 2);
+    }
+}
@@ -1,54 +1,20 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use serde_json;
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
 use std::path::PathBuf;
-use tokio::io::AsyncWriteExt;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::UnixStream;
+use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SecurityEvent {
-    pub timestamp: DateTime<Utc>,
-    pub event_type: EventType,
-    pub path: PathBuf,
-    pub details: EventDetails,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum EventType {
-    FileAccess,
-    FileModify,
-    FileCreate,
-    FileDelete,
-    DirectoryAccess,
-    CameraAccess,
-    SshAccess,
-    MicrophoneAccess,
-    NetworkConnection,
-    UsbDeviceInserted,
-    NetworkDiscovery,
-    PingDetected,
-    PortScanDetected,
-    CustomMessage,
-}
+use secmon_core::{ClientCommand, CommandResponse, SecurityEvent, EventType, EventDetails, Severity};
+use secmon_core::config::{client_config_value, resolve_socket_path};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EventDetails {
-    pub severity: Severity,
-    pub description: String,
-    pub metadata: HashMap<String, String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Severity {
-    Low,
-    Medium,
-    High,
-    Critical,
-}
+/// How long to wait for the daemon to acknowledge an injected event
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -61,6 +27,7 @@ async fn main() -> Result<()> {
 
     // Parse command line arguments
     let mut socket_path: Option<String> = None;
+    let mut token: Option<String> = None;
     let mut json_mode = false;
     let mut event_type = EventType::CustomMessage;
     let mut severity = Severity::Medium;
@@ -68,6 +35,8 @@ async fn main() -> Result<()> {
     let mut description: Option<String> = None;
     let mut metadata = HashMap::new();
     let mut use_stdin = false;
+    let mut batch_mode = false;
+    let mut timestamp: Option<DateTime<Utc>> = None;
 
     let mut i = 1;
     while i < args.len() {
@@ -81,6 +50,15 @@ async fn main() -> Result<()> {
                     std::process::exit(1);
                 }
             }
+            "--token" => {
+                if i + 1 < args.len() {
+                    token = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --token requires a value");
+                    std::process::exit(1);
+                }
+            }
             "--json" | "-j" => {
                 json_mode = true;
                 i += 1;
@@ -89,6 +67,10 @@ async fn main() -> Result<()> {
                 use_stdin = true;
                 i += 1;
             }
+            "--batch" | "-b" => {
+                batch_mode = true;
+                i += 1;
+            }
             "--type" | "-t" => {
                 if i + 1 < args.len() {
                     event_type = parse_event_type(&args[i + 1])?;
@@ -107,6 +89,15 @@ async fn main() -> Result<()> {
                     std::process::exit(1);
                 }
             }
+            "--timestamp" => {
+                if i + 1 < args.len() {
+                    timestamp = Some(parse_timestamp(&args[i + 1])?);
+                    i += 2;
+                } else {
+                    eprintln!("Error: --timestamp requires a value");
+                    std::process::exit(1);
+                }
+            }
             "--path" | "-p" => {
                 if i + 1 < args.len() {
                     path = Some(PathBuf::from(&args[i + 1]));
@@ -155,6 +146,13 @@ async fn main() -> Result<()> {
         }
     }
 
+    let socket = resolve_socket_path(socket_path.as_deref());
+    let token = token.or_else(get_token_from_config);
+
+    if batch_mode {
+        return send_batch(&socket, token.as_deref(), timestamp).await;
+    }
+
     // Handle different input methods
     let event = if json_mode || use_stdin {
         if json_mode {
@@ -163,10 +161,10 @@ async fn main() -> Result<()> {
                 read_stdin()?
             } else {
                 // Try to find JSON in remaining args
-                args.get(args.len() - 1).cloned().unwrap_or_default()
+                args.last().cloned().unwrap_or_default()
             };
 
-            parse_json_event(&json_input)?
+            parse_json_event(&json_input, timestamp)?
         } else {
             // Read raw text from stdin
             let stdin_content = read_stdin()?;
@@ -176,6 +174,7 @@ async fn main() -> Result<()> {
                 path.unwrap_or_else(|| PathBuf::from("/custom/message")),
                 Some(stdin_content),
                 metadata,
+                timestamp,
             )
         }
     } else {
@@ -191,12 +190,15 @@ async fn main() -> Result<()> {
             path.unwrap_or_else(|| PathBuf::from("/custom/message")),
             description,
             metadata,
+            timestamp,
         )
     };
 
     // Send the event
-    let socket = resolve_socket_path(socket_path.as_ref());
-    send_event(&socket, &event).await?;
+    let mut connection = DaemonConnection::connect(&socket, token.as_deref()).await?;
+    if let Some(error) = connection.send_event(&event).await? {
+        anyhow::bail!("Daemon rejected the event: {}", error);
+    }
 
     println!("Message sent successfully to daemon");
     Ok(())
@@ -209,32 +211,41 @@ fn print_help() {
     println!("    secmon-msg [OPTIONS] [DESCRIPTION]");
     println!("    echo 'data' | secmon-msg --stdin");
     println!("    echo '{{\"json\": \"data\"}}' | secmon-msg --json");
+    println!("    secmon-msg --batch < events.ndjson");
     println!();
     println!("OPTIONS:");
     println!("    -h, --help              Show this help message");
-    println!("    -s, --socket PATH       Socket path to connect to");
+    println!("    -s, --socket PATH       Socket path to connect to (default: $SECMON_SOCKET, then socket_path from the config file)");
+    println!("    --token TOKEN           Auth token (default: [auth] token from the config file)");
     println!("    -j, --json              Parse input as JSON event");
     println!("    --stdin                 Read message from stdin");
+    println!("    -b, --batch             Send one JSON event per stdin line over a single connection");
     println!("    -t, --type TYPE         Event type (default: CustomMessage)");
     println!("    --severity LEVEL        Severity level: Low, Medium, High, Critical");
     println!("    -p, --path PATH         File/resource path");
     println!("    -d, --description DESC  Event description");
+    println!("    --timestamp TIME        Event time as RFC 3339 (default: now)");
     println!("    -m, --metadata KEY=VAL  Add metadata key-value pair (can be used multiple times)");
     println!();
     println!("EVENT TYPES:");
-    println!("    CustomMessage, FileAccess, FileModify, FileCreate, FileDelete,");
-    println!("    CameraAccess, SshAccess, MicrophoneAccess, NetworkConnection,");
-    println!("    UsbDeviceInserted, NetworkDiscovery, PingDetected, PortScanDetected");
+    println!("    CustomMessage, FileAccess, FileModify, FileCreate, FileDelete, FileMove, DirectoryCreate,");
+    println!("    CameraAccess, SshAccess, MicrophoneAccess, NetworkConnection, PortOpened, PortClosed,");
+    println!("    UsbDeviceInserted, UsbDeviceRemoved, UsbDeviceBlocked, StorageMounted, StorageUnmounted,");
+    println!("    NetworkDiscovery, PingDetected, PortScanDetected, SynFloodDetected, ConnectionFlood,");
+    println!("    MonitorOverflow, MonitorDegraded, DaemonStarted, DaemonStopped, AvCaptureCorrelated");
     println!();
     println!("EXAMPLES:");
     println!("    secmon-msg \"System backup completed\"");
     println!("    secmon-msg --type CameraAccess --severity High \"Unauthorized camera access\"");
     println!("    secmon-msg --path /etc/passwd --description \"File modified\" --metadata user=admin");
     println!("    echo \"Custom alert message\" | secmon-msg --stdin --severity Critical");
+    println!("    secmon-msg --timestamp 2024-01-15T10:30:00Z \"Backup started\"");
     println!("    echo '{{\"description\":\"JSON event\",\"severity\":\"High\"}}' | secmon-msg --json");
+    println!("    secmon-client listen --format ndjson > captured.ndjson; secmon-msg --batch < captured.ndjson");
     println!();
-    println!("JSON FORMAT (when using --json):");
+    println!("JSON FORMAT (when using --json, and for each line with --batch):");
     println!("    {{");
+    println!("        \"timestamp\": \"2024-01-15T10:30:00Z\",");
     println!("        \"event_type\": \"CustomMessage\",");
     println!("        \"severity\": \"Medium\",");
     println!("        \"path\": \"/path/to/resource\",");
@@ -253,15 +264,30 @@ fn parse_event_type(type_str: &str) -> Result<EventType> {
         "filemodify" => Ok(EventType::FileModify),
         "filecreate" => Ok(EventType::FileCreate),
         "filedelete" => Ok(EventType::FileDelete),
+        "filemove" => Ok(EventType::FileMove),
+        "directorycreate" => Ok(EventType::DirectoryCreate),
         "directoryaccess" => Ok(EventType::DirectoryAccess),
         "cameraaccess" => Ok(EventType::CameraAccess),
         "sshaccess" => Ok(EventType::SshAccess),
         "microphoneaccess" => Ok(EventType::MicrophoneAccess),
         "networkconnection" => Ok(EventType::NetworkConnection),
+        "portopened" => Ok(EventType::PortOpened),
+        "portclosed" => Ok(EventType::PortClosed),
         "usbdeviceinserted" => Ok(EventType::UsbDeviceInserted),
+        "usbdeviceremoved" => Ok(EventType::UsbDeviceRemoved),
+        "usbdeviceblocked" => Ok(EventType::UsbDeviceBlocked),
+        "storagemounted" => Ok(EventType::StorageMounted),
+        "storageunmounted" => Ok(EventType::StorageUnmounted),
         "networkdiscovery" => Ok(EventType::NetworkDiscovery),
         "pingdetected" => Ok(EventType::PingDetected),
         "portscandetected" => Ok(EventType::PortScanDetected),
+        "synflooddetected" => Ok(EventType::SynFloodDetected),
+        "connectionflood" => Ok(EventType::ConnectionFlood),
+        "monitoroverflow" => Ok(EventType::MonitorOverflow),
+        "monitordegraded" => Ok(EventType::MonitorDegraded),
+        "daemonstarted" => Ok(EventType::DaemonStarted),
+        "daemonstopped" => Ok(EventType::DaemonStopped),
+        "avcapturecorrelated" => Ok(EventType::AvCaptureCorrelated),
         _ => Err(anyhow::anyhow!("Invalid event type: {}", type_str)),
     }
 }
@@ -282,9 +308,11 @@ fn create_event_from_options(
     path: PathBuf,
     description: Option<String>,
     metadata: HashMap<String, String>,
+    timestamp: Option<DateTime<Utc>>,
 ) -> SecurityEvent {
     SecurityEvent {
-        timestamp: Utc::now(),
+        id: Uuid::new_v4(),
+        timestamp: timestamp.unwrap_or_else(Utc::now),
         event_type,
         path,
         details: EventDetails {
@@ -295,7 +323,15 @@ fn create_event_from_options(
     }
 }
 
-fn parse_json_event(json_str: &str) -> Result<SecurityEvent> {
+/// Parse an RFC 3339 timestamp such as `2024-01-15T10:30:00Z`
+fn parse_timestamp(timestamp_str: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp_str)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .with_context(|| format!("Invalid timestamp: {} (expected RFC 3339, e.g. 2024-01-15T10:30:00Z)", timestamp_str))
+}
+
+/// `default_timestamp` is used when the JSON has no `timestamp` field, falling back to now
+fn parse_json_event(json_str: &str, default_timestamp: Option<DateTime<Utc>>) -> Result<SecurityEvent> {
     // Try to parse as complete SecurityEvent first
     if let Ok(event) = serde_json::from_str::<SecurityEvent>(json_str) {
         return Ok(event);
@@ -304,6 +340,7 @@ fn parse_json_event(json_str: &str) -> Result<SecurityEvent> {
     // Try to parse as partial event and fill in defaults
     #[derive(Deserialize)]
     struct PartialEvent {
+        timestamp: Option<String>,
         event_type: Option<String>,
         severity: Option<String>,
         path: Option<PathBuf>,
@@ -326,8 +363,14 @@ fn parse_json_event(json_str: &str) -> Result<SecurityEvent> {
         Severity::Medium
     };
 
+    let timestamp = match partial.timestamp {
+        Some(timestamp_str) => parse_timestamp(&timestamp_str)?,
+        None => default_timestamp.unwrap_or_else(Utc::now),
+    };
+
     Ok(SecurityEvent {
-        timestamp: Utc::now(),
+        id: Uuid::new_v4(),
+        timestamp,
         event_type,
         path: partial.path.unwrap_or_else(|| PathBuf::from("/custom/json")),
         details: EventDetails {
@@ -345,56 +388,104 @@ fn read_stdin() -> Result<String> {
     Ok(buffer.trim().to_string())
 }
 
-async fn send_event(socket_path: &str, event: &SecurityEvent) -> Result<()> {
-    let mut stream = UnixStream::connect(socket_path)
-        .await
-        .with_context(|| format!("Failed to connect to daemon socket: {}", socket_path))?;
+struct DaemonConnection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
 
-    let json = serde_json::to_string(event)
-        .context("Failed to serialize event to JSON")?;
+impl DaemonConnection {
+    async fn connect(socket_path: &str, token: Option<&str>) -> Result<Self> {
+        let stream = UnixStream::connect(socket_path)
+            .await
+            .with_context(|| format!("Failed to connect to daemon socket: {}", socket_path))?;
+        let (reader, mut writer) = stream.into_split();
+        if let Some(token) = token {
+            let line = format!("{}\n", serde_json::json!({ "auth": token }));
+            writer.write_all(line.as_bytes()).await
+                .context("Failed to send auth token")?;
+        }
+        Ok(Self { lines: BufReader::new(reader).lines(), writer })
+    }
 
-    let message = format!("{}\n", json);
-    stream.write_all(message.as_bytes()).await
-        .context("Failed to send event to daemon")?;
+    /// Inject `event` and wait for the daemon's acknowledgement. Returns the daemon's
+    /// error message if it rejected the event.
+    async fn send_event(&mut self, event: &SecurityEvent) -> Result<Option<String>> {
+        let command = ClientCommand::Inject { event: event.clone() };
+        let json = serde_json::to_string(&command)
+            .context("Failed to serialize event to JSON")?;
 
-    Ok(())
-}
+        let message = format!("{}\n", json);
+        self.writer.write_all(message.as_bytes()).await
+            .context("Failed to send event to daemon")?;
 
-fn resolve_socket_path(cli_socket: Option<&String>) -> String {
-    // 1. Command line argument takes highest priority
-    if let Some(socket) = cli_socket {
-        return socket.clone();
+        tokio::time::timeout(ACK_TIMEOUT, self.read_ack())
+            .await
+            .with_context(|| format!("Daemon did not acknowledge the event within {}s", ACK_TIMEOUT.as_secs()))?
     }
 
-    // 2. Try to read from config file
-    if let Some(config_socket) = get_socket_from_config() {
-        return config_socket;
+    async fn read_ack(&mut self) -> Result<Option<String>> {
+        // The connection also streams events, which may arrive before the response
+        while let Some(line) = self.lines.next_line().await.context("Failed to read from daemon")? {
+            if let Ok(response) = serde_json::from_str::<CommandResponse>(&line) {
+                if response.cmd == "auth" && !response.ok {
+                    anyhow::bail!("Daemon rejected the auth token (set --token or [auth] token)");
+                }
+                if response.cmd == "inject" {
+                    return Ok(if response.ok {
+                        None
+                    } else {
+                        Some(response.error.unwrap_or_else(|| "unknown error".to_string()))
+                    });
+                }
+            }
+        }
+        anyhow::bail!("Daemon closed the connection before acknowledging the event")
     }
-
-    // 3. Default fallback
-    "/tmp/secmon.sock".to_string()
 }
 
-fn get_socket_from_config() -> Option<String> {
-    use toml::Value;
-
-    let config_paths = [
-        "/etc/secmon/config.toml",
-        "./config.toml",
-        "config.toml"
-    ];
-
-    for config_path in &config_paths {
-        if let Ok(content) = std::fs::read_to_string(config_path) {
-            if let Ok(config) = toml::from_str::<Value>(&content) {
-                if let Some(socket_path) = config.get("socket_path") {
-                    if let Some(path_str) = socket_path.as_str() {
-                        return Some(path_str.to_string());
-                    }
-                }
+/// Send each NDJSON line on stdin as its own event, over one connection
+async fn send_batch(socket_path: &str, token: Option<&str>, default_timestamp: Option<DateTime<Utc>>) -> Result<()> {
+    let mut connection = DaemonConnection::connect(socket_path, token).await?;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut line_number = 0;
+    let mut sent = 0;
+    let mut failed = 0;
+
+    while let Some(line) = lines.next_line().await.context("Failed to read from stdin")? {
+        line_number += 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let event = match parse_json_event(line, default_timestamp) {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("Line {}: {:#}", line_number, e);
+                failed += 1;
+                continue;
             }
+        };
+        // A connection error or missing acknowledgement means nothing after this line can be sent
+        let rejected = connection.send_event(&event)
+            .await
+            .with_context(|| format!("Line {}: stopped after sending {} events", line_number, sent))?;
+        match rejected {
+            Some(error) => {
+                eprintln!("Line {}: daemon rejected the event: {}", line_number, error);
+                failed += 1;
+            }
+            None => sent += 1,
         }
     }
 
-    None
-}
\ No newline at end of file
+    println!("Sent {} events to daemon, {} failed", sent, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn get_token_from_config() -> Option<String> {
+    client_config_value(|config| config.get("auth").and_then(|auth| auth.get("token")).and_then(|token| token.as_str()).map(str::to_string))
+}
@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use chrono::SecondsFormat;
+use log::{error, info, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+
+use crate::config::{OutputConfig, SyslogConfig};
+use crate::syslog::{self, Target};
+use crate::{SecurityEvent, Severity};
+
+const VENDOR: &str = "soulvice";
+const PRODUCT: &str = "secmon";
+
+/// CEF severity on its 0-10 scale (ArcSight reads 0-3 as Low, 4-6 Medium, 7-8 High, 9-10 Very-High)
+fn cef_severity(severity: &Severity) -> u8 {
+    match severity {
+        Severity::Low => 3,
+        Severity::Medium => 5,
+        Severity::High => 8,
+        Severity::Critical => 10,
+    }
+}
+
+/// Escape a header field: `\` and `|` are backslash-escaped, and line breaks can't appear
+fn escape_header(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '|' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape an extension value: `\` and `=` are backslash-escaped, line breaks become `\n`/`\r`
+fn escape_extension(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '=' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Extension keys are alphanumeric, so a metadata key like `old_path` becomes `secmonOldPath`
+fn extension_key(metadata_key: &str) -> String {
+    let mut key = String::from("secmon");
+    for part in metadata_key.split(|c: char| !c.is_ascii_alphanumeric()).filter(|part| !part.is_empty()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            key.push(first.to_ascii_uppercase());
+            key.extend(chars);
+        }
+    }
+    key
+}
+
+/// Format an event as a CEF line:
+/// `CEF:0|soulvice|secmon|VERSION|EVENT_TYPE|DESCRIPTION|SEVERITY|EXTENSION`
+pub fn format_event(event: &SecurityEvent) -> String {
+    let mut extension = vec![
+        format!("rt={}", event.timestamp.timestamp_millis()),
+        format!("externalId={}", event.id),
+        format!("filePath={}", escape_extension(&event.path.to_string_lossy())),
+        format!("msg={}", escape_extension(&event.details.description)),
+        format!("cs1Label=secmonSeverity cs1={:?}", event.details.severity),
+    ];
+
+    let mut metadata: Vec<_> = event.details.metadata.iter().collect();
+    metadata.sort();
+    for (key, value) in metadata {
+        extension.push(format!("{}={}", extension_key(key), escape_extension(value)));
+    }
+
+    format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+        VENDOR,
+        PRODUCT,
+        escape_header(env!("CARGO_PKG_VERSION")),
+        escape_header(event.event_type.name()),
+        escape_header(&event.details.description),
+        cef_severity(&event.details.severity),
+        extension.join(" ")
+    )
+}
+
+/// Where CEF events go, opened with `open`
+pub enum Output {
+    File(tokio::fs::File),
+    Syslog { target: Target, facility: u8, hostname: String },
+}
+
+/// Open `cef_path`, or connect to the `[syslog]` target without one
+pub async fn open(config: &OutputConfig, syslog_config: &SyslogConfig) -> Result<Output> {
+    let output = match &config.cef_path {
+        Some(path) => {
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .with_context(|| format!("Failed to open CEF output: {}", path))?;
+            info!("Writing CEF events to {}", path);
+            Output::File(file)
+        }
+        None => {
+            let facility = syslog_config.facility_code()?;
+            let target = Target::connect(syslog_config.remote.as_deref()).await?;
+            info!(
+                "Sending CEF events to syslog ({})",
+                syslog_config.remote.as_deref().unwrap_or(syslog::LOCAL_SOCKET)
+            );
+            Output::Syslog { target, facility, hostname: syslog::hostname() }
+        }
+    };
+    Ok(output)
+}
+
+/// Write broadcast events as CEF to `output`
+pub async fn run(mut output: Output, mut receiver: broadcast::Receiver<SecurityEvent>) -> Result<()> {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                let line = format_event(&event);
+                let result = match &mut output {
+                    Output::File(file) => file.write_all(format!("{}\n", line).as_bytes()).await,
+                    Output::Syslog { target, facility, hostname } => {
+                        // RFC 5424 header with the CEF line as the message
+                        let message = format!(
+                            "<{}>1 {} {} {} {} - - {}",
+                            *facility as u32 * 8 + syslog::syslog_severity(&event.details.severity) as u32,
+                            event.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+                            hostname,
+                            syslog::APP_NAME,
+                            std::process::id(),
+                            line
+                        );
+                        target.send(message.as_bytes()).await.map(|_| ())
+                    }
+                };
+                if let Err(e) = result {
+                    error!("Failed to write CEF event: {}", e);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("CEF output fell behind, {} events were not written", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventDetails, EventType};
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    #[test]
+    fn header_fields_escape_pipes_and_backslashes() {
+        assert_eq!(escape_header(r"a|b\c"), r"a\|b\\c");
+        assert_eq!(escape_header("line one\nline two\r\n"), "line one line two  ");
+        // `=` only needs escaping in the extension
+        assert_eq!(escape_header("k=v"), "k=v");
+    }
+
+    #[test]
+    fn extension_values_escape_equals_backslashes_and_line_breaks() {
+        assert_eq!(escape_extension(r"a=b\c"), r"a\=b\\c");
+        assert_eq!(escape_extension("one\ntwo\r"), r"one\ntwo\r");
+        // `|` only needs escaping in the header
+        assert_eq!(escape_extension("a|b"), "a|b");
+    }
+
+    #[test]
+    fn metadata_keys_become_camel_case_extension_keys() {
+        assert_eq!(extension_key("old_path"), "secmonOldPath");
+        assert_eq!(extension_key("vendor-id"), "secmonVendorId");
+        assert_eq!(extension_key("pid"), "secmonPid");
+    }
+
+    #[test]
+    fn events_format_as_a_full_cef_line() {
+        let mut metadata = HashMap::new();
+        metadata.insert("old_path".to_string(), "/tmp/a=b".to_string());
+        metadata.insert("pid".to_string(), "42".to_string());
+        let id = Uuid::parse_str("6f1c9c1e-3a5b-4a57-9a4e-0d2a9f7e8b10").unwrap();
+        let event = SecurityEvent {
+            id,
+            timestamp: Utc.timestamp_millis_opt(1_727_274_645_123).unwrap(),
+            event_type: EventType::FileModify,
+            path: PathBuf::from(r"/srv/data|x\y"),
+            details: EventDetails {
+                severity: Severity::High,
+                description: "Modified: a|b\nc=d".to_string(),
+                metadata,
+            },
+        };
+
+        assert_eq!(
+            format_event(&event),
+            format!(
+                concat!(
+                    r"CEF:0|soulvice|secmon|{}|FileModify|Modified: a\|b c=d|8|",
+                    r"rt=1727274645123 externalId=6f1c9c1e-3a5b-4a57-9a4e-0d2a9f7e8b10 filePath=/srv/data|x\\y ",
+                    r"msg=Modified: a|b\nc\=d cs1Label=secmonSeverity cs1=High secmonOldPath=/tmp/a\=b secmonPid=42"
+                ),
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn severities_map_onto_the_cef_scale() {
+        let levels: Vec<u8> = [Severity::Low, Severity::Medium, Severity::High, Severity::Critical].iter().map(cef_severity).collect();
+        assert_eq!(levels, [3, 5, 8, 10]);
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::config::ChatNotifierConfig;
+use crate::{SecurityEvent, SecurityMonitor, Severity};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events listed in one message; the rest of a batch is summarised as a count
+const MAX_BATCH_LINES: usize = 20;
+
+/// Discord rejects messages longer than this many characters
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// One message for a batch of rendered event lines
+fn compose_message(lines: &[String], discord: bool) -> String {
+    let mut message = match lines {
+        [line] => line.clone(),
+        _ => {
+            let mut message = format!("{} security events:", lines.len());
+            for line in lines.iter().take(MAX_BATCH_LINES) {
+                message.push('\n');
+                message.push_str(line);
+            }
+            if lines.len() > MAX_BATCH_LINES {
+                message.push_str(&format!("\n... and {} more", lines.len() - MAX_BATCH_LINES));
+            }
+            message
+        }
+    };
+
+    if discord && message.chars().count() > DISCORD_MAX_CHARS {
+        message = message.chars().take(DISCORD_MAX_CHARS - 3).collect();
+        message.push_str("...");
+    }
+    message
+}
+
+async fn post_message(client: &reqwest::Client, url: &str, discord: bool, message: String) {
+    let key = if discord { "content" } else { "text" };
+    let body = serde_json::json!({ key: message });
+    let result = client
+        .post(url)
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        error!("Failed to post chat notification: {}", e);
+    }
+}
+
+/// Post broadcast events at or above the configured severity to a chat webhook. Events
+/// arriving within `batch_seconds` of the first pending one go out as a single message.
+pub async fn run(config: ChatNotifierConfig, mut receiver: broadcast::Receiver<SecurityEvent>) -> Result<()> {
+    let discord = config.is_discord()?;
+    let min_severity = Severity::from_name(&config.min_severity)
+        .with_context(|| format!("Unknown chat notifier severity '{}'", config.min_severity))?;
+    let window = Duration::from_secs(config.batch_seconds);
+    let client = reqwest::Client::new();
+
+    info!("Posting {:?} and above events to {} chat webhook", min_severity, config.service);
+
+    let mut pending: Vec<String> = Vec::new();
+    let mut flush_at: Option<Instant> = None;
+    loop {
+        let received = match flush_at {
+            Some(deadline) => tokio::select! {
+                received = receiver.recv() => Some(received),
+                _ = tokio::time::sleep_until(deadline) => None,
+            },
+            None => Some(receiver.recv().await),
+        };
+
+        match received {
+            Some(Ok(event)) => {
+                if event.details.severity.level() < min_severity.level() {
+                    continue;
+                }
+                pending.push(SecurityMonitor::substitute_event_fields(&config.template, &event));
+                flush_at.get_or_insert_with(|| Instant::now() + window);
+            }
+            Some(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                warn!("Chat notifier fell behind, {} events were not checked", skipped);
+            }
+            Some(Err(broadcast::error::RecvError::Closed)) => break,
+            None => {
+                flush_at = None;
+                let message = compose_message(&pending, discord);
+                pending.clear();
+                post_message(&client, &config.url, discord, message).await;
+            }
+        }
+    }
+
+    if !pending.is_empty() {
+        post_message(&client, &config.url, discord, compose_message(&pending, discord)).await;
+    }
+    Ok(())
+}
@@ -1,23 +1,546 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use inotify::WatchMask;
+use ipnetwork::IpNetwork;
+use log::warn;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::SecmonError;
+use crate::{EventType, Severity};
+
+const SEVERITY_NAMES: [&str; 4] = ["Low", "Medium", "High", "Critical"];
+
+/// Config files `secmon-client` and `secmon-msg` read daemon settings from, in order
+pub const CLIENT_CONFIG_PATHS: [&str; 3] = [
+    "/etc/secmon/config.toml",
+    "./config.toml",
+    "config.toml"
+];
+
+/// Overrides `socket_path` from the config files for `secmon-client` and `secmon-msg`
+pub const SOCKET_ENV_VAR: &str = "SECMON_SOCKET";
+
+/// `socket_path` when the config doesn't set one: `$XDG_RUNTIME_DIR/secmon.sock`, or a
+/// per-user path in /tmp without a runtime directory
+pub fn default_socket_path() -> String {
+    std::env::var("XDG_RUNTIME_DIR")
+        .map(|dir| format!("{}/secmon.sock", dir))
+        .unwrap_or_else(|_| format!("/tmp/secmon-{}.sock", std::env::var("USER").unwrap_or_else(|_| "user".to_string())))
+}
+
+/// First value `lookup` finds in the client config files, so clients and the daemon
+/// agree on settings
+pub fn client_config_value<T>(mut lookup: impl FnMut(&toml::Value) -> Option<T>) -> Option<T> {
+    for config_path in &CLIENT_CONFIG_PATHS {
+        if let Ok(content) = fs::read_to_string(config_path) {
+            if let Ok(config) = toml::from_str::<toml::Value>(&content) {
+                if let Some(value) = lookup(&config) {
+                    return Some(value);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// The socket a client connects to: `cli_socket`, then `$SECMON_SOCKET`, then
+/// `socket_path` from the client config files, then the daemon's own default.
+/// `${VAR}` and `~` are expanded the way the daemon expands its `socket_path`.
+pub fn resolve_socket_path(cli_socket: Option<&str>) -> String {
+    let socket = cli_socket
+        .map(str::to_string)
+        .or_else(|| std::env::var(SOCKET_ENV_VAR).ok().filter(|socket| !socket.is_empty()))
+        .or_else(|| {
+            client_config_value(|config| config.get("socket_path").and_then(|value| value.as_str()).map(str::to_string))
+        });
+    match socket {
+        Some(socket) => expand_env(&socket),
+        None => default_socket_path(),
+    }
+}
+
+/// A semantic problem found by [`Config::validate`]
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub field: String, // e.g. "triggers[0].min_severity"
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub socket_path: String,
+    #[serde(default = "default_socket_mode")]
+    pub socket_mode: String, // Octal permissions for the socket file, e.g. "0660"
+    #[serde(default)]
+    pub socket_group: Option<String>, // Group to own the socket file, for use with "0660"
     pub log_level: String,
+    #[serde(default)]
+    pub include: Vec<String>, // Globs of files whose watches and triggers are appended, e.g. "watches.d/*.toml"
     pub watches: Vec<WatchConfig>,
     #[serde(default)]
     pub notifications: NotificationConfig,
     #[serde(default)]
     pub triggers: Vec<EventTrigger>,
     #[serde(default)]
+    pub classification: Vec<ClassificationRule>,
+    #[serde(default)]
     pub network_ids: NetworkIDSConfig,
     #[serde(default)]
+    pub network_monitor: NetworkMonitorConfig,
+    #[serde(default)]
     pub display_local_time: bool,
+    #[serde(default)]
+    pub event_log: Option<String>, // Append events as NDJSON to this file
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+    #[serde(default)]
+    pub throttle: ThrottleConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub syslog: SyslogConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub dbus: DbusConfig,
+    #[serde(default)]
+    pub notifier: NotifierConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub remote: RemoteConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub correlation: CorrelationConfig,
+    #[serde(default)]
+    pub usb_allow: Vec<UsbAllowEntry>, // Known USB devices, reported as Low severity
+    #[serde(default)]
+    pub process_allow: Vec<ProcessAllowEntry>, // Programs whose camera/microphone use is Low severity
+    #[serde(default)]
+    pub usb: UsbConfig,
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
+    #[serde(default = "default_max_watches")]
+    pub max_watches: usize, // Upper bound on inotify watches, mostly for recursive trees
+    #[serde(default = "default_inotify_buffer_size")]
+    pub inotify_buffer_size: usize, // Bytes read from inotify per call
+    #[serde(default)]
+    pub use_fanotify: bool, // Watch device files with fanotify to report pid/exe (needs root)
+    #[serde(default = "default_rescan_interval_seconds")]
+    pub rescan_interval_seconds: u64, // Look for hot-plugged cameras/mics this often (0 = never)
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64, // Coalesce repeats of the same event type on a path (0 = off)
+    #[serde(default = "default_alert_log_path")]
+    pub alert_log_path: String, // Critical events written by secmon-client
+    #[serde(default = "default_max_alert_log_bytes")]
+    pub max_alert_log_bytes: u64, // Rotate the alert log once it grows past this (0 = never)
+    #[serde(default = "default_alert_log_keep")]
+    pub alert_log_keep: usize, // Rotated alert logs to keep, path.1 being the newest
+    #[serde(default = "default_pid_file")]
+    pub pid_file: String, // Written in --daemon mode; --pid-file overrides it
+    #[serde(default = "default_log_file")]
+    pub log_file: String, // Daemon output in --daemon mode; --log-file overrides it
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_socket_mode() -> String {
+    "0600".to_string()
+}
+
+fn default_max_watches() -> usize {
+    8192
+}
+
+fn default_inotify_buffer_size() -> usize {
+    64 * 1024
+}
+
+fn default_rescan_interval_seconds() -> u64 {
+    30
+}
+
+fn default_debounce_ms() -> u64 {
+    500
+}
+
+fn default_alert_log_path() -> String {
+    "/tmp/secmon-alerts.log".to_string()
+}
+
+fn default_max_alert_log_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_alert_log_keep() -> usize {
+    5
+}
+
+fn default_pid_file() -> String {
+    "/tmp/secmon.pid".to_string()
+}
+
+fn default_log_file() -> String {
+    "/tmp/secmon.log".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    #[serde(default)]
+    pub severity: HashMap<String, f64>, // Keep-ratio per severity, e.g. Low = 0.1
+    #[serde(default)]
+    pub event_types: HashMap<String, f64>, // Keep-ratio per event type, overrides severity
+}
+
+/// Thinning of noisy event types before they are broadcast, so clients, triggers, the
+/// event log and the store all see the reduced stream. Critical events are never dropped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleConfig {
+    #[serde(default)]
+    pub sample: HashMap<String, f64>, // Keep-ratio per event type, e.g. FileAccess = 0.1
+    #[serde(default)]
+    pub max_per_second: HashMap<String, f64>, // Token-bucket rate per event type
+    #[serde(default)]
+    pub global_max_per_second: Option<f64>, // Rate for all non-Critical events together
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub path: Option<String>, // SQLite database queried by `secmon-client search` and `stats`
+    #[serde(default)]
+    pub retention_days: u64, // Delete stored events older than this (0 = keep forever)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub listen: Option<String>, // Serve Prometheus metrics at http://<listen>/metrics, e.g. "127.0.0.1:9184"
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    #[serde(default)]
+    pub listen: Option<String>, // Also serve the socket protocol over TCP here, e.g. "127.0.0.1:9185"
+}
+
+impl RemoteConfig {
+    /// The TCP address to listen on. Anywhere but loopback needs a non-blank `[auth] token`,
+    /// since otherwise every host that can reach the port reads (and injects) events.
+    pub fn listen_address(&self, auth: &AuthConfig) -> std::result::Result<Option<std::net::SocketAddr>, SecmonError> {
+        let Some(listen) = &self.listen else {
+            return Ok(None);
+        };
+        let address: std::net::SocketAddr = listen
+            .parse()
+            .map_err(|_| SecmonError::Config(format!("'{}' is not an address:port", listen)))?;
+        if !address.ip().is_loopback() && auth.token.as_deref().is_none_or(|token| token.trim().is_empty()) {
+            return Err(SecmonError::Config(format!(
+                "'{}' is reachable from other hosts, which needs [auth] token to be set",
+                listen
+            )));
+        }
+        Ok(Some(address))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    #[serde(default = "default_event_buffer")]
+    pub event_buffer: usize, // Events held for slow socket clients before they start missing some
+    #[serde(default = "default_slow_client_policy")]
+    pub slow_client_policy: String, // "drop": a lagging client skips events, "disconnect": it is disconnected
+}
+
+fn default_event_buffer() -> usize {
+    100
+}
+
+fn default_slow_client_policy() -> String {
+    "drop".to_string()
+}
+
+/// tokio's broadcast channel rejects larger capacities
+const MAX_EVENT_BUFFER: usize = usize::MAX >> 1;
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            event_buffer: default_event_buffer(),
+            slow_client_policy: default_slow_client_policy(),
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// Capacity for the event broadcast channel
+    pub fn event_capacity(&self) -> std::result::Result<usize, SecmonError> {
+        if (1..=MAX_EVENT_BUFFER).contains(&self.event_buffer) {
+            Ok(self.event_buffer)
+        } else {
+            Err(SecmonError::Config(format!(
+                "event_buffer must be between 1 and {}, got {}",
+                MAX_EVENT_BUFFER, self.event_buffer
+            )))
+        }
+    }
+
+    /// Whether a client that falls behind the buffer is disconnected rather than skipping ahead
+    pub fn disconnects_slow_clients(&self) -> std::result::Result<bool, SecmonError> {
+        match self.slow_client_policy.to_ascii_lowercase().as_str() {
+            "drop" => Ok(false),
+            "disconnect" => Ok(true),
+            _ => Err(SecmonError::Config(format!(
+                "unknown slow_client_policy '{}' (expected drop or disconnect)",
+                self.slow_client_policy
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationConfig {
+    #[serde(default = "default_av_capture_window_seconds")]
+    pub av_capture_window_seconds: u64, // Camera + microphone access this close together raises AvCaptureCorrelated (0 = off)
+}
+
+fn default_av_capture_window_seconds() -> u64 {
+    3
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            av_capture_window_seconds: default_av_capture_window_seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessAllowEntry {
+    pub exe: String, // Full executable path, or a bare name matching any executable of that name
+}
+
+impl ProcessAllowEntry {
+    /// Paths compare exactly and names against the executable's file name. An executable
+    /// replaced since the process started (readlink adds " (deleted)") still matches.
+    pub fn matches(&self, exe: &str) -> bool {
+        let exe = exe.strip_suffix(" (deleted)").unwrap_or(exe);
+        if self.exe.contains('/') {
+            self.exe == exe
+        } else {
+            Path::new(exe).file_name().is_some_and(|name| name == self.exe.as_str())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsbAllowEntry {
+    pub vendor_id: String, // 4 hex digits as udev reports them, e.g. "046d", or "*"
+    pub product_id: String,
+    #[serde(default)]
+    pub serial: Option<String>, // Unset (or "*") matches every unit of the model
+}
+
+impl UsbAllowEntry {
+    /// IDs compare case-insensitively; a device without a serial only matches entries
+    /// that don't name one
+    pub fn matches(&self, vendor_id: &str, product_id: &str, serial: Option<&str>) -> bool {
+        let field = |expected: &str, actual: &str| expected == "*" || expected.eq_ignore_ascii_case(actual);
+        field(&self.vendor_id, vendor_id)
+            && field(&self.product_id, product_id)
+            && match (self.serial.as_deref(), serial) {
+                (None | Some("*"), _) => true,
+                (Some(expected), Some(actual)) => expected == actual,
+                (Some(_), None) => false,
+            }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsbConfig {
+    #[serde(default)]
+    pub block_critical: bool, // Unbind Critical, non-allowlisted USB devices from the kernel (needs root)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub token: Option<String>, // Clients must send {"auth":"<token>"} first, on the socket and over TCP
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoIpConfig {
+    #[serde(default)]
+    pub db_path: Option<String>, // GeoLite2-Country or -City .mmdb, for `country`
+    #[serde(default)]
+    pub asn_db_path: Option<String>, // GeoLite2-ASN .mmdb, for `asn` and `as_org`
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    #[serde(default)]
+    pub run_as: Option<String>, // Switch to this user once root-only resources are open
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyslogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_syslog_facility")]
+    pub facility: String, // "auth", "authpriv", "daemon", "local0".."local7", ...
+    #[serde(default)]
+    pub remote: Option<String>, // "host:514" sends over UDP instead of to /dev/log
+}
+
+fn default_syslog_facility() -> String {
+    "auth".to_string()
+}
+
+/// RFC 5424 facility codes by name
+const SYSLOG_FACILITIES: [(&str, u8); 20] = [
+    ("kern", 0), ("user", 1), ("mail", 2), ("daemon", 3), ("auth", 4),
+    ("syslog", 5), ("lpr", 6), ("news", 7), ("uucp", 8), ("cron", 9),
+    ("authpriv", 10), ("ftp", 11),
+    ("local0", 16), ("local1", 17), ("local2", 18), ("local3", 19),
+    ("local4", 20), ("local5", 21), ("local6", 22), ("local7", 23),
+];
+
+impl SyslogConfig {
+    pub fn facility_code(&self) -> std::result::Result<u8, SecmonError> {
+        SYSLOG_FACILITIES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&self.facility))
+            .map(|(_, code)| *code)
+            .ok_or_else(|| {
+                SecmonError::Config(format!(
+                    "unknown syslog facility '{}' (expected one of: {})",
+                    self.facility,
+                    SYSLOG_FACILITIES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+                ))
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DbusConfig {
+    #[serde(default)]
+    pub export: bool, // Emit each event as an org.soulvice.secmon SecurityEvent signal
+    #[serde(default = "default_dbus_bus")]
+    pub bus: String, // "session" or "system"
+}
+
+fn default_dbus_bus() -> String {
+    "session".to_string()
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        Self {
+            export: false,
+            bus: default_dbus_bus(),
+        }
+    }
+}
+
+impl DbusConfig {
+    /// Whether signals go on the system bus rather than the session bus
+    pub fn uses_system_bus(&self) -> std::result::Result<bool, SecmonError> {
+        match self.bus.to_ascii_lowercase().as_str() {
+            "session" => Ok(false),
+            "system" => Ok(true),
+            _ => Err(SecmonError::Config(format!("unknown dbus bus '{}' (expected session or system)", self.bus))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct OutputConfig {
+    #[serde(default)]
+    pub cef: bool, // Also write every event as an ArcSight CEF line
+    #[serde(default)]
+    pub cef_path: Option<String>, // Append CEF lines here; without it they go to the [syslog] target
+}
+
+/// Built-in notification sinks, fed from the event broadcast rather than triggers
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct NotifierConfig {
+    #[serde(default)]
+    pub chat: Option<ChatNotifierConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatNotifierConfig {
+    pub url: String, // Incoming webhook URL
+    #[serde(default = "default_chat_service")]
+    pub service: String, // "slack" posts {"text": ...}, "discord" posts {"content": ...}
+    #[serde(default = "default_chat_min_severity")]
+    pub min_severity: String,
+    #[serde(default = "default_chat_template")]
+    pub template: String, // One line per event; {path}, {severity}, {description}, {event_type}, ...
+    #[serde(default = "default_chat_batch_seconds")]
+    pub batch_seconds: u64, // Events arriving this soon after the first are sent in one message
+}
+
+fn default_chat_service() -> String {
+    "slack".to_string()
+}
+
+fn default_chat_min_severity() -> String {
+    "High".to_string()
+}
+
+fn default_chat_template() -> String {
+    "[{severity}] {event_type}: {description}".to_string()
+}
+
+fn default_chat_batch_seconds() -> u64 {
+    5
+}
+
+impl ChatNotifierConfig {
+    /// Whether messages use Discord's payload shape rather than Slack's
+    pub fn is_discord(&self) -> std::result::Result<bool, SecmonError> {
+        match self.service.to_ascii_lowercase().as_str() {
+            "slack" => Ok(false),
+            "discord" => Ok(true),
+            _ => Err(SecmonError::Config(format!(
+                "unknown chat service '{}' (expected slack or discord)",
+                self.service
+            ))),
+        }
+    }
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            facility: default_syslog_facility(),
+            remote: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NetworkIDSConfig {
     pub enabled: bool,
     pub port_scan_threshold: usize,
@@ -25,9 +548,97 @@ pub struct NetworkIDSConfig {
     pub ping_threshold: usize,
     pub monitor_icmp: bool,
     pub alert_on_discovery: bool,
+    #[serde(default = "default_syn_flood_threshold")]
+    pub syn_flood_threshold: usize, // Half-open (SYN_RECV) connections from one source
+    #[serde(default = "default_port_scan_cooldown_seconds")]
+    pub port_scan_cooldown_seconds: u64, // After a PortScanDetected, the same source is quiet this long
+    #[serde(default = "default_discovery_ports")]
+    pub discovery_ports: Vec<u16>, // Service ports that count towards a discovery sweep
+    #[serde(default = "default_discovery_count")]
+    pub discovery_count: usize, // NetworkDiscovery once a source has hit this many of them
+    #[serde(default = "default_connection_rate_threshold")]
+    pub connection_rate_threshold: usize, // New connections from one source per scan window (0 = off)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_syn_flood_threshold() -> usize {
+    100
+}
+
+fn default_port_scan_cooldown_seconds() -> u64 {
+    300
+}
+
+fn default_discovery_ports() -> Vec<u16> {
+    vec![21, 22, 23, 25, 53, 80, 110, 143, 443, 993, 995]
+}
+
+fn default_discovery_count() -> usize {
+    3
+}
+
+fn default_connection_rate_threshold() -> usize {
+    60
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkMonitorConfig {
+    #[serde(default)]
+    pub allow_cidrs: Vec<String>, // Connections to these ranges are not reported
+    #[serde(default)]
+    pub deny_cidrs: Vec<String>, // Connections to these ranges are always High severity
+    #[serde(default = "default_udp_idle_timeout_seconds")]
+    pub udp_idle_timeout_seconds: u64, // Forget a UDP flow after it has been gone this long
+    #[serde(default)]
+    pub resolve_dns: bool, // Add the reverse-DNS name of the remote address as remote_host
+    #[serde(default = "default_baseline_mode")]
+    pub baseline_mode: String, // "snapshot" ignores connections open at startup, "none" reports them
+    #[serde(default)]
+    pub report_port_closed: bool, // Raise PortClosed when a listening TCP socket goes away
+}
+
+fn default_udp_idle_timeout_seconds() -> u64 {
+    300
+}
+
+fn default_baseline_mode() -> String {
+    "snapshot".to_string()
+}
+
+impl NetworkMonitorConfig {
+    pub fn allow_networks(&self) -> std::result::Result<Vec<IpNetwork>, SecmonError> {
+        parse_cidrs(&self.allow_cidrs)
+    }
+
+    pub fn deny_networks(&self) -> std::result::Result<Vec<IpNetwork>, SecmonError> {
+        parse_cidrs(&self.deny_cidrs)
+    }
+
+    /// Whether connections already open at startup are reported rather than taken as the baseline
+    pub fn reports_existing(&self) -> std::result::Result<bool, SecmonError> {
+        match self.baseline_mode.to_ascii_lowercase().as_str() {
+            "snapshot" => Ok(false),
+            "none" => Ok(true),
+            _ => Err(SecmonError::Config(format!(
+                "unknown baseline_mode '{}' (expected snapshot or none)",
+                self.baseline_mode
+            ))),
+        }
+    }
+}
+
+/// Parse "10.0.0.0/8", "2001:db8::/32" or a bare address (a single-host range)
+fn parse_cidrs(cidrs: &[String]) -> std::result::Result<Vec<IpNetwork>, SecmonError> {
+    cidrs
+        .iter()
+        .map(|cidr| {
+            cidr.trim()
+                .parse::<IpNetwork>()
+                .map_err(|e| SecmonError::Config(format!("Invalid CIDR '{}': {}", cidr, e)))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct WatchConfig {
     pub path: String,
     pub description: String,
@@ -38,6 +649,81 @@ pub struct WatchConfig {
     pub pattern: bool, // If true, treat path as a glob pattern
     #[serde(default)]
     pub auto_discover: bool, // If true, automatically discover devices
+    #[serde(default)]
+    pub event_cooldown_ms: Option<u64>, // Suppress repeat events per path for this long
+    #[serde(default)]
+    pub mask: Option<Vec<String>>, // inotify events to watch, e.g. ["modify", "create"]
+    #[serde(default)]
+    pub exclude: Vec<String>, // Glob patterns matched against the full path and the file name
+    #[serde(default)]
+    pub debounce_ms: Option<u64>, // Overrides the global debounce_ms for this watch
+    #[serde(default)]
+    pub severity: Option<String>, // Raise events under this watch to at least this severity
+}
+
+impl WatchConfig {
+    /// The minimum severity for events under this watch, if one is set
+    pub fn severity_floor(&self) -> std::result::Result<Option<Severity>, SecmonError> {
+        let Some(name) = &self.severity else {
+            return Ok(None);
+        };
+        match Severity::from_name(name) {
+            Some(severity) => Ok(Some(severity)),
+            None => Err(SecmonError::Config(format!(
+                "unknown severity '{}' for watch {} (expected one of: {})",
+                name,
+                self.path,
+                SEVERITY_NAMES.join(", ")
+            ))),
+        }
+    }
+
+    /// The inotify mask for this watch; MODIFY | CREATE | DELETE | MOVE | ACCESS | OPEN when unset
+    pub fn watch_mask(&self) -> std::result::Result<WatchMask, SecmonError> {
+        let Some(names) = &self.mask else {
+            return Ok(WatchMask::MODIFY
+                | WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVE
+                | WatchMask::ACCESS
+                | WatchMask::OPEN);
+        };
+
+        let mut mask = WatchMask::empty();
+        for name in names {
+            mask |= match name.to_lowercase().as_str() {
+                "access" => WatchMask::ACCESS,
+                "modify" => WatchMask::MODIFY,
+                "attrib" => WatchMask::ATTRIB,
+                "close_write" => WatchMask::CLOSE_WRITE,
+                "close_nowrite" => WatchMask::CLOSE_NOWRITE,
+                "close" => WatchMask::CLOSE,
+                "open" => WatchMask::OPEN,
+                "moved_from" => WatchMask::MOVED_FROM,
+                "moved_to" => WatchMask::MOVED_TO,
+                "move" => WatchMask::MOVE,
+                "create" => WatchMask::CREATE,
+                "delete" => WatchMask::DELETE,
+                "delete_self" => WatchMask::DELETE_SELF,
+                "move_self" => WatchMask::MOVE_SELF,
+                "all" => WatchMask::ALL_EVENTS,
+                other => {
+                    return Err(SecmonError::Config(format!(
+                        "unknown mask '{}' for watch {} (expected one of: access, modify, attrib, \
+                         close_write, close_nowrite, close, open, moved_from, moved_to, move, \
+                         create, delete, delete_self, move_self, all)",
+                        other, self.path
+                    )));
+                }
+            };
+        }
+
+        if mask.is_empty() {
+            return Err(SecmonError::Config(format!("empty mask for watch {}", self.path)));
+        }
+
+        Ok(mask)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +732,42 @@ pub struct NotificationConfig {
     pub dbus_enabled: bool,
     pub min_severity: String, // "Low", "Medium", "High", "Critical"
     pub timeout_ms: u32, // Notification timeout in milliseconds
+    #[serde(default)]
+    pub state_file: Option<String>, // Persist notification cooldowns here across restarts
+    #[serde(default = "default_notify_command")]
+    pub command: String, // Run by secmon-client for critical events with a title and body
+    #[serde(default = "default_notification_cooldown_seconds")]
+    pub cooldown_seconds: u64, // Repeat notifications for the same event type and path are skipped this long (0 = never)
+    #[serde(default = "default_device_cooldown_seconds")]
+    pub device_cooldown_seconds: u64, // The same for camera and microphone access
+    #[serde(default = "default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32, // At most this many notifications per minute (0 = unlimited)
+}
+
+fn default_notification_cooldown_seconds() -> u64 {
+    30
+}
+
+fn default_device_cooldown_seconds() -> u64 {
+    60
+}
+
+fn default_rate_limit_per_minute() -> u32 {
+    5
+}
+
+fn default_notify_command() -> String {
+    "notify-send".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassificationRule {
+    pub path_regex: String, // Matched against the full event path
+    #[serde(default)]
+    pub event_type: Option<String>, // Replaces the mask-based type when set
+    pub severity: String, // "Low", "Medium", "High", "Critical"
+    #[serde(default)]
+    pub description: Option<String>, // Replaces the built-in description when set
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,12 +776,30 @@ pub struct EventTrigger {
     pub enabled: bool,
     pub event_types: Vec<String>, // ["CameraAccess", "SshAccess", etc.]
     pub min_severity: String,
+    #[serde(default)]
     pub command: String, // Command to execute
+    #[serde(default)]
     pub args: Vec<String>, // Command arguments
     #[serde(default)]
+    pub webhook_url: Option<String>, // POST the event as JSON here instead of running a command
+    #[serde(default)]
+    pub webhook_headers: HashMap<String, String>, // Extra request headers, e.g. Authorization
+    #[serde(default = "default_webhook_timeout_seconds")]
+    pub webhook_timeout_seconds: u64,
+    #[serde(default)]
     pub run_async: bool, // Don't wait for command completion
     #[serde(default)]
+    pub timeout_seconds: Option<u64>, // Kill the command if it runs longer than this
+    #[serde(default)]
     pub cooldown_seconds: u64, // Minimum time between executions
+    #[serde(default)]
+    pub metadata_match: HashMap<String, String>, // All keys must match: "value", "~regex" or "!value"
+    #[serde(default)]
+    pub path_regex: Option<String>, // Only fire for events whose path matches
+}
+
+fn default_webhook_timeout_seconds() -> u64 {
+    10
 }
 
 impl Default for NotificationConfig {
@@ -69,6 +809,24 @@ impl Default for NotificationConfig {
             dbus_enabled: true,
             min_severity: "Medium".to_string(),
             timeout_ms: 5000,
+            state_file: None,
+            command: default_notify_command(),
+            cooldown_seconds: default_notification_cooldown_seconds(),
+            device_cooldown_seconds: default_device_cooldown_seconds(),
+            rate_limit_per_minute: default_rate_limit_per_minute(),
+        }
+    }
+}
+
+impl Default for NetworkMonitorConfig {
+    fn default() -> Self {
+        Self {
+            allow_cidrs: Vec::new(),
+            deny_cidrs: Vec::new(),
+            udp_idle_timeout_seconds: default_udp_idle_timeout_seconds(),
+            resolve_dns: false,
+            baseline_mode: default_baseline_mode(),
+            report_port_closed: false,
         }
     }
 }
@@ -82,19 +840,23 @@ impl Default for NetworkIDSConfig {
             ping_threshold: 5,              // Alert after 5+ pings in short time
             monitor_icmp: false,            // Disabled by default (requires root)
             alert_on_discovery: true,       // Alert on network discovery attempts
+            syn_flood_threshold: default_syn_flood_threshold(),
+            port_scan_cooldown_seconds: default_port_scan_cooldown_seconds(),
+            discovery_ports: default_discovery_ports(),
+            discovery_count: default_discovery_count(),
+            connection_rate_threshold: default_connection_rate_threshold(),
         }
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        let socket_path = std::env::var("XDG_RUNTIME_DIR")
-            .map(|dir| format!("{}/secmon.sock", dir))
-            .unwrap_or_else(|_| format!("/tmp/secmon-{}.sock", std::env::var("USER").unwrap_or_else(|_| "user".to_string())));
-
         Self {
-            socket_path,
+            socket_path: default_socket_path(),
+            socket_mode: default_socket_mode(),
+            socket_group: None,
             log_level: "info".to_string(),
+            include: Vec::new(),
             notifications: NotificationConfig::default(),
             display_local_time: true,
             triggers: vec![
@@ -112,6 +874,12 @@ impl Default for Config {
                     ],
                     run_async: true,
                     cooldown_seconds: 5,
+                    metadata_match: HashMap::new(),
+                    webhook_url: None,
+                    webhook_headers: HashMap::new(),
+                    webhook_timeout_seconds: default_webhook_timeout_seconds(),
+                    timeout_seconds: None,
+                    path_regex: None,
                 },
                 EventTrigger {
                     name: "SSH Access Alert".to_string(),
@@ -127,6 +895,12 @@ impl Default for Config {
                     ],
                     run_async: true,
                     cooldown_seconds: 10,
+                    metadata_match: HashMap::new(),
+                    webhook_url: None,
+                    webhook_headers: HashMap::new(),
+                    webhook_timeout_seconds: default_webhook_timeout_seconds(),
+                    timeout_seconds: None,
+                    path_regex: None,
                 },
                 EventTrigger {
                     name: "Port Scan Alert".to_string(),
@@ -142,6 +916,12 @@ impl Default for Config {
                     ],
                     run_async: true,
                     cooldown_seconds: 30,
+                    metadata_match: HashMap::new(),
+                    webhook_url: None,
+                    webhook_headers: HashMap::new(),
+                    webhook_timeout_seconds: default_webhook_timeout_seconds(),
+                    timeout_seconds: None,
+                    path_regex: None,
                 },
                 EventTrigger {
                     name: "Network Discovery Alert".to_string(),
@@ -156,6 +936,12 @@ impl Default for Config {
                     ],
                     run_async: true,
                     cooldown_seconds: 60,
+                    metadata_match: HashMap::new(),
+                    webhook_url: None,
+                    webhook_headers: HashMap::new(),
+                    webhook_timeout_seconds: default_webhook_timeout_seconds(),
+                    timeout_seconds: None,
+                    path_regex: None,
                 },
             ],
             watches: vec![
@@ -167,6 +953,7 @@ impl Default for Config {
                     recursive: false,
                     pattern: true,
                     auto_discover: true,
+                    ..Default::default()
                 },
                 // Auto-discover all microphone/audio devices
                 WatchConfig {
@@ -176,6 +963,7 @@ impl Default for Config {
                     recursive: true,
                     pattern: true,
                     auto_discover: true,
+                    ..Default::default()
                 },
                 WatchConfig {
                     path: "/tmp/.pulse*".to_string(),
@@ -184,6 +972,7 @@ impl Default for Config {
                     recursive: true,
                     pattern: true,
                     auto_discover: true,
+                    ..Default::default()
                 },
                 WatchConfig {
                     path: "/run/user/*/pulse".to_string(),
@@ -192,6 +981,7 @@ impl Default for Config {
                     recursive: true,
                     pattern: true,
                     auto_discover: true,
+                    ..Default::default()
                 },
                 // SSH monitoring
                 WatchConfig {
@@ -201,6 +991,7 @@ impl Default for Config {
                     recursive: true,
                     pattern: false,
                     auto_discover: false,
+                    ..Default::default()
                 },
                 WatchConfig {
                     path: "/etc/ssh".to_string(),
@@ -209,6 +1000,7 @@ impl Default for Config {
                     recursive: true,
                     pattern: false,
                     auto_discover: false,
+                    ..Default::default()
                 },
                 WatchConfig {
                     path: "/var/log/auth.log".to_string(),
@@ -217,14 +1009,156 @@ impl Default for Config {
                     recursive: false,
                     pattern: false,
                     auto_discover: false,
+                    ..Default::default()
                 },
             ],
             network_ids: NetworkIDSConfig::default(),
+            network_monitor: NetworkMonitorConfig::default(),
+            classification: Vec::new(),
+            event_log: None,
+            sampling: SamplingConfig::default(),
+            throttle: ThrottleConfig::default(),
+            storage: StorageConfig::default(),
+            syslog: SyslogConfig::default(),
+            output: OutputConfig::default(),
+            dbus: DbusConfig::default(),
+            notifier: NotifierConfig::default(),
+            metrics: MetricsConfig::default(),
+            remote: RemoteConfig::default(),
+            auth: AuthConfig::default(),
+            daemon: DaemonConfig::default(),
+            security: SecurityConfig::default(),
+            correlation: CorrelationConfig::default(),
+            usb_allow: Vec::new(),
+            process_allow: Vec::new(),
+            usb: UsbConfig::default(),
+            geoip: GeoIpConfig::default(),
+            max_watches: default_max_watches(),
+            inotify_buffer_size: default_inotify_buffer_size(),
+            use_fanotify: false,
+            rescan_interval_seconds: default_rescan_interval_seconds(),
+            debounce_ms: default_debounce_ms(),
+            alert_log_path: default_alert_log_path(),
+            max_alert_log_bytes: default_max_alert_log_bytes(),
+            alert_log_keep: default_alert_log_keep(),
+            pid_file: default_pid_file(),
+            log_file: default_log_file(),
+        }
+    }
+}
+
+/// How a config file is written, chosen by its extension: `.yaml`/`.yml` and `.json`,
+/// anything else (including no extension) is TOML
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    /// Deserialize a config (or an included fragment) as written, before `${VAR}` expansion
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> Result<T> {
+        let config = match self {
+            ConfigFormat::Toml => toml::from_str(content)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+            ConfigFormat::Json => serde_json::from_str(content)?,
+        };
+        Ok(config)
+    }
+
+    pub fn serialize(self, config: &Config) -> Result<String> {
+        let content = match self {
+            ConfigFormat::Toml => toml::to_string_pretty(config)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(config)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(config)? + "\n",
+        };
+        Ok(content)
+    }
+}
+
+/// What an included file contributes; anything else in it is ignored, so scalar settings
+/// always come from the main file
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFragment {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    watches: Vec<WatchConfig>,
+    #[serde(default)]
+    triggers: Vec<EventTrigger>,
+}
+
+/// Append the watches and triggers of the files `patterns` match, then those of the files
+/// they include in turn. `chain` holds the files being read, to catch include cycles.
+fn collect_includes(
+    path: &Path,
+    patterns: &[String],
+    chain: &mut Vec<PathBuf>,
+    watches: &mut Vec<WatchConfig>,
+    triggers: &mut Vec<EventTrigger>,
+) -> Result<()> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        let cycle: Vec<String> = chain.iter().chain([&canonical]).map(|file| file.display().to_string()).collect();
+        bail!("Include cycle: {}", cycle.join(" -> "));
+    }
+    chain.push(canonical.clone());
+
+    // Relative patterns are resolved against the including file's directory
+    let base = canonical.parent().unwrap_or(Path::new("/"));
+    for pattern in patterns {
+        let pattern = base.join(expand_env(pattern)).to_string_lossy().into_owned();
+        let mut files: Vec<PathBuf> = glob::glob(&pattern)
+            .with_context(|| format!("Invalid include pattern: {}", pattern))?
+            .filter_map(|entry| entry.ok())
+            .collect();
+        if files.is_empty() && !pattern.contains(['*', '?', '[']) {
+            bail!("Included config file not found: {}", pattern);
+        }
+        files.sort();
+
+        for file in files {
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read included config file: {}", file.display()))?;
+            let fragment: ConfigFragment = ConfigFormat::from_path(&file.to_string_lossy()).parse(&content)
+                .with_context(|| format!("Failed to parse included config file: {}", file.display()))?;
+            watches.extend(fragment.watches);
+            triggers.extend(fragment.triggers);
+            collect_includes(&file, &fragment.include, chain, watches, triggers)?;
         }
     }
+
+    chain.pop();
+    Ok(())
 }
 
 impl Config {
+    /// Append the watches and triggers of the files listed in `include`; `path` is this
+    /// config's own file, which relative patterns are resolved against
+    pub fn merge_includes(&mut self, path: &str) -> Result<()> {
+        if self.include.is_empty() {
+            return Ok(());
+        }
+        let mut watches = Vec::new();
+        let mut triggers = Vec::new();
+        collect_includes(Path::new(path), &self.include, &mut Vec::new(), &mut watches, &mut triggers)?;
+        self.watches.extend(watches);
+        self.triggers.extend(triggers);
+        Ok(())
+    }
+
     pub fn load(path: &str) -> Result<Self> {
         if !std::path::Path::new(path).exists() {
             println!("Config file not found, creating default at: {}", path);
@@ -236,19 +1170,379 @@ impl Config {
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file: {}", path))?;
 
-        let config: Config = toml::from_str(&content)
+        let mut config: Config = ConfigFormat::from_path(path).parse(&content)
             .with_context(|| format!("Failed to parse config file: {}", path))?;
+        config.merge_includes(path)?;
+        config.expand_env_vars();
+
+        for watch in &config.watches {
+            watch.watch_mask()
+                .with_context(|| format!("Invalid config file: {}", path))?;
+            watch.severity_floor()
+                .with_context(|| format!("Invalid config file: {}", path))?;
+        }
+        config.network_monitor.allow_networks()
+            .with_context(|| format!("Invalid config file: {}", path))?;
+        config.network_monitor.deny_networks()
+            .with_context(|| format!("Invalid config file: {}", path))?;
+        config.syslog.facility_code()
+            .with_context(|| format!("Invalid config file: {}", path))?;
+        config.daemon.event_capacity()
+            .with_context(|| format!("Invalid config file: {}", path))?;
+        config.daemon.disconnects_slow_clients()
+            .with_context(|| format!("Invalid config file: {}", path))?;
+        config.dbus.uses_system_bus()
+            .with_context(|| format!("Invalid config file: {}", path))?;
+        if let Some(chat) = &config.notifier.chat {
+            chat.is_discord()
+                .with_context(|| format!("Invalid config file: {}", path))?;
+        }
+        config.remote.listen_address(&config.auth)
+            .with_context(|| format!("Invalid config file: {}", path))?;
 
         Ok(config)
     }
 
+    /// Expand `${VAR}` and a leading `~` in the socket path, watch paths and trigger
+    /// commands, so one config file works across users and machines
+    fn expand_env_vars(&mut self) {
+        self.socket_path = expand_env(&self.socket_path);
+        for watch in &mut self.watches {
+            watch.path = expand_env(&watch.path);
+        }
+        for trigger in &mut self.triggers {
+            trigger.command = expand_env(&trigger.command);
+            for arg in &mut trigger.args {
+                *arg = expand_env(arg);
+            }
+        }
+    }
+
+    /// `socket_mode` as permission bits
+    pub fn socket_permissions(&self) -> std::result::Result<u32, SecmonError> {
+        let digits = self.socket_mode.trim_start_matches("0o");
+        match u32::from_str_radix(digits, 8) {
+            Ok(mode) if mode <= 0o777 => Ok(mode),
+            _ => Err(SecmonError::Config(format!(
+                "socket_mode must be octal permissions up to 0777, got '{}'",
+                self.socket_mode
+            ))),
+        }
+    }
+
+    /// Check the values serde can't: severity and event type names, trigger actions,
+    /// regexes, glob patterns, masks and CIDRs. Returns every problem rather than the first.
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |field: String, message: String, suggestion: Option<String>| {
+            issues.push(ConfigIssue { field, message, suggestion });
+        };
+
+        if Severity::from_name(&self.notifications.min_severity).is_none() {
+            issue(
+                "notifications.min_severity".to_string(),
+                format!("unknown severity '{}'", self.notifications.min_severity),
+                Some(severity_suggestion(&self.notifications.min_severity)),
+            );
+        }
+
+        for (i, watch) in self.watches.iter().enumerate() {
+            if watch.path.trim().is_empty() {
+                issue(format!("watches[{}].path", i), "path is empty".to_string(), None);
+            } else if watch.pattern {
+                if let Err(e) = glob::Pattern::new(&watch.path) {
+                    issue(
+                        format!("watches[{}].path", i),
+                        format!("invalid glob pattern '{}': {}", watch.path, e),
+                        Some("escape literal '[' and ']' as '[[]' and '[]]'".to_string()),
+                    );
+                }
+            }
+            for (j, exclude) in watch.exclude.iter().enumerate() {
+                if let Err(e) = glob::Pattern::new(exclude) {
+                    issue(
+                        format!("watches[{}].exclude[{}]", i, j),
+                        format!("invalid glob pattern '{}': {}", exclude, e),
+                        None,
+                    );
+                }
+            }
+            if let Err(e) = watch.watch_mask() {
+                issue(format!("watches[{}].mask", i), issue_message(e), None);
+            }
+            if let (Some(name), Err(_)) = (&watch.severity, watch.severity_floor()) {
+                issue(
+                    format!("watches[{}].severity", i),
+                    format!("unknown severity '{}'", name),
+                    Some(severity_suggestion(name)),
+                );
+            }
+        }
+
+        for (i, trigger) in self.triggers.iter().enumerate() {
+            let field = |name: &str| format!("triggers[{}].{} ('{}')", i, name, trigger.name);
+
+            if Severity::from_name(&trigger.min_severity).is_none() {
+                issue(
+                    field("min_severity"),
+                    format!("unknown severity '{}'", trigger.min_severity),
+                    Some(severity_suggestion(&trigger.min_severity)),
+                );
+            }
+            if trigger.event_types.is_empty() {
+                issue(field("event_types"), "no event types, so the trigger never fires".to_string(), None);
+            }
+            for name in &trigger.event_types {
+                if EventType::from_name(name).is_none() {
+                    issue(
+                        field("event_types"),
+                        format!("unknown event type '{}'", name),
+                        Some(event_type_suggestion(name)),
+                    );
+                }
+            }
+
+            match (trigger.command.trim().is_empty(), &trigger.webhook_url) {
+                (true, None) => issue(
+                    field("command"),
+                    "command is empty".to_string(),
+                    Some("set command, or webhook_url to POST the event instead".to_string()),
+                ),
+                (false, Some(_)) => issue(
+                    field("webhook_url"),
+                    "both command and webhook_url are set".to_string(),
+                    Some("use a separate trigger for each action".to_string()),
+                ),
+                _ => {}
+            }
+            if let Some(url) = &trigger.webhook_url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    issue(field("webhook_url"), format!("'{}' is not an http(s) URL", url), None);
+                }
+            }
+
+            if let Some(pattern) = &trigger.path_regex {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    issue(field("path_regex"), format!("invalid regex '{}': {}", pattern, e), None);
+                }
+            }
+            for (key, expected) in &trigger.metadata_match {
+                if let Some(pattern) = expected.strip_prefix('~') {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        issue(
+                            field(&format!("metadata_match.{}", key)),
+                            format!("invalid regex '{}': {}", pattern, e),
+                            None,
+                        );
+                    }
+                }
+            }
+        }
+
+        for (i, rule) in self.classification.iter().enumerate() {
+            if let Err(e) = regex::Regex::new(&rule.path_regex) {
+                issue(
+                    format!("classification[{}].path_regex", i),
+                    format!("invalid regex '{}': {}", rule.path_regex, e),
+                    None,
+                );
+            }
+            if Severity::from_name(&rule.severity).is_none() {
+                issue(
+                    format!("classification[{}].severity", i),
+                    format!("unknown severity '{}'", rule.severity),
+                    Some(severity_suggestion(&rule.severity)),
+                );
+            }
+            if let Some(name) = &rule.event_type {
+                if EventType::from_name(name).is_none() {
+                    issue(
+                        format!("classification[{}].event_type", i),
+                        format!("unknown event type '{}'", name),
+                        Some(event_type_suggestion(name)),
+                    );
+                }
+            }
+        }
+
+        for (name, ratio) in &self.sampling.severity {
+            if Severity::from_name(name).is_none() {
+                issue(
+                    format!("sampling.severity.{}", name),
+                    format!("unknown severity '{}'", name),
+                    Some(severity_suggestion(name)),
+                );
+            }
+            if !(0.0..=1.0).contains(ratio) {
+                issue(format!("sampling.severity.{}", name), format!("ratio {} is outside 0.0-1.0", ratio), None);
+            }
+        }
+        for (name, ratio) in &self.sampling.event_types {
+            if EventType::from_name(name).is_none() {
+                issue(
+                    format!("sampling.event_types.{}", name),
+                    format!("unknown event type '{}'", name),
+                    Some(event_type_suggestion(name)),
+                );
+            }
+            if !(0.0..=1.0).contains(ratio) {
+                issue(format!("sampling.event_types.{}", name), format!("ratio {} is outside 0.0-1.0", ratio), None);
+            }
+        }
+
+        for (name, ratio) in &self.throttle.sample {
+            if EventType::from_name(name).is_none() {
+                issue(
+                    format!("throttle.sample.{}", name),
+                    format!("unknown event type '{}'", name),
+                    Some(event_type_suggestion(name)),
+                );
+            }
+            if !(0.0..=1.0).contains(ratio) {
+                issue(format!("throttle.sample.{}", name), format!("ratio {} is outside 0.0-1.0", ratio), None);
+            }
+        }
+        for (name, rate) in &self.throttle.max_per_second {
+            if EventType::from_name(name).is_none() {
+                issue(
+                    format!("throttle.max_per_second.{}", name),
+                    format!("unknown event type '{}'", name),
+                    Some(event_type_suggestion(name)),
+                );
+            }
+            if *rate <= 0.0 {
+                issue(format!("throttle.max_per_second.{}", name), format!("rate {} must be above 0", rate), None);
+            }
+        }
+        if let Some(rate) = self.throttle.global_max_per_second {
+            if rate <= 0.0 {
+                issue("throttle.global_max_per_second".to_string(), format!("rate {} must be above 0", rate), None);
+            }
+        }
+
+        if self.network_ids.discovery_count == 0 {
+            issue(
+                "network_ids.discovery_count".to_string(),
+                "must be at least 1".to_string(),
+                Some("set alert_on_discovery = false to turn discovery alerts off".to_string()),
+            );
+        }
+
+        for (i, entry) in self.process_allow.iter().enumerate() {
+            if entry.exe.trim().is_empty() {
+                issue(format!("process_allow[{}].exe", i), "exe is empty".to_string(), None);
+            } else if entry.exe.contains('/') && !entry.exe.starts_with('/') {
+                issue(
+                    format!("process_allow[{}].exe", i),
+                    format!("'{}' is neither an absolute path nor a bare name", entry.exe),
+                    Some("use e.g. \"/usr/bin/zoom\" or \"zoom\"".to_string()),
+                );
+            }
+        }
+
+        for (i, entry) in self.usb_allow.iter().enumerate() {
+            for (name, id) in [("vendor_id", &entry.vendor_id), ("product_id", &entry.product_id)] {
+                if id != "*" && !(id.len() == 4 && id.chars().all(|c| c.is_ascii_hexdigit())) {
+                    issue(
+                        format!("usb_allow[{}].{}", i, name),
+                        format!("'{}' is not a 4-digit hex ID", id),
+                        Some("use the ID from lsusb, e.g. \"046d\", or \"*\" for any".to_string()),
+                    );
+                }
+            }
+        }
+
+        if self.usb.block_critical {
+            if let Some(user) = &self.security.run_as {
+                issue(
+                    "usb.block_critical".to_string(),
+                    format!("unbinding devices needs root, but security.run_as switches to '{}'", user),
+                    Some("remove run_as, or turn block_critical off".to_string()),
+                );
+            }
+        }
+
+        if let Err(e) = self.network_monitor.allow_networks() {
+            issue("network_monitor.allow_cidrs".to_string(), issue_message(e), None);
+        }
+        if let Err(e) = self.network_monitor.deny_networks() {
+            issue("network_monitor.deny_cidrs".to_string(), issue_message(e), None);
+        }
+        if let Err(e) = self.network_monitor.reports_existing() {
+            issue("network_monitor.baseline_mode".to_string(), issue_message(e), None);
+        }
+        if let Some(listen) = &self.metrics.listen {
+            if listen.parse::<std::net::SocketAddr>().is_err() {
+                issue(
+                    "metrics.listen".to_string(),
+                    format!("'{}' is not an address:port", listen),
+                    Some("e.g. \"127.0.0.1:9184\"".to_string()),
+                );
+            }
+        }
+        if self.auth.token.as_ref().is_some_and(|token| token.trim().is_empty()) {
+            issue(
+                "auth.token".to_string(),
+                "token is empty".to_string(),
+                Some("set a long random token, or remove it to turn authentication off".to_string()),
+            );
+        }
+        if let Err(e) = self.remote.listen_address(&self.auth) {
+            issue(
+                "remote.listen".to_string(),
+                issue_message(e),
+                Some("e.g. \"127.0.0.1:9185\", tunnelled over SSH, or set [auth] token".to_string()),
+            );
+        }
+        if let Err(e) = self.socket_permissions() {
+            issue(
+                "socket_mode".to_string(),
+                issue_message(e),
+                Some("e.g. \"0600\" for root only or \"0660\" with socket_group".to_string()),
+            );
+        }
+        if let Err(e) = self.syslog.facility_code() {
+            issue("syslog.facility".to_string(), issue_message(e), None);
+        }
+        if let Err(e) = self.daemon.event_capacity() {
+            issue("daemon.event_buffer".to_string(), issue_message(e), None);
+        }
+        if let Err(e) = self.daemon.disconnects_slow_clients() {
+            issue("daemon.slow_client_policy".to_string(), issue_message(e), None);
+        }
+        if let Err(e) = self.dbus.uses_system_bus() {
+            issue("dbus.bus".to_string(), issue_message(e), None);
+        }
+        if let Some(chat) = &self.notifier.chat {
+            if !chat.url.starts_with("http://") && !chat.url.starts_with("https://") {
+                issue(
+                    "notifier.chat.url".to_string(),
+                    format!("'{}' is not an http(s) URL", chat.url),
+                    None,
+                );
+            }
+            if let Err(e) = chat.is_discord() {
+                issue("notifier.chat.service".to_string(), issue_message(e), None);
+            }
+            if Severity::from_name(&chat.min_severity).is_none() {
+                issue(
+                    "notifier.chat.min_severity".to_string(),
+                    format!("unknown severity '{}'", chat.min_severity),
+                    Some(severity_suggestion(&chat.min_severity)),
+                );
+            }
+        }
+
+        issues
+    }
+
     pub fn save(&self, path: &str) -> Result<()> {
         if let Some(parent) = std::path::Path::new(path).parent() {
             fs::create_dir_all(parent)
                 .with_context(|| format!("Failed to create config directory: {:?}", parent))?;
         }
 
-        let content = toml::to_string_pretty(self)
+        let content = ConfigFormat::from_path(path).serialize(self)
             .context("Failed to serialize config")?;
 
         fs::write(path, content)
@@ -256,4 +1550,145 @@ impl Config {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Replace `${VAR}` with its value and a leading `~` with $HOME. Unset variables are
+/// left as written (with a warning) rather than expanding to an empty string.
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    if rest == "~" || rest.starts_with("~/") {
+        match std::env::var("HOME") {
+            Ok(home) => {
+                expanded.push_str(&home);
+                rest = &rest[1..];
+            }
+            Err(_) => warn!("HOME is not set, leaving '~' unexpanded in '{}'", value),
+        }
+    }
+
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let placeholder = &rest[start..start + 3 + len];
+        expanded.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(var) => expanded.push_str(&var),
+            Err(_) => {
+                warn!("Environment variable {} is not set, leaving '{}' unexpanded in '{}'", name, placeholder, value);
+                expanded.push_str(placeholder);
+            }
+        }
+        rest = &rest[start + 3 + len..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// The message without the "Configuration error:" prefix, which the field name makes redundant
+fn issue_message(error: SecmonError) -> String {
+    match error {
+        SecmonError::Config(message) => message,
+        other => other.to_string(),
+    }
+}
+
+fn severity_suggestion(name: &str) -> String {
+    match closest_name(name, SEVERITY_NAMES.iter().copied()) {
+        Some(closest) => format!("did you mean '{}'?", closest),
+        None => format!("expected one of: {}", SEVERITY_NAMES.join(", ")),
+    }
+}
+
+fn event_type_suggestion(name: &str) -> String {
+    match closest_name(name, EventType::ALL.iter().map(EventType::name)) {
+        Some(closest) => format!("did you mean '{}'?", closest),
+        None => format!(
+            "expected one of: {}",
+            EventType::ALL.iter().map(EventType::name).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// The candidate within a couple of typos of `input` (case-insensitive), if any
+fn closest_name<'a>(input: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let input = input.to_lowercase();
+    candidates
+        .map(|candidate| (edit_distance(&input, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= 2.max(input.len() / 4))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, counting an adjacent swap ("Hgih") as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            rows[i][j] = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                rows[i][j] = rows[i][j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote_config(listen: &str, token: Option<&str>) -> Config {
+        Config {
+            remote: RemoteConfig { listen: Some(listen.to_string()) },
+            auth: AuthConfig { token: token.map(str::to_string) },
+            ..Config::default()
+        }
+    }
+
+    fn remote_issues(config: &Config) -> Vec<ConfigIssue> {
+        config.validate().into_iter().filter(|issue| issue.field == "remote.listen").collect()
+    }
+
+    #[test]
+    fn remote_listen_beyond_loopback_needs_a_token() {
+        for listen in ["0.0.0.0:9185", "192.0.2.10:9185", "[::]:9185"] {
+            // A blank token is no token
+            for token in [None, Some(""), Some("  \t")] {
+                let config = remote_config(listen, token);
+                assert!(config.remote.listen_address(&config.auth).is_err(), "{} {:?}", listen, token);
+                assert_eq!(remote_issues(&config).len(), 1, "{} {:?}", listen, token);
+            }
+
+            let config = remote_config(listen, Some("long-random-string"));
+            assert!(config.remote.listen_address(&config.auth).unwrap().is_some(), "{}", listen);
+            assert!(remote_issues(&config).is_empty(), "{}", listen);
+        }
+    }
+
+    #[test]
+    fn remote_listen_on_loopback_works_without_a_token() {
+        for listen in ["127.0.0.1:9185", "[::1]:9185"] {
+            let config = remote_config(listen, None);
+            assert!(config.remote.listen_address(&config.auth).unwrap().is_some(), "{}", listen);
+            assert!(remote_issues(&config).is_empty(), "{}", listen);
+        }
+
+        let config = remote_config("localhost:9185", None);
+        assert!(config.remote.listen_address(&config.auth).is_err());
+        assert!(Config::default().remote.listen_address(&AuthConfig::default()).unwrap().is_none());
+    }
+}
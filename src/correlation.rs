@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, watch};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::{EventDetails, EventType, SecurityEvent, Severity};
+
+/// Camera and microphone opened within the configured window of each other, as when a
+/// call is being recorded
+fn av_capture_event(camera: &(DateTime<Utc>, PathBuf), microphone: &(DateTime<Utc>, PathBuf), window_seconds: u64) -> SecurityEvent {
+    let gap = (camera.0 - microphone.0).abs();
+
+    let mut metadata = HashMap::new();
+    metadata.insert("camera_path".to_string(), camera.1.display().to_string());
+    metadata.insert("microphone_path".to_string(), microphone.1.display().to_string());
+    metadata.insert("gap_ms".to_string(), gap.num_milliseconds().to_string());
+    metadata.insert("window_seconds".to_string(), window_seconds.to_string());
+
+    SecurityEvent {
+        id: Uuid::new_v4(),
+        timestamp: Utc::now(),
+        event_type: EventType::AvCaptureCorrelated,
+        path: camera.1.clone(),
+        details: EventDetails {
+            severity: Severity::Critical,
+            description: format!(
+                "Camera and microphone accessed together: {} and {} within {:.1}s",
+                camera.1.display(),
+                microphone.1.display(),
+                gap.num_milliseconds() as f64 / 1000.0
+            ),
+            metadata,
+        },
+    }
+}
+
+/// Watch broadcast events for camera and microphone access close together. Correlated
+/// events go to `derived`, which the filesystem loop dispatches so triggers apply to them.
+/// The window is read per event, so reloads take effect (0 turns correlation off).
+pub async fn run(
+    mut receiver: broadcast::Receiver<SecurityEvent>,
+    config_updates: watch::Receiver<Arc<Config>>,
+    derived: mpsc::Sender<SecurityEvent>,
+) {
+    let mut camera: Option<(DateTime<Utc>, PathBuf)> = None;
+    let mut microphone: Option<(DateTime<Utc>, PathBuf)> = None;
+
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Event correlation fell behind, {} events were not checked", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        // A [[process_allow]] program using both devices is an ordinary call
+        if event.details.metadata.get("trusted_process").is_some_and(|trusted| trusted == "true") {
+            continue;
+        }
+        match event.event_type {
+            EventType::CameraAccess => camera = Some((event.timestamp, event.path)),
+            EventType::MicrophoneAccess => microphone = Some((event.timestamp, event.path)),
+            _ => continue,
+        }
+
+        let window_seconds = config_updates.borrow().correlation.av_capture_window_seconds;
+        if window_seconds == 0 {
+            continue;
+        }
+        let correlated = match (&camera, &microphone) {
+            (Some(camera), Some(microphone))
+                if (camera.0 - microphone.0).abs() <= chrono::Duration::seconds(window_seconds as i64) =>
+            {
+                av_capture_event(camera, microphone, window_seconds)
+            }
+            _ => continue,
+        };
+
+        // Each pair is reported once; a new one needs both devices to be accessed again
+        camera = None;
+        microphone = None;
+        info!("{}", correlated.details.description);
+        if derived.send(correlated).await.is_err() {
+            break;
+        }
+    }
+}
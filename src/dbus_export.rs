@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use tokio::sync::broadcast;
+use zbus::object_server::SignalEmitter;
+
+use crate::SecurityEvent;
+
+const BUS_NAME: &str = "org.soulvice.secmon";
+const OBJECT_PATH: &str = "/org/soulvice/secmon";
+
+/// The object events are signalled from; it has no methods or properties
+struct EventSignals;
+
+#[zbus::interface(name = "org.soulvice.secmon")]
+impl EventSignals {
+    /// An event as JSON, the same line socket clients receive
+    #[zbus(signal)]
+    async fn security_event(emitter: &SignalEmitter<'_>, event: &str) -> zbus::Result<()>;
+}
+
+/// Own org.soulvice.secmon on the session (or system) bus, for `run`
+pub async fn connect(system_bus: bool) -> Result<zbus::Connection> {
+    let builder = if system_bus {
+        zbus::connection::Builder::system()?
+    } else {
+        zbus::connection::Builder::session()?
+    };
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, EventSignals)?
+        .build()
+        .await
+        .with_context(|| format!("Failed to register {} on the {} bus", BUS_NAME, if system_bus { "system" } else { "session" }))?;
+    Ok(connection)
+}
+
+/// Emit a SecurityEvent signal on `connection` for every broadcast event, so desktop
+/// applets can subscribe without the socket
+pub async fn run(connection: zbus::Connection, mut receiver: broadcast::Receiver<SecurityEvent>) -> Result<()> {
+    let emitter = SignalEmitter::new(&connection, OBJECT_PATH)?;
+    info!("Emitting events as {} signals at {}", BUS_NAME, OBJECT_PATH);
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                let json = match serde_json::to_string(&event) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        error!("Failed to serialize event for D-Bus: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = EventSignals::security_event(&emitter, &json).await {
+                    error!("Failed to emit D-Bus signal: {}", e);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("D-Bus export fell behind, {} events were not signalled", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use log::{debug, info, warn};
+use log::{debug, warn};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -12,21 +12,19 @@ impl DeviceDiscovery {
 
         // Check /dev/video* devices
         if let Ok(entries) = fs::read_dir("/dev") {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if let Some(filename) = path.file_name() {
-                        let filename_str = filename.to_string_lossy();
-
-                        // Match video devices: video0, video1, video2, etc.
-                        if filename_str.starts_with("video") &&
-                           filename_str.chars().skip(5).all(|c| c.is_ascii_digit()) {
-
-                            // Verify it's actually a character device (not just a file named videoX)
-                            if Self::is_video_device(&path)? {
-                                devices.push(path.clone());
-                                info!("Discovered video device: {}", path.display());
-                            }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if let Some(filename) = path.file_name() {
+                    let filename_str = filename.to_string_lossy();
+
+                    // Match video devices: video0, video1, video2, etc.
+                    if filename_str.starts_with("video") &&
+                       filename_str.chars().skip(5).all(|c| c.is_ascii_digit()) {
+
+                        // Verify it's actually a character device (not just a file named videoX)
+                        if Self::is_video_device(&path)? {
+                            devices.push(path.clone());
+                            debug!("Discovered video device: {}", path.display());
                         }
                     }
                 }
@@ -35,13 +33,11 @@ impl DeviceDiscovery {
 
         // Also check /dev/v4l/by-id/ for more descriptive names
         if let Ok(entries) = fs::read_dir("/dev/v4l/by-id") {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if Self::is_video_device(&path)? {
-                        devices.push(path.clone());
-                        info!("Discovered V4L device: {}", path.display());
-                    }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if Self::is_video_device(&path)? {
+                    devices.push(path.clone());
+                    debug!("Discovered V4L device: {}", path.display());
                 }
             }
         }
@@ -81,23 +77,21 @@ impl DeviceDiscovery {
         }
 
         if let Ok(entries) = fs::read_dir(snd_path) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if let Some(filename) = path.file_name() {
-                        let filename_str = filename.to_string_lossy();
-
-                        // Match PCM devices, control devices, etc.
-                        if filename_str.starts_with("pcm") ||      // PCM audio devices
-                           filename_str.starts_with("control") ||  // ALSA control devices
-                           filename_str.starts_with("hw") ||       // Hardware devices
-                           filename_str.starts_with("seq") ||      // Sequencer
-                           filename_str.starts_with("timer") {     // Timer
-
-                            if Self::is_audio_device(&path)? {
-                                devices.push(path.clone());
-                                info!("Discovered ALSA device: {}", path.display());
-                            }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if let Some(filename) = path.file_name() {
+                    let filename_str = filename.to_string_lossy();
+
+                    // Match PCM devices, control devices, etc.
+                    if filename_str.starts_with("pcm") ||      // PCM audio devices
+                       filename_str.starts_with("control") ||  // ALSA control devices
+                       filename_str.starts_with("hw") ||       // Hardware devices
+                       filename_str.starts_with("seq") ||      // Sequencer
+                       filename_str.starts_with("timer") {     // Timer
+
+                        if Self::is_audio_device(&path)? {
+                            devices.push(path.clone());
+                            debug!("Discovered ALSA device: {}", path.display());
                         }
                     }
                 }
@@ -123,19 +117,17 @@ impl DeviceDiscovery {
             let path = Path::new(path_str);
             if path.exists() {
                 devices.push(path.to_path_buf());
-                info!("Discovered PulseAudio path: {}", path.display());
+                debug!("Discovered PulseAudio path: {}", path.display());
             }
         }
 
         // Check for PulseAudio sockets in runtime directories
         if let Ok(entries) = fs::read_dir("/run/user") {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let pulse_dir = entry.path().join("pulse");
-                    if pulse_dir.exists() {
-                        devices.push(pulse_dir);
-                        info!("Discovered user PulseAudio: {}", entry.path().display());
-                    }
+            for entry in entries.flatten() {
+                let pulse_dir = entry.path().join("pulse");
+                if pulse_dir.exists() {
+                    devices.push(pulse_dir);
+                    debug!("Discovered user PulseAudio: {}", entry.path().display());
                 }
             }
         }
@@ -156,15 +148,13 @@ impl DeviceDiscovery {
             if path.exists() {
                 // Check if there are JACK-related files
                 if let Ok(entries) = fs::read_dir(path) {
-                    for entry in entries {
-                        if let Ok(entry) = entry {
-                            let filename = entry.file_name();
-                            let filename_str = filename.to_string_lossy();
-
-                            if filename_str.contains("jack") {
-                                devices.push(entry.path());
-                                info!("Discovered JACK device: {}", entry.path().display());
-                            }
+                    for entry in entries.flatten() {
+                        let filename = entry.file_name();
+                        let filename_str = filename.to_string_lossy();
+
+                        if filename_str.contains("jack") {
+                            devices.push(entry.path());
+                            debug!("Discovered JACK device: {}", entry.path().display());
                         }
                     }
                 }
@@ -235,7 +225,7 @@ impl DeviceDiscovery {
         match Self::discover_video_devices() {
             Ok(video_devices) => {
                 paths.extend(video_devices);
-                info!("Discovered {} video devices", paths.len());
+                debug!("Discovered {} video devices", paths.len());
             }
             Err(e) => {
                 warn!("Failed to discover video devices: {}", e);
@@ -247,7 +237,7 @@ impl DeviceDiscovery {
             Ok(audio_devices) => {
                 let audio_count = audio_devices.len();
                 paths.extend(audio_devices);
-                info!("Discovered {} audio devices", audio_count);
+                debug!("Discovered {} audio devices", audio_count);
             }
             Err(e) => {
                 warn!("Failed to discover audio devices: {}", e);
@@ -256,22 +246,4 @@ impl DeviceDiscovery {
 
         Ok(paths)
     }
-
-    /// Check if new devices have appeared (for periodic rescanning)
-    pub fn rescan_devices(current_devices: &[PathBuf]) -> Result<Vec<PathBuf>> {
-        let discovered = Self::discover_all_monitored_paths()?;
-
-        let mut new_devices = Vec::new();
-        for device in discovered {
-            if !current_devices.contains(&device) {
-                new_devices.push(device);
-            }
-        }
-
-        if !new_devices.is_empty() {
-            info!("Discovered {} new devices", new_devices.len());
-        }
-
-        Ok(new_devices)
-    }
 }
\ No newline at end of file
@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+
+use crate::config::SamplingConfig;
+use crate::sampling::Sampler;
+use crate::SecurityEvent;
+
+/// Open (creating if needed) the event log for appending
+pub async fn open(path: &str) -> Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open event log: {}", path))
+}
+
+/// Append broadcast events to an NDJSON file opened with `open`, which `--replay` can read back
+pub async fn run(
+    path: String,
+    mut file: tokio::fs::File,
+    sampling: SamplingConfig,
+    mut receiver: broadcast::Receiver<SecurityEvent>,
+) -> Result<()> {
+    info!("Writing events to {}", path);
+    let mut sampler = Sampler::new(sampling);
+
+    loop {
+        match receiver.recv().await {
+            Ok(mut event) => {
+                if !sampler.sample(&mut event) {
+                    continue;
+                }
+
+                match serde_json::to_string(&event) {
+                    Ok(json) => {
+                        if let Err(e) = file.write_all(format!("{}\n", json).as_bytes()).await {
+                            error!("Failed to write event log {}: {}", path, e);
+                        }
+                    }
+                    Err(e) => error!("Failed to serialize event for log: {}", e),
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Event log fell behind, {} events were not written", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+
+    Ok(())
+}
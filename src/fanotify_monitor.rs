@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::{EventType, SecurityEvent, SecurityMonitor, EventDetails, Severity};
+
+/// Repeat opens of the same device by the same process are reported at most this often,
+/// matching the daemon's camera/microphone de-duplication window
+const REPEAT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Largest file handle the kernel hands out (MAX_HANDLE_SZ in linux/fcntl.h)
+const MAX_HANDLE_SZ: usize = 128;
+
+/// fanotify-based watcher for device files. Unlike inotify it reports which process
+/// opened or read the file, at the cost of requiring CAP_SYS_ADMIN.
+///
+/// The group runs in FAN_REPORT_FID mode: the kernel does not report device opens to
+/// groups that receive an open fd per event, so events are matched back to the marked
+/// path by file handle instead.
+pub struct FanotifyMonitor {
+    fd: OwnedFd,
+    event_sender: broadcast::Sender<SecurityEvent>,
+    config_updates: watch::Receiver<Arc<Config>>, // process_allow is read from here so reloads apply
+    marked: HashMap<Vec<u8>, PathBuf>, // File handle (type + bytes) -> marked path
+}
+
+impl FanotifyMonitor {
+    /// Fails when fanotify is unavailable (usually not running as root); callers fall back to inotify
+    pub fn new(event_sender: broadcast::Sender<SecurityEvent>, config_updates: watch::Receiver<Arc<Config>>) -> Result<Self> {
+        let fd = unsafe {
+            libc::fanotify_init(
+                libc::FAN_CLASS_NOTIF | libc::FAN_CLOEXEC | libc::FAN_REPORT_FID,
+                (libc::O_RDONLY | libc::O_LARGEFILE | libc::O_CLOEXEC) as libc::c_uint,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to initialize fanotify");
+        }
+
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            event_sender,
+            config_updates,
+            marked: HashMap::new(),
+        })
+    }
+
+    /// Report opens and reads of `path`
+    pub fn add_path(&mut self, path: &Path) -> Result<()> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .with_context(|| format!("Invalid path for fanotify: {}", path.display()))?;
+
+        let result = unsafe {
+            libc::fanotify_mark(
+                self.fd.as_raw_fd(),
+                libc::FAN_MARK_ADD,
+                libc::FAN_OPEN | libc::FAN_ACCESS,
+                libc::AT_FDCWD,
+                c_path.as_ptr(),
+            )
+        };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to add fanotify mark for {}", path.display()));
+        }
+
+        let handle = Self::file_handle(&c_path)
+            .with_context(|| format!("Failed to get file handle for {}", path.display()))?;
+        self.marked.insert(handle, path.to_path_buf());
+        Ok(())
+    }
+
+    /// `name_to_handle_at` for `path`, returned as handle type followed by the handle bytes,
+    /// the same layout as the tail of a FAN_EVENT_INFO_TYPE_FID record
+    fn file_handle(path: &CString) -> std::io::Result<Vec<u8>> {
+        // struct file_handle { u32 handle_bytes; i32 handle_type; u8 f_handle[]; }
+        let mut buffer = vec![0u8; 8 + MAX_HANDLE_SZ];
+        buffer[..4].copy_from_slice(&(MAX_HANDLE_SZ as u32).to_ne_bytes());
+        let mut mount_id: libc::c_int = 0;
+
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_name_to_handle_at,
+                libc::AT_FDCWD,
+                path.as_ptr(),
+                buffer.as_mut_ptr(),
+                &mut mount_id as *mut libc::c_int,
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let handle_bytes = u32::from_ne_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+        Ok(buffer[4..8 + handle_bytes].to_vec())
+    }
+
+    /// Find the marked path an event refers to from its FID info record
+    fn event_path(&self, event: &[u8], metadata_len: usize) -> Option<&PathBuf> {
+        let header_size = std::mem::size_of::<libc::fanotify_event_info_header>();
+        let mut offset = metadata_len;
+
+        while offset + header_size <= event.len() {
+            let info_type = event[offset];
+            let info_len = u16::from_ne_bytes([event[offset + 2], event[offset + 3]]) as usize;
+            if info_len == 0 || offset + info_len > event.len() {
+                return None;
+            }
+
+            // Record: header (4) + fsid (8) + handle_bytes (4) + handle_type (4) + f_handle
+            if info_type == libc::FAN_EVENT_INFO_TYPE_FID && info_len >= 20 {
+                let record = &event[offset..offset + info_len];
+                let handle_bytes = u32::from_ne_bytes([record[12], record[13], record[14], record[15]]) as usize;
+                if 20 + handle_bytes <= record.len() {
+                    return self.marked.get(&record[16..20 + handle_bytes]);
+                }
+            }
+
+            offset += info_len;
+        }
+
+        None
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marked.is_empty()
+    }
+
+    pub fn marked_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.marked.values()
+    }
+
+    /// Blocking read loop; run it on a blocking thread
+    pub fn start_monitoring(self) -> Result<()> {
+        info!("fanotify monitoring started for {} device(s)", self.marked.len());
+
+        let own_pid = std::process::id() as i32;
+        let metadata_size = std::mem::size_of::<libc::fanotify_event_metadata>();
+        let mut buffer = vec![0u8; 4096 * metadata_size];
+        let mut last_reported: HashMap<(PathBuf, i32), Instant> = HashMap::new();
+
+        loop {
+            let len = unsafe {
+                libc::read(self.fd.as_raw_fd(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+            };
+            if len < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err).context("Failed to read fanotify events");
+            }
+
+            let len = len as usize;
+            let mut offset = 0;
+            while offset + metadata_size <= len {
+                let metadata: libc::fanotify_event_metadata = unsafe {
+                    std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const libc::fanotify_event_metadata)
+                };
+                let event_len = metadata.event_len as usize;
+                if event_len < metadata_size
+                    || offset + event_len > len
+                    || metadata.vers != libc::FANOTIFY_METADATA_VERSION
+                {
+                    error!("Unexpected fanotify event layout, stopping fanotify monitoring");
+                    return Ok(());
+                }
+                let event = &buffer[offset..offset + event_len];
+                offset += event_len;
+
+                if metadata.mask & libc::FAN_Q_OVERFLOW != 0 {
+                    warn!("fanotify queue overflowed, some device accesses were not reported");
+                    continue;
+                }
+                if metadata.pid == own_pid {
+                    continue;
+                }
+
+                let Some(path) = self.event_path(event, metadata.metadata_len as usize).cloned() else {
+                    debug!("Could not match fanotify event to a watched device");
+                    continue;
+                };
+
+                let now = Instant::now();
+                let key = (path.clone(), metadata.pid);
+                if last_reported.get(&key).is_some_and(|last| now.duration_since(*last) < REPEAT_WINDOW) {
+                    continue;
+                }
+                last_reported.retain(|_, last| now.duration_since(*last) < REPEAT_WINDOW);
+                last_reported.insert(key, now);
+
+                let security_event = self.create_security_event(path, metadata.mask, metadata.pid);
+                if let Err(e) = self.event_sender.send(security_event) {
+                    error!("Failed to send fanotify event: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Whether `exe` matches a `[[process_allow]]` entry
+    fn is_trusted_process(&self, exe: &str) -> bool {
+        self.config_updates.borrow().process_allow.iter().any(|entry| entry.matches(exe))
+    }
+
+    fn create_security_event(&self, path: PathBuf, mask: u64, pid: i32) -> SecurityEvent {
+        let inotify_mask = if mask & libc::FAN_OPEN != 0 {
+            inotify::EventMask::OPEN
+        } else {
+            inotify::EventMask::ACCESS
+        };
+        let (event_type, mut severity, description) = SecurityMonitor::classify_event(&path, &path, inotify_mask);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("mask".to_string(), format!("{:?}", inotify_mask));
+        metadata.insert("source".to_string(), "fanotify".to_string());
+        metadata.insert("pid".to_string(), pid.to_string());
+
+        // The process may already have exited, or belong to another user without ptrace access
+        match std::fs::read_link(format!("/proc/{}/exe", pid)) {
+            Ok(exe) => {
+                metadata.insert("exe".to_string(), exe.display().to_string());
+            }
+            Err(e) => {
+                debug!("Could not read executable of pid {}: {}", pid, e);
+                metadata.insert("exe".to_string(), "unknown".to_string());
+            }
+        }
+        if let Ok(proc_metadata) = std::fs::metadata(format!("/proc/{}", pid)) {
+            metadata.insert("uid".to_string(), proc_metadata.uid().to_string());
+        }
+
+        // Allowlisted programs (a video-call app) using the camera or microphone are routine
+        if matches!(event_type, EventType::CameraAccess | EventType::MicrophoneAccess)
+            && metadata.get("exe").is_some_and(|exe| self.is_trusted_process(exe))
+        {
+            severity = Severity::Low;
+            metadata.insert("trusted_process".to_string(), "true".to_string());
+        }
+
+        let description = match metadata.get("exe") {
+            Some(exe) if exe != "unknown" => format!("{} (pid {}, {})", description, pid, exe),
+            _ => format!("{} (pid {})", description, pid),
+        };
+
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type,
+            path,
+            details: EventDetails {
+                severity,
+                description,
+                metadata,
+            },
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use lru::LruCache;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use crate::config::GeoIpConfig;
+
+/// Looked-up addresses remembered, most recently used first
+const CACHE_CAPACITY: usize = 4096;
+
+/// Marks the start of the metadata map at the end of an .mmdb file
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// Nested maps and pointers deeper than this are treated as a corrupt database
+const MAX_DECODE_DEPTH: usize = 32;
+
+/// A MaxMind DB (.mmdb) file held in memory. Implements the parts of the format
+/// GeoLite2 databases use: the binary search tree and the data section decoder.
+struct Mmdb {
+    data: Vec<u8>,
+    node_count: usize,
+    record_size: usize, // Bits per record: 24, 28 or 32
+    ip_version: u64,
+    data_section: usize, // Offset of the data section in `data`
+    ipv4_start: usize, // Node reached by the 96 zero bits of ::a.b.c.d in an IPv6 tree
+}
+
+impl Mmdb {
+    fn open(path: &str) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+
+        let marker = data
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .context("No MaxMind DB metadata found")?;
+        let metadata_section = &data[marker + METADATA_MARKER.len()..];
+        let (metadata, _) = decode(metadata_section, 0, 0).context("Invalid MaxMind DB metadata")?;
+
+        let field = |name: &str| metadata.get(name).and_then(Value::as_u64);
+        let (Some(node_count), Some(record_size), Some(ip_version)) =
+            (field("node_count"), field("record_size"), field("ip_version"))
+        else {
+            bail!("MaxMind DB metadata lacks node_count, record_size or ip_version");
+        };
+        if ![24, 28, 32].contains(&record_size) {
+            bail!("Unsupported MaxMind DB record size {}", record_size);
+        }
+
+        let tree_size = node_count as usize * record_size as usize * 2 / 8;
+        if tree_size + 16 > marker {
+            bail!("MaxMind DB search tree is larger than the file");
+        }
+
+        let mut mmdb = Self {
+            data,
+            node_count: node_count as usize,
+            record_size: record_size as usize,
+            ip_version,
+            data_section: tree_size + 16,
+            ipv4_start: 0,
+        };
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= mmdb.node_count {
+                    break;
+                }
+                node = mmdb.record(node, 0).context("Truncated MaxMind DB search tree")?;
+            }
+            mmdb.ipv4_start = node;
+        }
+        Ok(mmdb)
+    }
+
+    /// The left (bit 0) or right (bit 1) record of a search tree node
+    fn record(&self, node: usize, bit: u8) -> Option<usize> {
+        let node_bytes = self.record_size * 2 / 8;
+        let bytes = self.data.get(node * node_bytes..(node + 1) * node_bytes)?;
+        let value = match (self.record_size, bit) {
+            (24, 0) => be_uint(&bytes[0..3]),
+            (24, _) => be_uint(&bytes[3..6]),
+            // The middle byte holds the high nibble of both records
+            (28, 0) => ((bytes[3] as usize & 0xF0) << 20) | be_uint(&bytes[0..3]),
+            (28, _) => ((bytes[3] as usize & 0x0F) << 24) | be_uint(&bytes[4..7]),
+            (_, 0) => be_uint(&bytes[0..4]),
+            (_, _) => be_uint(&bytes[4..8]),
+        };
+        Some(value)
+    }
+
+    /// The data record for the network containing `ip`, if any
+    fn lookup(&self, ip: IpAddr) -> Option<Value> {
+        let (bytes, mut node) = match ip {
+            IpAddr::V4(ipv4) => (ipv4.octets().to_vec(), self.ipv4_start),
+            IpAddr::V6(_) if self.ip_version == 4 => return None,
+            IpAddr::V6(ipv6) => (ipv6.octets().to_vec(), 0),
+        };
+
+        for i in 0..bytes.len() * 8 {
+            if node >= self.node_count {
+                break;
+            }
+            let bit = (bytes[i / 8] >> (7 - i % 8)) & 1;
+            node = self.record(node, bit)?;
+        }
+
+        // Equal to node_count means "no data"; beyond it is a pointer into the data section
+        if node <= self.node_count {
+            return None;
+        }
+        let offset = node - self.node_count - 16;
+        decode(&self.data[self.data_section..], offset, 0).map(|(value, _)| value)
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |value, byte| (value << 8) | *byte as usize)
+}
+
+/// Decode the value at `offset` in a data section (pointers are relative to its start).
+/// Returns the value and the offset just past it.
+fn decode(section: &[u8], offset: usize, depth: usize) -> Option<(Value, usize)> {
+    if depth > MAX_DECODE_DEPTH {
+        return None;
+    }
+    let control = *section.get(offset)?;
+    let mut pos = offset + 1;
+    let mut kind = control >> 5;
+
+    if kind == 1 {
+        // Pointer: two bits pick the width, the low three bits are its top bits
+        let width = ((control >> 3) & 0x3) as usize + 1;
+        let bytes = section.get(pos..pos + width)?;
+        let high = (control & 0x7) as usize;
+        let target = match width {
+            1 => (high << 8) | be_uint(bytes),
+            2 => ((high << 16) | be_uint(bytes)) + 2048,
+            3 => ((high << 24) | be_uint(bytes)) + 526_336,
+            _ => be_uint(bytes),
+        };
+        let (value, _) = decode(section, target, depth + 1)?;
+        return Some((value, pos + width));
+    }
+    if kind == 0 {
+        kind = 7 + *section.get(pos)?;
+        pos += 1;
+    }
+
+    let mut size = (control & 0x1f) as usize;
+    if size >= 29 {
+        let extra = size - 28;
+        let bytes = section.get(pos..pos + extra)?;
+        size = match extra {
+            1 => 29 + be_uint(bytes),
+            2 => 285 + be_uint(bytes),
+            _ => 65_821 + be_uint(bytes),
+        };
+        pos += extra;
+    }
+
+    let payload = |len: usize| section.get(pos..pos + len);
+    let value = match kind {
+        2 => Value::String(String::from_utf8_lossy(payload(size)?).into_owned()),
+        3 => Value::from(f64::from_be_bytes(payload(8)?.try_into().ok()?)),
+        4 => Value::Null, // Raw bytes; nothing secmon reads
+        5 | 6 | 9 | 10 => {
+            let value = payload(size)?.iter().fold(0u128, |value, byte| (value << 8) | *byte as u128);
+            u64::try_from(value).map(Value::from).unwrap_or_else(|_| Value::String(value.to_string()))
+        }
+        7 => {
+            let mut map = serde_json::Map::new();
+            for _ in 0..size {
+                let (key, next) = decode(section, pos, depth + 1)?;
+                let (value, next) = decode(section, next, depth + 1)?;
+                map.insert(key.as_str()?.to_string(), value);
+                pos = next;
+            }
+            return Some((Value::Object(map), pos));
+        }
+        8 => {
+            let value = payload(size)?.iter().fold(0u32, |value, byte| (value << 8) | *byte as u32);
+            Value::from(value as i32)
+        }
+        11 => {
+            let mut items = Vec::with_capacity(size.min(1024));
+            for _ in 0..size {
+                let (item, next) = decode(section, pos, depth + 1)?;
+                items.push(item);
+                pos = next;
+            }
+            return Some((Value::Array(items), pos));
+        }
+        14 => return Some((Value::Bool(size != 0), pos)),
+        15 => Value::from(f32::from_be_bytes(payload(4)?.try_into().ok()?) as f64),
+        _ => return None,
+    };
+    let len = match kind {
+        3 => 8,
+        15 => 4,
+        _ => size,
+    };
+    Some((value, pos + len))
+}
+
+#[derive(Debug, Clone, Default)]
+struct GeoInfo {
+    country: Option<String>, // ISO 3166 code
+    asn: Option<u64>,
+    as_org: Option<String>,
+}
+
+/// Country and ASN lookups for public addresses, from GeoLite2 (or compatible) databases
+pub struct GeoIp {
+    databases: Vec<Mmdb>,
+    cache: Mutex<LruCache<IpAddr, GeoInfo>>,
+}
+
+impl GeoIp {
+    /// Load the configured databases once. Missing or unreadable files are logged and
+    /// skipped; None when there is nothing to look addresses up in.
+    pub fn load(config: &GeoIpConfig) -> Option<Arc<Self>> {
+        let mut databases = Vec::new();
+        for path in config.db_path.iter().chain(config.asn_db_path.iter()) {
+            match Mmdb::open(path) {
+                Ok(mmdb) => {
+                    info!("Loaded GeoIP database {} ({} nodes)", path, mmdb.node_count);
+                    databases.push(mmdb);
+                }
+                Err(e) => warn!("GeoIP database {} unavailable, skipping it: {:#}", path, e),
+            }
+        }
+        if databases.is_empty() {
+            return None;
+        }
+
+        Some(Arc::new(Self {
+            databases,
+            cache: Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap())),
+        }))
+    }
+
+    /// Add `country`, `asn` and `as_org` for a public address, where the databases know them
+    pub fn enrich(&self, ip: IpAddr, metadata: &mut HashMap<String, String>) {
+        let ip = match ip {
+            IpAddr::V6(ipv6) => ipv6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        if !is_public(ip) {
+            return;
+        }
+
+        let info = self.lookup(ip);
+        if let Some(country) = info.country {
+            metadata.insert("country".to_string(), country);
+        }
+        if let Some(asn) = info.asn {
+            metadata.insert("asn".to_string(), asn.to_string());
+        }
+        if let Some(as_org) = info.as_org {
+            metadata.insert("as_org".to_string(), as_org);
+        }
+    }
+
+    fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        if let Some(info) = self.cache.lock().unwrap().get(&ip) {
+            return info.clone();
+        }
+
+        let mut info = GeoInfo::default();
+        for record in self.databases.iter().filter_map(|mmdb| mmdb.lookup(ip)) {
+            let string = |value: &Value| value.as_str().map(str::to_string);
+            if info.country.is_none() {
+                info.country = string(&record["country"]["iso_code"])
+                    .or_else(|| string(&record["registered_country"]["iso_code"]));
+            }
+            if info.asn.is_none() {
+                info.asn = record["autonomous_system_number"].as_u64();
+                info.as_org = string(&record["autonomous_system_organization"]);
+            }
+        }
+
+        self.cache.lock().unwrap().put(ip, info.clone());
+        info
+    }
+}
+
+/// Whether an address is routable on the internet, so worth looking up
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ipv4) => {
+            let [a, b, ..] = ipv4.octets();
+            !(ipv4.is_private()
+                || ipv4.is_loopback()
+                || ipv4.is_link_local()
+                || ipv4.is_broadcast()
+                || ipv4.is_unspecified()
+                || ipv4.is_multicast()
+                || ipv4.is_documentation()
+                || (a == 100 && (64..128).contains(&b)) // Carrier-grade NAT, 100.64.0.0/10
+                || a == 0
+                || a >= 240)
+        }
+        IpAddr::V6(ipv6) => {
+            let first = ipv6.segments()[0];
+            !(ipv6.is_loopback()
+                || ipv6.is_unspecified()
+                || ipv6.is_multicast()
+                || (first & 0xfe00) == 0xfc00 // Unique local, fc00::/7
+                || (first & 0xffc0) == 0xfe80) // Link-local, fe80::/10
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Event types shared by the daemon, `secmon-client` and `secmon-msg`.
+//!
+//! Everything that crosses the Unix socket is defined here once, so the three
+//! binaries can't drift apart in what they serialize and accept. The config
+//! types live here too so `secmon-client config validate` checks a file
+//! exactly the way the daemon loads it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+pub mod config;
+pub mod error;
+pub mod sampling;
+pub mod storage;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    /// Assigned where the event is created, so sinks and clients can refer to it.
+    /// Events recorded before ids existed are given a new one when read.
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub event_type: EventType,
+    pub path: PathBuf,
+    pub details: EventDetails,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EventType {
+    FileAccess,
+    FileModify,
+    FileCreate,
+    FileDelete,
+    FileMove,
+    DirectoryCreate,
+    DirectoryAccess,
+    CameraAccess,
+    SshAccess,
+    MicrophoneAccess,
+    NetworkConnection,
+    PortOpened,
+    PortClosed,
+    UsbDeviceInserted,
+    UsbDeviceRemoved,
+    UsbDeviceBlocked,
+    StorageMounted,
+    StorageUnmounted,
+    NetworkDiscovery,
+    PingDetected,
+    PortScanDetected,
+    SynFloodDetected,
+    ConnectionFlood,
+    MonitorOverflow,
+    MonitorDegraded,
+    DaemonStarted,
+    DaemonStopped,
+    AvCaptureCorrelated,
+    CustomMessage,
+}
+
+impl EventType {
+    /// Every event type, in declaration order
+    pub const ALL: [EventType; 29] = [
+        EventType::FileAccess,
+        EventType::FileModify,
+        EventType::FileCreate,
+        EventType::FileDelete,
+        EventType::FileMove,
+        EventType::DirectoryCreate,
+        EventType::DirectoryAccess,
+        EventType::CameraAccess,
+        EventType::SshAccess,
+        EventType::MicrophoneAccess,
+        EventType::NetworkConnection,
+        EventType::PortOpened,
+        EventType::PortClosed,
+        EventType::UsbDeviceInserted,
+        EventType::UsbDeviceRemoved,
+        EventType::UsbDeviceBlocked,
+        EventType::StorageMounted,
+        EventType::StorageUnmounted,
+        EventType::NetworkDiscovery,
+        EventType::PingDetected,
+        EventType::PortScanDetected,
+        EventType::SynFloodDetected,
+        EventType::ConnectionFlood,
+        EventType::MonitorOverflow,
+        EventType::MonitorDegraded,
+        EventType::DaemonStarted,
+        EventType::DaemonStopped,
+        EventType::AvCaptureCorrelated,
+        EventType::CustomMessage,
+    ];
+
+    /// Parse a configuration name such as "FileModify" (case-insensitive)
+    pub fn from_name(name: &str) -> Option<EventType> {
+        Self::ALL.iter().find(|event_type| event_type.name().eq_ignore_ascii_case(name)).cloned()
+    }
+
+    /// Name used for this event type in configuration (triggers, sampling)
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventType::CameraAccess => "CameraAccess",
+            EventType::SshAccess => "SshAccess",
+            EventType::MicrophoneAccess => "MicrophoneAccess",
+            EventType::NetworkConnection => "NetworkConnection",
+            EventType::PortOpened => "PortOpened",
+            EventType::PortClosed => "PortClosed",
+            EventType::UsbDeviceInserted => "UsbDeviceInserted",
+            EventType::UsbDeviceRemoved => "UsbDeviceRemoved",
+            EventType::UsbDeviceBlocked => "UsbDeviceBlocked",
+            EventType::StorageMounted => "StorageMounted",
+            EventType::StorageUnmounted => "StorageUnmounted",
+            EventType::NetworkDiscovery => "NetworkDiscovery",
+            EventType::PingDetected => "PingDetected",
+            EventType::PortScanDetected => "PortScanDetected",
+            EventType::SynFloodDetected => "SynFloodDetected",
+            EventType::ConnectionFlood => "ConnectionFlood",
+            EventType::MonitorOverflow => "MonitorOverflow",
+            EventType::MonitorDegraded => "MonitorDegraded",
+            EventType::DaemonStarted => "DaemonStarted",
+            EventType::DaemonStopped => "DaemonStopped",
+            EventType::AvCaptureCorrelated => "AvCaptureCorrelated",
+            EventType::FileAccess => "FileAccess",
+            EventType::FileModify => "FileModify",
+            EventType::FileCreate => "FileCreate",
+            EventType::FileDelete => "FileDelete",
+            EventType::FileMove => "FileMove",
+            EventType::DirectoryCreate => "DirectoryCreate",
+            EventType::DirectoryAccess => "DirectoryAccess",
+            EventType::CustomMessage => "CustomMessage",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventDetails {
+    pub severity: Severity,
+    pub description: String,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Parse a configuration name such as "High" (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name.to_lowercase().as_str() {
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+
+    /// Rank for "at least this severe" comparisons, Low = 1 to Critical = 4
+    pub fn level(&self) -> u8 {
+        match self {
+            Severity::Low => 1,
+            Severity::Medium => 2,
+            Severity::High => 3,
+            Severity::Critical => 4,
+        }
+    }
+}
+
+/// A request a client writes to the event socket, one JSON object per line, e.g.
+/// `{"cmd":"stats"}`. Lines without a `cmd` field are still read as events to inject.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// Event counters and uptime
+    Stats,
+    /// Daemon health: uptime, watches, clients and which monitors came up
+    Status,
+    /// Re-read the daemon's config file, like SIGHUP
+    Reload,
+    /// Only stream matching events to this client. Every field is optional and all set
+    /// fields must match; sending it as the first line means no unwanted event is sent.
+    Subscribe {
+        #[serde(default)]
+        min_severity: Option<String>,
+        #[serde(default)]
+        event_types: Vec<String>, // Any of these, e.g. ["CameraAccess", "MicrophoneAccess"]
+        #[serde(default)]
+        path_regex: Option<String>,
+    },
+    /// Broadcast an event and reply once it is accepted; unlike a bare event line, an
+    /// event that doesn't parse gets an error response instead of being dropped
+    Inject { event: SecurityEvent },
+}
+
+/// The daemon's reply to a [`ClientCommand`], written on the socket as one line between events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResponse {
+    pub cmd: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+/// Whether a monitoring subsystem came up, as reported by the `status` command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SubsystemStatus {
+    Running,
+    Disabled,
+    Failed { error: String },
+}
+
+/// The `data` of a `status` response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub version: String,
+    pub pid: u32,
+    pub uptime_seconds: u64,
+    pub inotify_watches: usize,
+    pub clients_connected: usize,
+    pub events_total: u64,
+    pub subsystems: BTreeMap<String, SubsystemStatus>, // e.g. "usb", "network"
+}
+
+/// A line the daemon writes to a client about the stream itself rather than an event,
+/// e.g. `{"type":"Lagged","skipped":12}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum StreamNotice {
+    /// The client read too slowly and this many events were dropped for it
+    Lagged { skipped: u64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Position of every variant in declaration order. The match is exhaustive, so a new
+    /// variant fails to compile here until it is added, and then fails the test until it
+    /// is in `EventType::ALL` too.
+    fn declaration_index(event_type: &EventType) -> usize {
+        match event_type {
+            EventType::FileAccess => 0,
+            EventType::FileModify => 1,
+            EventType::FileCreate => 2,
+            EventType::FileDelete => 3,
+            EventType::FileMove => 4,
+            EventType::DirectoryCreate => 5,
+            EventType::DirectoryAccess => 6,
+            EventType::CameraAccess => 7,
+            EventType::SshAccess => 8,
+            EventType::MicrophoneAccess => 9,
+            EventType::NetworkConnection => 10,
+            EventType::PortOpened => 11,
+            EventType::PortClosed => 12,
+            EventType::UsbDeviceInserted => 13,
+            EventType::UsbDeviceRemoved => 14,
+            EventType::UsbDeviceBlocked => 15,
+            EventType::StorageMounted => 16,
+            EventType::StorageUnmounted => 17,
+            EventType::NetworkDiscovery => 18,
+            EventType::PingDetected => 19,
+            EventType::PortScanDetected => 20,
+            EventType::SynFloodDetected => 21,
+            EventType::ConnectionFlood => 22,
+            EventType::MonitorOverflow => 23,
+            EventType::MonitorDegraded => 24,
+            EventType::DaemonStarted => 25,
+            EventType::DaemonStopped => 26,
+            EventType::AvCaptureCorrelated => 27,
+            EventType::CustomMessage => 28,
+        }
+    }
+
+    #[test]
+    fn all_lists_every_event_type_once_in_order() {
+        for (i, event_type) in EventType::ALL.iter().enumerate() {
+            assert_eq!(declaration_index(event_type), i, "{} is out of place in EventType::ALL", event_type.name());
+        }
+    }
+
+    #[test]
+    fn event_types_round_trip_through_serde_and_names() {
+        for event_type in &EventType::ALL {
+            let json = serde_json::to_string(event_type).unwrap();
+            assert_eq!(json, format!("{{\"type\":\"{}\"}}", event_type.name()));
+            let parsed: EventType = serde_json::from_str(&json).unwrap();
+            assert_eq!(declaration_index(&parsed), declaration_index(event_type));
+
+            let by_name = EventType::from_name(&event_type.name().to_lowercase()).unwrap();
+            assert_eq!(declaration_index(&by_name), declaration_index(event_type));
+        }
+    }
+
+    #[test]
+    fn severities_round_trip_through_serde_and_names() {
+        for severity in [Severity::Low, Severity::Medium, Severity::High, Severity::Critical] {
+            let json = serde_json::to_string(&severity).unwrap();
+            assert_eq!(json, format!("\"{:?}\"", severity));
+            let parsed: Severity = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.level(), severity.level());
+            assert_eq!(Severity::from_name(&format!("{:?}", severity).to_uppercase()).unwrap().level(), severity.level());
+        }
+        assert!(Severity::from_name("Hgih").is_none());
+    }
+
+    #[test]
+    fn security_events_round_trip() {
+        let mut metadata = HashMap::new();
+        metadata.insert("pid".to_string(), "42".to_string());
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::CameraAccess,
+            path: PathBuf::from("/dev/video0"),
+            details: EventDetails { severity: Severity::High, description: "Camera".to_string(), metadata },
+        };
+
+        let parsed: SecurityEvent = serde_json::from_str(&serde_json::to_string(&event).unwrap()).unwrap();
+        assert_eq!(parsed.id, event.id);
+        assert_eq!(parsed.timestamp, event.timestamp);
+        assert_eq!(parsed.path, event.path);
+        assert_eq!(parsed.details.metadata, event.details.metadata);
+
+        // Events recorded before ids existed still parse, with a fresh id
+        let legacy = r#"{"timestamp":"2025-09-25T14:30:45.123Z","event_type":{"type":"FileCreate"},"path":"/tmp/x","details":{"severity":"Medium","description":"old","metadata":{}}}"#;
+        let parsed: SecurityEvent = serde_json::from_str(legacy).unwrap();
+        assert!(!parsed.id.is_nil());
+    }
+}
@@ -2,178 +2,709 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use inotify::{Inotify, WatchMask, WatchDescriptor};
 use log::{debug, error, info, warn};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::os::unix::fs::PermissionsExt;
-use tokio::io::{AsyncWriteExt, AsyncBufReadExt, BufReader};
-use tokio::net::{UnixListener, UnixStream};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, AsyncBufReadExt, BufReader, Interest};
+use tokio::net::{TcpListener, UnixListener};
 use tokio::sync::broadcast;
 use tokio_stream::wrappers::UnixListenerStream;
 use tokio_stream::StreamExt;
+use uuid::Uuid;
 
-mod config;
-mod error;
+use secmon_core::{config, sampling};
 mod network_monitor;
 mod usb_monitor;
 mod device_discovery;
 mod network_ids;
-
-use config::{Config, WatchConfig, EventTrigger, NotificationConfig, NetworkIDSConfig};
-use error::SecmonError;
+mod event_log;
+mod syslog;
+mod cef;
+mod chat_notifier;
+#[cfg(feature = "dbus")]
+mod dbus_export;
+mod metrics;
+mod fanotify_monitor;
+mod replay;
+mod systemd;
+mod privileges;
+mod correlation;
+mod geoip;
+mod throttle;
+
+use config::{Config, WatchConfig, EventTrigger};
 use network_monitor::NetworkMonitor;
 use usb_monitor::UsbMonitor;
 use device_discovery::DeviceDiscovery;
 use network_ids::NetworkIDS;
+use replay::ReplayOptions;
+use metrics::Metrics;
+use geoip::GeoIp;
+use fanotify_monitor::FanotifyMonitor;
+use throttle::Throttle;
+pub use secmon_core::{SecurityEvent, EventType, EventDetails, Severity};
+use secmon_core::{ClientCommand, CommandResponse, DaemonStatus, StreamNotice, SubsystemStatus};
+
+/// How long a MOVED_FROM waits for its MOVED_TO before being reported as a move-out
+const MOVE_PAIR_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How often debounce windows are checked for bursts to flush while inotify is quiet
+const DEBOUNCE_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Longest stretch of trigger command output written to the log
+const TRIGGER_OUTPUT_LOG_LIMIT: usize = 1024;
+
+/// With `[auth] token` set, clients have this long to send {"auth":"<token>"}
+const AUTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// Longest {"auth":"<token>"} line read from a client, newline included
+const MAX_AUTH_LINE: usize = 1024;
+/// Longest command or injected event line read from a client; longer ones disconnect it
+const MAX_CLIENT_LINE: usize = 64 * 1024;
+/// Pause before dropping a client that failed to authenticate, to slow down guessing
+const AUTH_FAILURE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+/// On SIGTERM/SIGINT, how long running async triggers and the event store writer get to
+/// finish before the daemon exits anyway
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// How often configured watch paths that were deleted are checked for reappearing
+const LOST_WATCH_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A single inotify watch and the configured watch entry it was created from
+struct WatchedPath {
+    path: PathBuf,
+    watch: Arc<WatchConfig>,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SecurityEvent {
-    pub timestamp: DateTime<Utc>,
-    pub event_type: EventType,
-    pub path: PathBuf,
-    pub details: EventDetails,
+/// A `[[classification]]` rule with its regex compiled and names resolved
+struct CompiledClassificationRule {
+    path_regex: Regex,
+    event_type: Option<EventType>,
+    severity: Severity,
+    description: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum EventType {
-    FileAccess,
-    FileModify,
-    FileCreate,
-    FileDelete,
-    DirectoryAccess,
-    CameraAccess,
-    SshAccess,
-    MicrophoneAccess,
-    NetworkConnection,
-    UsbDeviceInserted,
-    NetworkDiscovery,
-    PingDetected,
-    PortScanDetected,
-    CustomMessage,
+/// Regexes and globs compiled from the config, so they are validated once and never
+/// rebuilt per event. Rebuilt (and re-validated) when the config is reloaded.
+struct CompiledConfig {
+    metadata_patterns: HashMap<String, Regex>,
+    trigger_path_patterns: HashMap<String, Regex>, // Trigger path_regex, keyed by pattern
+    classification_rules: Vec<CompiledClassificationRule>,
+    exclude_patterns: HashMap<String, glob::Pattern>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EventDetails {
-    pub severity: Severity,
-    pub description: String,
-    pub metadata: HashMap<String, String>,
+impl CompiledConfig {
+    fn new(config: &Config) -> Result<Self> {
+        // Compile `~regex` metadata conditions and path_regex once rather than per event
+        let mut metadata_patterns = HashMap::new();
+        let mut trigger_path_patterns = HashMap::new();
+        for trigger in &config.triggers {
+            if let Some(pattern) = &trigger.path_regex {
+                let regex = Regex::new(pattern).with_context(|| {
+                    format!("Invalid path_regex '{}' in trigger '{}'", pattern, trigger.name)
+                })?;
+                trigger_path_patterns.insert(pattern.clone(), regex);
+            }
+
+            if trigger.webhook_url.is_some() != trigger.command.is_empty() {
+                anyhow::bail!("Trigger '{}' must set exactly one of command and webhook_url", trigger.name);
+            }
+            for (name, value) in &trigger.webhook_headers {
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("Invalid webhook header name '{}' in trigger '{}'", name, trigger.name))?;
+                reqwest::header::HeaderValue::from_str(value)
+                    .with_context(|| format!("Invalid value for webhook header '{}' in trigger '{}'", name, trigger.name))?;
+            }
+
+            for expected in trigger.metadata_match.values() {
+                if let Some(pattern) = expected.strip_prefix('~') {
+                    let regex = Regex::new(pattern).with_context(|| {
+                        format!("Invalid metadata_match regex '{}' in trigger '{}'", pattern, trigger.name)
+                    })?;
+                    metadata_patterns.insert(pattern.to_string(), regex);
+                }
+            }
+        }
+
+        // Classification rules, validated up front so a typo fails at startup
+        let mut classification_rules = Vec::new();
+        for rule in &config.classification {
+            let path_regex = Regex::new(&rule.path_regex).with_context(|| {
+                format!("Invalid classification path_regex '{}'", rule.path_regex)
+            })?;
+            let event_type = match &rule.event_type {
+                Some(name) => Some(EventType::from_name(name).ok_or_else(|| {
+                    anyhow::anyhow!("Unknown event_type '{}' in classification rule '{}'", name, rule.path_regex)
+                })?),
+                None => None,
+            };
+            let severity = Severity::from_name(&rule.severity).ok_or_else(|| {
+                anyhow::anyhow!("Unknown severity '{}' in classification rule '{}'", rule.severity, rule.path_regex)
+            })?;
+
+            classification_rules.push(CompiledClassificationRule {
+                path_regex,
+                event_type,
+                severity,
+                description: rule.description.clone(),
+            });
+        }
+
+        // Same for watch excludes
+        let mut exclude_patterns = HashMap::new();
+        for watch in &config.watches {
+            for exclude in &watch.exclude {
+                let pattern = glob::Pattern::new(exclude).with_context(|| {
+                    format!("Invalid exclude pattern '{}' for watch {}", exclude, watch.path)
+                })?;
+                exclude_patterns.insert(exclude.clone(), pattern);
+            }
+        }
+
+        Ok(Self {
+            metadata_patterns,
+            trigger_path_patterns,
+            classification_rules,
+            exclude_patterns,
+        })
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Severity {
-    Low,
-    Medium,
-    High,
-    Critical,
+/// Re-reads the config file for SIGHUP and the `reload` socket command, and publishes
+/// it to the event loop and the network monitors
+struct ConfigReloader {
+    config_path: String,
+    config_updates: tokio::sync::watch::Sender<Arc<Config>>,
+}
+
+impl ConfigReloader {
+    /// Load and validate the config file, then hand it to the event loop. An invalid file
+    /// is returned as an error and the running configuration kept.
+    fn reload(&self) -> Result<()> {
+        info!("Reloading configuration from {}", self.config_path);
+
+        // Config::load writes a default config for a missing file, which a reload must not do
+        if !Path::new(&self.config_path).exists() {
+            anyhow::bail!("{} not found", self.config_path);
+        }
+        let config = Config::load(&self.config_path)?;
+        CompiledConfig::new(&config)?;
+
+        self.config_updates.send_replace(Arc::new(config));
+        Ok(())
+    }
+
+    async fn handle_signals(self: Arc<Self>) -> Result<()> {
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP");
+            if let Err(e) = self.reload() {
+                error!("Config reload failed, keeping current configuration: {:#}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Shared state the socket handlers need to serve clients and answer their commands
+struct ClientContext {
+    event_sender: broadcast::Sender<SecurityEvent>,
+    metrics: Arc<Metrics>,
+    reloader: Option<Arc<ConfigReloader>>, // None in replay mode
+    started_at: std::time::Instant,
+    config_updates: tokio::sync::watch::Receiver<Arc<Config>>, // For the auth token, so reloads apply
+    disconnect_slow_clients: bool, // slow_client_policy = "disconnect"
+}
+
+/// Which events a client has subscribed to; the default passes everything
+#[derive(Debug, Clone, Default)]
+struct ClientFilter {
+    min_severity: Option<Severity>,
+    event_types: Vec<&'static str>, // Names; empty means any type
+    path_regex: Option<Regex>,
+}
+
+impl ClientFilter {
+    /// Build a filter from a subscribe command, rejecting unknown names and bad regexes
+    fn new(min_severity: Option<&str>, event_types: &[String], path_regex: Option<&str>) -> Result<Self> {
+        let min_severity = match min_severity {
+            Some(name) => Some(Severity::from_name(name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown severity '{}'", name))?),
+            None => None,
+        };
+        let event_types = event_types
+            .iter()
+            .map(|name| EventType::from_name(name)
+                .map(|event_type| event_type.name())
+                .ok_or_else(|| anyhow::anyhow!("Unknown event type '{}'", name)))
+            .collect::<Result<Vec<_>>>()?;
+        let path_regex = match path_regex {
+            Some(pattern) => Some(Regex::new(pattern)
+                .with_context(|| format!("Invalid path_regex '{}'", pattern))?),
+            None => None,
+        };
+
+        Ok(Self { min_severity, event_types, path_regex })
+    }
+
+    fn matches(&self, event: &SecurityEvent) -> bool {
+        self.min_severity
+            .as_ref()
+            .is_none_or(|min_severity| event.details.severity.level() >= min_severity.level())
+            && (self.event_types.is_empty() || self.event_types.contains(&event.event_type.name()))
+            && self.path_regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&event.path.to_string_lossy()))
+    }
+}
+
+/// Debounce state for one (path, event type): when the burst's first event went out,
+/// and the repeats held back since, flushed as one event when the window closes
+struct DebounceEntry {
+    reported_at: std::time::Instant,
+    window: std::time::Duration,
+    suppressed: u64,
+    last: Option<SecurityEvent>, // Latest repeat, flushed with the burst's count
+}
+
+/// Events held back by a per-watch cooldown, reported as a summary when it expires
+#[derive(Default)]
+struct SuppressedEvents {
+    count: usize,
+    last: Option<SecurityEvent>,
 }
 
 pub struct SecurityMonitor {
     config: Arc<Config>,
+    config_updates: tokio::sync::watch::Sender<Arc<Config>>, // Reloaded configs (SIGHUP)
+    pub config_path: Option<String>, // Re-read on SIGHUP
     event_sender: broadcast::Sender<SecurityEvent>,
     #[allow(dead_code)]
     _event_receiver: broadcast::Receiver<SecurityEvent>,
     inotify: Inotify,
-    watched_paths: HashMap<WatchDescriptor, PathBuf>,
+    watched_paths: HashMap<WatchDescriptor, WatchedPath>,
     pub socket_path: String,
+    pub replay: Option<ReplayOptions>,
+    pub listen_fd: Option<RawFd>, // Pre-bound socket from systemd socket activation
     trigger_cooldowns: Arc<tokio::sync::Mutex<HashMap<String, std::time::Instant>>>,
+    http_client: reqwest::Client, // Shared by webhook triggers for connection reuse
+    compiled: CompiledConfig,
+    pending_moves: Arc<std::sync::Mutex<HashMap<u32, SecurityEvent>>>, // MOVED_FROM halves by cookie
+    fanotify: Option<FanotifyMonitor>, // Device watches with process attribution (use_fanotify)
+    device_scans: Option<tokio::sync::mpsc::Receiver<Vec<PathBuf>>>, // Devices present at each rescan, watched by the inotify loop
+    known_devices: HashSet<PathBuf>, // Devices watched at startup or by a rescan, forgotten once they disappear
+    lost_watches: Vec<Arc<WatchConfig>>, // Configured paths deleted since, watched again when they reappear
+    derived_sender: tokio::sync::mpsc::Sender<SecurityEvent>, // Events synthesized outside the inotify loop: correlation, cooldown summaries, move-outs
+    derived_events: Option<tokio::sync::mpsc::Receiver<SecurityEvent>>, // Dispatched by the inotify loop like its own events
+    debounce: HashMap<(PathBuf, &'static str), DebounceEntry>,
+    path_cooldowns: Arc<std::sync::Mutex<HashMap<(String, PathBuf), SuppressedEvents>>>,
+    throttle: std::sync::Mutex<Throttle>, // [throttle], applied before dispatch
+    metrics: Arc<Metrics>,
+    trigger_tasks: std::sync::Mutex<tokio::task::JoinSet<()>>, // run_async triggers, waited for on shutdown
+    storage_writer: Option<std::thread::JoinHandle<()>>,
+    stopping: tokio::sync::watch::Sender<bool>, // Set on shutdown; the storage writer drains and exits
 }
 
 impl SecurityMonitor {
     pub fn new(config: Config) -> Result<Self> {
-        let (event_sender, event_receiver) = broadcast::channel(100);
+        let (event_sender, event_receiver) = broadcast::channel(config.daemon.event_capacity()?);
         let inotify = Inotify::init().context("Failed to initialize inotify")?;
         let socket_path = config.socket_path.clone();
 
+        let compiled = CompiledConfig::new(&config)?;
+        let throttle = Throttle::new(&config.throttle);
+        let config = Arc::new(config);
+        let (config_updates, _) = tokio::sync::watch::channel(Arc::clone(&config));
+        let (derived_sender, derived_events) = tokio::sync::mpsc::channel(64);
+
         Ok(SecurityMonitor {
-            config: Arc::new(config),
+            config,
+            config_updates,
+            config_path: None,
             event_sender,
             _event_receiver: event_receiver,
             inotify,
             watched_paths: HashMap::new(),
             socket_path,
+            replay: None,
+            listen_fd: None,
             trigger_cooldowns: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            http_client: reqwest::Client::new(),
+            compiled,
+            pending_moves: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            fanotify: None,
+            device_scans: None,
+            known_devices: HashSet::new(),
+            lost_watches: Vec::new(),
+            derived_sender,
+            derived_events: Some(derived_events),
+            debounce: HashMap::new(),
+            path_cooldowns: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            throttle: std::sync::Mutex::new(throttle),
+            metrics: Arc::new(Metrics::default()),
+            trigger_tasks: std::sync::Mutex::new(tokio::task::JoinSet::new()),
+            storage_writer: None,
+            stopping: tokio::sync::watch::channel(false).0,
         })
     }
 
     pub async fn start(&mut self) -> Result<()> {
-        self.setup_watches()?;
+        // Replayed captures stand in for live monitoring, so no watches are needed
+        if self.replay.is_none() {
+            self.setup_watches()?;
+        }
+
+        if self.replay.is_none() && self.config.rescan_interval_seconds > 0 {
+            self.start_device_rescan();
+        }
+
+        if self.replay.is_none() {
+            tokio::spawn(correlation::run(self.event_sender.subscribe(), self.config_updates.subscribe(), self.derived_sender.clone()));
+        }
+
+        if let Some(fanotify) = self.fanotify.take().filter(|fanotify| fanotify.has_marks()) {
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = fanotify.start_monitoring() {
+                    error!("fanotify monitoring error: {:#}", e);
+                }
+            });
+        }
 
         let socket_path = &self.config.socket_path;
-        if std::path::Path::new(socket_path).exists() {
-            // Try to connect to check if it's stale
-            if tokio::net::UnixStream::connect(socket_path).await.is_ok() {
-                return Err(anyhow::anyhow!(
-                    "Another instance is already running on socket: {}", socket_path
-                ));
-            } else {
-                // Socket exists but no one is listening - it's stale, remove it
-                std::fs::remove_file(socket_path)
-                    .context("Failed to remove stale socket")?;
-                info!("Removed stale socket: {}", socket_path);
+        let listener = if let Some(fd) = self.listen_fd {
+            // systemd owns the socket file and its permissions, so just adopt the fd
+            let std_listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+            std_listener.set_nonblocking(true)
+                .context("Failed to set systemd socket to non-blocking")?;
+            let listener = UnixListener::from_std(std_listener)
+                .context("Failed to adopt systemd-activated socket")?;
+
+            info!("Security monitor started, listening on systemd-activated socket (fd {})", fd);
+            listener
+        } else {
+            if std::path::Path::new(socket_path).exists() {
+                // Try to connect to check if it's stale
+                if tokio::net::UnixStream::connect(socket_path).await.is_ok() {
+                    return Err(anyhow::anyhow!(
+                        "Another instance is already running on socket: {}", socket_path
+                    ));
+                } else {
+                    // Socket exists but no one is listening - it's stale, remove it
+                    std::fs::remove_file(socket_path)
+                        .context("Failed to remove stale socket")?;
+                    info!("Removed stale socket: {}", socket_path);
+                }
+            }
+
+            let listener = UnixListener::bind(socket_path)
+                .context("Failed to bind Unix socket")?;
+
+            // Only the owner (and socket_group, if set) may connect unless socket_mode opens it up
+            let mode = self.config.socket_permissions()?;
+            if let Some(group) = &self.config.socket_group {
+                let gid = privileges::group_id(group)?;
+                std::os::unix::fs::chown(socket_path, None, Some(gid))
+                    .with_context(|| format!("Failed to give socket to group {}", group))?;
+            }
+            std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(mode))
+                .context("Failed to set socket permissions")?;
+
+            info!("Security monitor started, listening on {}", socket_path);
+            listener
+        };
+
+        // Checked at load: anything but loopback needs a token
+        let tcp_listener = match self.config.remote.listen_address(&self.config.auth)? {
+            Some(listen) => {
+                let tcp_listener = TcpListener::bind(listen)
+                    .await
+                    .with_context(|| format!("Failed to bind remote listener {}", listen))?;
+                if listen.ip().is_loopback() {
+                    info!("Also serving clients over TCP on {}", listen);
+                } else {
+                    warn!("Serving clients over TCP on {}: connections need the token but are not encrypted", listen);
+                }
+                Some(tcp_listener)
             }
+            None => None,
+        };
+
+        // Everything that may need root is opened before run_as takes effect: inotify and
+        // fanotify (setup_watches), the socket, raw ICMP sockets and the udev monitor
+        let live = self.replay.is_none();
+        let monitor_icmp = self.config.network_ids.enabled && self.config.network_ids.monitor_icmp;
+        let icmp_sockets = if live && monitor_icmp {
+            network_ids::open_icmp_sockets()
+        } else {
+            Vec::new()
+        };
+        if live {
+            let icmp_status = if !monitor_icmp {
+                SubsystemStatus::Disabled
+            } else if icmp_sockets.is_empty() {
+                warn!("No raw ICMP socket could be opened (needs CAP_NET_RAW), pings are counted without source addresses");
+                SubsystemStatus::Failed {
+                    error: "no raw ICMP socket could be opened (needs CAP_NET_RAW); pings are counted system-wide without source addresses".to_string(),
+                }
+            } else {
+                SubsystemStatus::Running
+            };
+            self.metrics.set_subsystem("icmp", icmp_status);
+        }
+        if live {
+            let usb_status = self.start_usb_monitoring().await.unwrap_or_else(|_| SubsystemStatus::Failed {
+                error: "USB monitoring thread exited during setup".to_string(),
+            });
+            self.metrics.set_subsystem("usb", usb_status);
+        }
+        let geoip = if live { GeoIp::load(&self.config.geoip) } else { None };
+
+        // Sinks are opened before run_as too, so their files and ports may be root-only. One
+        // that fails to open is left out, as if it had failed while running.
+        let event_log = match self.config.event_log.clone() {
+            Some(path) => event_log::open(&path).await
+                .map(|file| (path, file))
+                .map_err(|e| error!("Event log error: {:#}", e))
+                .ok(),
+            None => None,
+        };
+        let metrics_listener = match &self.config.metrics.listen {
+            Some(listen) => metrics::bind(listen).await
+                .map_err(|e| error!("Metrics endpoint error: {:#}", e))
+                .ok(),
+            None => None,
+        };
+        let syslog_target = if self.config.syslog.enabled {
+            syslog::Target::connect(self.config.syslog.remote.as_deref()).await
+                .map_err(|e| error!("Syslog forwarding error: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+        let cef_output = if self.config.output.cef {
+            cef::open(&self.config.output, &self.config.syslog).await
+                .map_err(|e| error!("CEF output error: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+        #[cfg(feature = "dbus")]
+        let dbus_connection = if self.config.dbus.export {
+            dbus_export::connect(self.config.dbus.uses_system_bus()?).await
+                .map_err(|e| error!("D-Bus export error: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+        #[cfg(not(feature = "dbus"))]
+        if self.config.dbus.export {
+            warn!("[dbus] export is set, but this build has no D-Bus support (the dbus feature)");
+        }
+        let event_store = match self.config.storage.path.clone() {
+            Some(path) => secmon_core::storage::EventStore::open(&path)
+                .map(|store| (path, store))
+                .map_err(|e| error!("Event store error: {:#}", e))
+                .ok(),
+            None => None,
+        };
+
+        if let Some(user) = &self.config.security.run_as {
+            privileges::drop_to(user)
+                .with_context(|| format!("Failed to switch to run_as user {}, refusing to continue", user))?;
+        }
+
+        // Durable event log, written from its own subscriber so sampling never touches live clients
+        if let Some((event_log_path, file)) = event_log {
+            let receiver = self.event_sender.subscribe();
+            let sampling = self.config.sampling.clone();
+            tokio::spawn(async move {
+                if let Err(e) = event_log::run(event_log_path, file, sampling, receiver).await {
+                    error!("Event log error: {}", e);
+                }
+            });
+        }
+
+        // Counted even without a metrics endpoint, for the `stats` socket command
+        tokio::spawn(metrics::count_events(Arc::clone(&self.metrics), self.event_sender.subscribe()));
+        if let Some(listener) = metrics_listener {
+            let metrics = Arc::clone(&self.metrics);
+            tokio::spawn(async move {
+                if let Err(e) = metrics::serve(listener, metrics).await {
+                    error!("Metrics endpoint error: {:#}", e);
+                }
+            });
+        }
+
+        if let Some(target) = syslog_target {
+            let receiver = self.event_sender.subscribe();
+            let syslog_config = self.config.syslog.clone();
+            tokio::spawn(async move {
+                if let Err(e) = syslog::run(syslog_config, target, receiver).await {
+                    error!("Syslog forwarding error: {:#}", e);
+                }
+            });
+        }
+
+        if let Some(output) = cef_output {
+            let receiver = self.event_sender.subscribe();
+            tokio::spawn(async move {
+                if let Err(e) = cef::run(output, receiver).await {
+                    error!("CEF output error: {:#}", e);
+                }
+            });
+        }
+
+        if let Some(chat_config) = self.config.notifier.chat.clone() {
+            let receiver = self.event_sender.subscribe();
+            tokio::spawn(async move {
+                if let Err(e) = chat_notifier::run(chat_config, receiver).await {
+                    error!("Chat notifier error: {:#}", e);
+                }
+            });
+        }
+
+        #[cfg(feature = "dbus")]
+        if let Some(connection) = dbus_connection {
+            let receiver = self.event_sender.subscribe();
+            tokio::spawn(async move {
+                if let Err(e) = dbus_export::run(connection, receiver).await {
+                    error!("D-Bus export error: {:#}", e);
+                }
+            });
+        }
+
+        // Queryable history for `secmon-client search` and `stats`. SQLite calls block, so
+        // the writer gets its own thread.
+        if let Some((storage_path, store)) = event_store {
+            let receiver = self.event_sender.subscribe();
+            let retention_days = self.config.storage.retention_days;
+            let sampling = self.config.sampling.clone();
+            let stop = self.stopping.subscribe();
+            let runtime = tokio::runtime::Handle::current();
+            self.storage_writer = Some(std::thread::spawn(move || {
+                if let Err(e) = secmon_core::storage::run(store, storage_path, retention_days, sampling, receiver, stop, runtime) {
+                    error!("Event store error: {:#}", e);
+                }
+            }));
         }
 
-        let listener = UnixListener::bind(socket_path)
-            .context("Failed to bind Unix socket")?;
+        // The session boundary goes first, once every sink that persists events is subscribed
+        let started_event = self.create_started_event();
+        info!("{}", started_event.details.description);
+        let _ = self.event_sender.send(started_event);
 
-        // Set socket permissions to allow all users to connect (when running as root)
-        if let Err(e) = std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o666)) {
-            warn!("Failed to set socket permissions (may not work for non-root users): {}", e);
+        // Monitors that failed during setup are reported now that the event log, syslog
+        // and store are subscribed; clients can ask for them with the status command
+        for (subsystem, status) in self.metrics.subsystems() {
+            if let SubsystemStatus::Failed { error } = status {
+                let _ = self.event_sender.send(Self::create_degraded_event(&subsystem, &error));
+            }
         }
 
-        info!("Security monitor started, listening on {}", socket_path);
+        // Watches are in place and the socket is accepting, so tell systemd we're up
+        systemd::notify("READY=1");
 
-        let event_sender_socket = self.event_sender.clone();
+        let reloader = match (&self.config_path, &self.replay) {
+            (Some(config_path), None) => Some(Arc::new(ConfigReloader {
+                config_path: config_path.clone(),
+                config_updates: self.config_updates.clone(),
+            })),
+            _ => None,
+        };
+        let context = Arc::new(ClientContext {
+            event_sender: self.event_sender.clone(),
+            metrics: Arc::clone(&self.metrics),
+            reloader: reloader.clone(),
+            started_at: std::time::Instant::now(),
+            config_updates: self.config_updates.subscribe(),
+            disconnect_slow_clients: self.config.daemon.disconnects_slow_clients()?,
+        });
+        if let Some(tcp_listener) = tcp_listener {
+            tokio::spawn(Self::handle_tcp_connections(tcp_listener, Arc::clone(&context)));
+        }
         let socket_task = tokio::spawn(async move {
-            Self::handle_socket_connections(listener, event_sender_socket).await
+            Self::handle_socket_connections(listener, context).await
         });
 
+        if let Some(replay) = self.replay.clone() {
+            info!("Replay mode: live monitoring disabled, replaying {}", replay.path.display());
+
+            tokio::select! {
+                result = socket_task => {
+                    if let Err(e) = result {
+                        error!("Socket task error: {}", e);
+                    }
+                },
+                result = self.replay_events(&replay) => {
+                    if let Err(e) = result {
+                        error!("Replay error: {}", e);
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        if let Some(reloader) = reloader {
+            tokio::spawn(async move {
+                if let Err(e) = reloader.handle_signals().await {
+                    error!("Config reload handler error: {}", e);
+                }
+            });
+        }
+
+        // The socket server and the filesystem loop run for the daemon's lifetime; the other
+        // monitors may stop early (disabled, or missing permissions) without taking it down
+        let mut monitor_tasks = tokio::task::JoinSet::new();
+
         // Start network monitoring
         let event_sender_network = self.event_sender.clone();
-        let network_task = tokio::spawn(async move {
-            let mut network_monitor = NetworkMonitor::new(event_sender_network);
+        let network_config = self.config.network_monitor.clone();
+        let network_updates = self.config_updates.subscribe();
+        let network_geoip = geoip.clone();
+        let network_metrics = Arc::clone(&self.metrics);
+        monitor_tasks.spawn(async move {
+            let mut network_monitor = match NetworkMonitor::new(event_sender_network.clone(), &network_config, network_updates, network_geoip) {
+                Ok(monitor) => monitor,
+                Err(e) => {
+                    error!("Network monitoring disabled: {:#}", e);
+                    Self::report_degraded(&network_metrics, &event_sender_network, "network", format!("{:#}", e));
+                    return;
+                }
+            };
+            network_metrics.set_subsystem("network", SubsystemStatus::Running);
             if let Err(e) = network_monitor.start_monitoring().await {
                 error!("Network monitoring error: {}", e);
+                Self::report_degraded(&network_metrics, &event_sender_network, "network", e.to_string());
             }
         });
 
-        // Start USB monitoring in a separate task using spawn_blocking
-        let event_sender_usb = self.event_sender.clone();
-        let usb_task = tokio::task::spawn_blocking(move || {
-            let rt = tokio::runtime::Handle::current();
-            rt.block_on(async {
-                let usb_monitor_result = UsbMonitor::new(event_sender_usb);
-                match usb_monitor_result {
-                    Ok(mut usb_monitor) => {
-                        if let Err(e) = usb_monitor.start_monitoring().await {
-                            error!("USB monitoring error: {}", e);
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Failed to initialize USB monitoring (may require root): {}", e);
-                    }
-                }
-            })
-        });
-
         // Start Network IDS monitoring (if enabled)
         let event_sender_ids = self.event_sender.clone();
         let ids_config = self.config.network_ids.clone();
-        let ids_task = tokio::spawn(async move {
+        let ids_updates = self.config_updates.subscribe();
+        let ids_metrics = Arc::clone(&self.metrics);
+        monitor_tasks.spawn(async move {
             if ids_config.enabled {
+                ids_metrics.set_subsystem("network_ids", SubsystemStatus::Running);
                 let mut network_ids = NetworkIDS::new(
-                    event_sender_ids,
-                    ids_config.port_scan_threshold,
-                    ids_config.scan_window_seconds,
-                    ids_config.ping_threshold
+                    event_sender_ids.clone(),
+                    &ids_config,
+                    ids_updates,
+                    icmp_sockets,
+                    geoip,
                 );
                 if let Err(e) = network_ids.start_monitoring().await {
                     error!("Network IDS monitoring error: {}", e);
+                    Self::report_degraded(&ids_metrics, &event_sender_ids, "network_ids", e.to_string());
                 }
             } else {
                 info!("Network IDS monitoring disabled in configuration");
+                ids_metrics.set_subsystem("network_ids", SubsystemStatus::Disabled);
             }
         });
 
@@ -185,58 +716,164 @@ impl SecurityMonitor {
             Ok::<(), anyhow::Error>(())
         };
 
-        // Wait for all tasks - using select to handle them concurrently
-        tokio::select! {
-            result = socket_task => {
-                if let Err(e) = result {
-                    error!("Socket task error: {}", e);
-                }
-            },
-            result = network_task => {
-                if let Err(e) = result {
-                    error!("Network task error: {}", e);
-                }
-            },
-            result = usb_task => {
-                if let Err(e) = result {
-                    error!("USB task error: {}", e);
-                }
-            },
-            result = ids_task => {
-                if let Err(e) = result {
-                    error!("Network IDS task error: {}", e);
-                }
-            },
-            result = filesystem_task => {
-                if let Err(e) = result {
-                    error!("Filesystem task error: {}", e);
+        tokio::pin!(filesystem_task);
+        let mut socket_task = socket_task;
+        loop {
+            tokio::select! {
+                result = &mut socket_task => {
+                    if let Err(e) = result {
+                        error!("Socket task error: {}", e);
+                    }
+                    break;
+                },
+                result = &mut filesystem_task => {
+                    if let Err(e) = result {
+                        error!("Filesystem task error: {}", e);
+                    }
+                    break;
+                },
+                Some(result) = monitor_tasks.join_next() => {
+                    if let Err(e) = result {
+                        error!("Monitor task error: {}", e);
+                    }
                 }
             }
         }
         Ok(())
     }
 
-    fn setup_watches(&mut self) -> Result<()> {
-        let watches = self.config.watches.clone();
-        for watch_config in &watches {
-            if !watch_config.enabled {
-                debug!("Skipping disabled watch: {}", watch_config.path);
-                continue;
+    /// Swap in a reloaded config: drop watches whose entry was removed or changed, add
+    /// watches for new or changed entries, and switch triggers, rules and thresholds
+    fn apply_config(&mut self, config: Arc<Config>) {
+        let compiled = match CompiledConfig::new(&config) {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                error!("Config reload failed, keeping current configuration: {:#}", e);
+                return;
             }
+        };
 
-            if watch_config.auto_discover {
-                self.setup_auto_discovered_watches(watch_config)?;
-            } else if watch_config.pattern {
-                self.setup_pattern_watches(watch_config)?;
-            } else {
-                self.setup_single_watch(&watch_config.path, &watch_config.description)?;
+        let old_config = std::mem::replace(&mut self.config, Arc::clone(&config));
+        self.compiled = compiled;
+        if old_config.throttle != config.throttle {
+            self.throttle = std::sync::Mutex::new(Throttle::new(&config.throttle));
+        }
+
+        let removed: Vec<&WatchConfig> = old_config.watches.iter()
+            .filter(|watch| !config.watches.contains(watch))
+            .collect();
+        let added: Vec<&WatchConfig> = config.watches.iter()
+            .filter(|watch| !old_config.watches.contains(watch))
+            .collect();
+
+        let stale: Vec<WatchDescriptor> = self.watched_paths.iter()
+            .filter(|(_, watched)| removed.contains(&watched.watch.as_ref()))
+            .map(|(wd, _)| wd.clone())
+            .collect();
+        for wd in stale {
+            if let Some(watched) = self.watched_paths.remove(&wd) {
+                debug!("Removing watch for {}", watched.path.display());
+            }
+            if let Err(e) = self.inotify.watches().remove(wd) {
+                debug!("Failed to remove watch: {}", e);
+            }
+        }
+
+        for watch in &added {
+            if let Err(e) = self.setup_watch(watch) {
+                warn!("Failed to add watch for {}: {:#}", watch.path, e);
+            }
+        }
+
+        // These are only read at startup
+        if old_config.socket_path != config.socket_path
+            || old_config.socket_mode != config.socket_mode
+            || old_config.socket_group != config.socket_group
+            || old_config.event_log != config.event_log
+            || old_config.syslog != config.syslog
+            || old_config.output != config.output
+            || old_config.dbus != config.dbus
+            || old_config.notifier != config.notifier
+            || old_config.metrics != config.metrics
+            || old_config.daemon != config.daemon
+            || old_config.use_fanotify != config.use_fanotify
+            || old_config.inotify_buffer_size != config.inotify_buffer_size
+            || old_config.network_ids.enabled != config.network_ids.enabled
+            || old_config.network_ids.monitor_icmp != config.network_ids.monitor_icmp
+            || old_config.security != config.security
+            || old_config.pid_file != config.pid_file
+            || old_config.log_file != config.log_file
+            || old_config.geoip != config.geoip
+            || old_config.remote != config.remote
+        {
+            warn!("socket_path, socket_mode, socket_group, pid_file, log_file, event_log, syslog, output, dbus, notifier, metrics, remote, daemon, security, geoip, use_fanotify, inotify_buffer_size, network_ids.enabled and network_ids.monitor_icmp changes take effect after a restart");
+        }
+
+        let network_changed = old_config.network_ids != config.network_ids
+            || old_config.network_monitor != config.network_monitor;
+
+        let mut metadata = HashMap::new();
+        metadata.insert("watches_added".to_string(), added.len().to_string());
+        metadata.insert("watches_removed".to_string(), removed.len().to_string());
+        metadata.insert("triggers".to_string(), config.triggers.len().to_string());
+        metadata.insert("network_settings_changed".to_string(), network_changed.to_string());
+
+        let description = format!(
+            "Configuration reloaded: {} watches added, {} removed, {} triggers{}",
+            added.len(),
+            removed.len(),
+            config.triggers.len(),
+            if network_changed { ", network settings updated" } else { "" }
+        );
+        info!("{}", description);
+
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::CustomMessage,
+            path: PathBuf::from(self.config_path.as_deref().unwrap_or("")),
+            details: EventDetails {
+                severity: Severity::Low,
+                description,
+                metadata,
+            },
+        };
+        let _ = self.event_sender.send(event);
+    }
+
+    fn setup_watches(&mut self) -> Result<()> {
+        if self.config.use_fanotify {
+            match FanotifyMonitor::new(self.event_sender.clone(), self.config_updates.subscribe()) {
+                Ok(fanotify) => self.fanotify = Some(fanotify),
+                Err(e) => warn!("fanotify unavailable, using inotify for device watches: {:#}", e),
             }
         }
 
+        let watches = self.config.watches.clone();
+        for watch_config in &watches {
+            self.setup_watch(watch_config)?;
+        }
+
         Ok(())
     }
 
-    fn setup_auto_discovered_watches(&mut self, watch_config: &WatchConfig) -> Result<()> {
+    fn setup_watch(&mut self, watch_config: &WatchConfig) -> Result<()> {
+        if !watch_config.enabled {
+            debug!("Skipping disabled watch: {}", watch_config.path);
+            return Ok(());
+        }
+
+        let watch = Arc::new(watch_config.clone());
+        if watch_config.auto_discover {
+            self.setup_auto_discovered_watches(&watch)
+        } else if watch_config.pattern {
+            self.setup_pattern_watches(&watch)
+        } else {
+            self.setup_single_watch(&watch_config.path, &watch_config.description, &watch)
+        }
+    }
+
+    fn setup_auto_discovered_watches(&mut self, watch_config: &Arc<WatchConfig>) -> Result<()> {
         // Use device discovery for auto-discovery patterns
         if watch_config.path.contains("video") {
             let video_devices = DeviceDiscovery::discover_video_devices()
@@ -248,7 +885,8 @@ impl SecurityMonitor {
             for device in video_devices {
                 self.setup_single_watch(
                     &device.to_string_lossy(),
-                    &format!("Auto-discovered video device: {}", device.display())
+                    &format!("Auto-discovered video device: {}", device.display()),
+                    watch_config,
                 )?;
             }
         }
@@ -263,7 +901,8 @@ impl SecurityMonitor {
             for device in audio_devices {
                 self.setup_single_watch(
                     &device.to_string_lossy(),
-                    &format!("Auto-discovered audio device: {}", device.display())
+                    &format!("Auto-discovered audio device: {}", device.display()),
+                    watch_config,
                 )?;
             }
         }
@@ -271,7 +910,136 @@ impl SecurityMonitor {
         Ok(())
     }
 
-    fn setup_pattern_watches(&mut self, watch_config: &WatchConfig) -> Result<()> {
+    /// libudev handles aren't Send, so USB monitoring gets its own thread and runtime.
+    /// A plain thread (not spawn_blocking) doesn't hold up or outlive the main runtime.
+    /// The returned receiver completes once the udev socket is open or setup failed.
+    fn start_usb_monitoring(&self) -> tokio::sync::oneshot::Receiver<SubsystemStatus> {
+        let (ready_sender, ready) = tokio::sync::oneshot::channel();
+        let event_sender_usb = self.event_sender.clone();
+        let usb_updates = self.config_updates.subscribe();
+        std::thread::spawn(move || {
+            let rt = match tokio::runtime::Builder::new_current_thread().enable_time().build() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    error!("Failed to start USB monitoring runtime: {}", e);
+                    return;
+                }
+            };
+            rt.block_on(async {
+                let usb_monitor_result = UsbMonitor::new(event_sender_usb, usb_updates);
+                match usb_monitor_result {
+                    Ok(mut usb_monitor) => {
+                        if let Err(e) = usb_monitor.start_monitoring(ready_sender).await {
+                            error!("USB monitoring error: {}", e);
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to initialize USB monitoring (may require root): {}", e);
+                        let _ = ready_sender.send(SubsystemStatus::Failed { error: format!("{:#}", e) });
+                    }
+                }
+            })
+        });
+        ready
+    }
+
+    /// Periodically look for plugged-in cameras and microphones. The task only scans; the
+    /// inotify loop gets each scan's devices over a channel and watches new ones itself
+    /// (see `watch_rescanned_devices`), so they go through the same setup as configured paths.
+    fn start_device_rescan(&mut self) {
+        let (scan_tx, scan_rx) = tokio::sync::mpsc::channel(1);
+        self.device_scans = Some(scan_rx);
+
+        // Devices already watched (or marked for fanotify) by setup_watches
+        self.known_devices = self.watched_paths.values()
+            .map(|watched| watched.path.clone())
+            .chain(self.fanotify.iter().flat_map(|fanotify| fanotify.marked_paths().cloned()))
+            .collect();
+        let config_updates = self.config_updates.subscribe();
+        let interval = std::time::Duration::from_secs(self.config.rescan_interval_seconds);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await; // The initial scan happened in setup_watches
+
+            loop {
+                ticker.tick().await;
+
+                // Reloads can add or remove auto_discover watches
+                let auto_discover = config_updates.borrow().watches.iter().any(|watch| watch.enabled && watch.auto_discover);
+                if !auto_discover {
+                    continue;
+                }
+
+                match DeviceDiscovery::discover_all_monitored_paths() {
+                    Ok(devices) => {
+                        if scan_tx.send(devices).await.is_err() {
+                            return; // Monitor loop is gone
+                        }
+                    }
+                    Err(e) => warn!("Device rescan failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Watch devices from a rescan that aren't watched yet, routed to the `auto_discover`
+    /// watch for their kind, and forget ones that are gone so a replugged device is
+    /// watched again
+    async fn watch_rescanned_devices(&mut self, present: Vec<PathBuf>) {
+        self.known_devices.retain(|device| present.contains(device));
+
+        for device in present {
+            if self.known_devices.contains(&device) {
+                continue;
+            }
+
+            // Same routing as setup_auto_discovered_watches
+            let device_str = device.to_string_lossy().to_string();
+            let is_video = device_str.contains("video") || device_str.contains("v4l");
+            let Some(watch) = self.config.watches.iter().find(|watch| {
+                watch.enabled && watch.auto_discover && if is_video {
+                    watch.path.contains("video")
+                } else {
+                    watch.path.contains("snd") || watch.path.contains("pulse")
+                }
+            }) else {
+                continue;
+            };
+            let watch = Arc::new(watch.clone());
+
+            // Attempted once per appearance, so a device that can't be watched isn't retried every scan
+            self.known_devices.insert(device.clone());
+            let kind = if is_video { "video" } else { "audio" };
+            if let Err(e) = self.setup_single_watch(&device_str, &format!("New {} device: {}", kind, device.display()), &watch) {
+                warn!("Failed to add watch for new device {}: {:#}", device.display(), e);
+                continue;
+            }
+            if !self.watched_paths.values().any(|watched| watched.path == device) {
+                continue; // Gone again, or the watch limit was reached
+            }
+            info!("Added watch for new device: {}", device.display());
+
+            let mut metadata = HashMap::new();
+            metadata.insert("source".to_string(), "device_rescan".to_string());
+            metadata.insert("device_kind".to_string(), kind.to_string());
+
+            let event = SecurityEvent {
+                id: Uuid::new_v4(),
+                timestamp: Utc::now(),
+                event_type: EventType::CustomMessage,
+                path: device.clone(),
+                details: EventDetails {
+                    severity: Severity::Low,
+                    description: format!("Now watching new {} device: {}", kind, device.display()),
+                    metadata,
+                },
+            };
+            self.dispatch_event(event).await;
+        }
+    }
+
+    fn setup_pattern_watches(&mut self, watch_config: &Arc<WatchConfig>) -> Result<()> {
         // Use glob to expand patterns
         match glob::glob(&watch_config.path) {
             Ok(paths) => {
@@ -282,7 +1050,8 @@ impl SecurityMonitor {
                             found_any = true;
                             self.setup_single_watch(
                                 &path.to_string_lossy(),
-                                &format!("Pattern-matched: {} ({})", watch_config.description, path.display())
+                                &format!("Pattern-matched: {} ({})", watch_config.description, path.display()),
+                                watch_config,
                             )?;
                         }
                         Err(e) => {
@@ -303,77 +1072,564 @@ impl SecurityMonitor {
         Ok(())
     }
 
-    fn setup_single_watch(&mut self, path_str: &str, description: &str) -> Result<()> {
+    fn setup_single_watch(&mut self, path_str: &str, description: &str, watch: &Arc<WatchConfig>) -> Result<()> {
         let path = Path::new(path_str);
         if !path.exists() {
             debug!("Watch path does not exist: {} ({})", path_str, description);
             return Ok(());
         }
 
-        let mask = WatchMask::MODIFY
-            | WatchMask::CREATE
-            | WatchMask::DELETE
-            | WatchMask::ACCESS
-            | WatchMask::OPEN;
+        // Device files go to fanotify when available so events name the accessing process
+        if let Some(fanotify) = self.fanotify.as_mut() {
+            let is_device = std::fs::metadata(path)
+                .map(|metadata| metadata.file_type().is_char_device() || metadata.file_type().is_block_device())
+                .unwrap_or(false);
+            if is_device {
+                match fanotify.add_path(path) {
+                    Ok(()) => {
+                        info!("Added fanotify watch for: {} ({})", path_str, description);
+                        return Ok(());
+                    }
+                    Err(e) => warn!("{:#}, falling back to inotify", e),
+                }
+            }
+        }
 
-        let wd = self.inotify.watches().add(&path, mask)
-            .with_context(|| format!("Failed to add watch for {}", path_str))?;
+        if watch.recursive && path.is_dir() {
+            let added = self.add_recursive_watches(path, watch);
+            info!("Added {} recursive watches for: {} ({})", added, path_str, description);
+            return Ok(());
+        }
 
-        self.watched_paths.insert(wd, path.to_path_buf());
+        if !self.add_watch(path, watch)? {
+            return Ok(());
+        }
         info!("Added watch for: {} ({})", path_str, description);
 
         Ok(())
     }
 
+    /// Watch configured paths that were deleted again once they exist. Entries dropped
+    /// or changed by a reload are forgotten; the reload sets those up itself.
+    fn restore_lost_watches(&mut self) {
+        for watch in std::mem::take(&mut self.lost_watches) {
+            if !self.config.watches.contains(&watch) {
+                continue;
+            }
+            if !Path::new(&watch.path).exists() {
+                self.lost_watches.push(watch);
+                continue;
+            }
+            match self.setup_single_watch(&watch.path, &watch.description, &watch) {
+                Ok(()) => info!("Watched path {} reappeared, watching it again", watch.path),
+                Err(e) => warn!("Failed to watch {} again: {:#}", watch.path, e),
+            }
+        }
+    }
+
+    /// Add a watch on `path`. Returns Ok(false) when the watch cap or the kernel's
+    /// inotify watch limit has been reached, so callers can stop walking a tree.
+    fn add_watch(&mut self, path: &Path, watch: &Arc<WatchConfig>) -> Result<bool> {
+        if self.watched_paths.len() >= self.config.max_watches {
+            warn!("Watch limit of {} reached (max_watches), not watching {}", self.config.max_watches, path.display());
+            return Ok(false);
+        }
+
+        let mask = Self::watch_mask_for(watch, path.is_dir())?;
+        match self.inotify.watches().add(path, mask) {
+            Ok(wd) => {
+                self.watched_paths.insert(wd, WatchedPath {
+                    path: path.to_path_buf(),
+                    watch: Arc::clone(watch),
+                });
+                Ok(true)
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ENOSPC) => {
+                warn!(
+                    "inotify watch limit reached while watching {} (raise fs.inotify.max_user_watches)",
+                    path.display()
+                );
+                Ok(false)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to add watch for {}", path.display())),
+        }
+    }
+
+    /// The inotify mask for one watched path. Directories get IN_ONLYDIR, so a directory
+    /// swapped for a file since it was checked isn't watched as one, and DELETE_SELF when
+    /// recursive so removed subdirectories are forgotten.
+    fn watch_mask_for(watch: &WatchConfig, is_dir: bool) -> Result<WatchMask> {
+        let mut mask = watch.watch_mask()?;
+        if is_dir {
+            mask |= WatchMask::ONLYDIR;
+            if watch.recursive {
+                mask |= WatchMask::DELETE_SELF;
+            }
+        }
+        Ok(mask)
+    }
+
+    /// Watch `root` and every directory below it, returning how many watches were added
+    fn add_recursive_watches(&mut self, root: &Path, watch: &Arc<WatchConfig>) -> usize {
+        if self.is_excluded(watch, root) {
+            return 0;
+        }
+
+        // Excluded directories (e.g. `**/.git`) are pruned along with everything below them
+        let directories: Vec<PathBuf> = walkdir::WalkDir::new(root)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|entry| !self.is_excluded(watch, entry.path()))
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    debug!("Skipping unreadable path under {}: {}", root.display(), e);
+                    None
+                }
+            })
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect();
+
+        let mut added = 0;
+        for directory in directories {
+            match self.add_watch(&directory, watch) {
+                Ok(true) => added += 1,
+                Ok(false) => break,
+                Err(e) => debug!("{:#}", e),
+            }
+        }
+
+        added
+    }
+
     async fn monitor_events(&mut self) -> Result<()> {
-        let mut buffer = [0; 4096];
+        // Each event needs up to 16 bytes plus the file name, so never go below one page
+        let mut buffer = vec![0; self.config.inotify_buffer_size.max(4096)];
+        let mut config_updates = self.config_updates.subscribe();
+        // The inotify fd is opened non-blocking; wait for it on the runtime's reactor
+        let inotify_fd = AsyncFd::with_interest(self.inotify.as_raw_fd(), Interest::READABLE)
+            .context("Failed to register inotify with the runtime")?;
+        let mut derived_events = self.derived_events.take();
+        let mut device_scans = self.device_scans.take();
+        let mut lost_watch_retry = tokio::time::interval(LOST_WATCH_RETRY_INTERVAL);
+        lost_watch_retry.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut debounce_flush = tokio::time::interval(DEBOUNCE_FLUSH_INTERVAL);
+        debounce_flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // Pinged from this loop rather than a task of its own, so systemd restarts us if it stalls
+        let mut watchdog_ping = systemd::watchdog_interval().map(|interval| {
+            debug!("Sending systemd watchdog pings every {:?}", interval);
+            tokio::time::interval(interval)
+        });
 
         loop {
-            let events = self.inotify.read_events_blocking(&mut buffer)
-                .context("Failed to read inotify events")?;
+            self.metrics.set_inotify_watches(self.watched_paths.len());
+            let mut ready = tokio::select! {
+                ready = inotify_fd.readable() => ready.context("Failed to poll inotify")?,
+                _ = debounce_flush.tick(), if !self.debounce.is_empty() => {
+                    for flushed in self.flush_debounced(std::time::Instant::now()) {
+                        self.dispatch_event(flushed).await;
+                    }
+                    continue;
+                }
+                _ = lost_watch_retry.tick(), if !self.lost_watches.is_empty() => {
+                    self.restore_lost_watches();
+                    continue;
+                }
+                Ok(()) = config_updates.changed() => {
+                    let config = config_updates.borrow_and_update().clone();
+                    self.apply_config(config);
+                    continue;
+                }
+                Some(event) = async { derived_events.as_mut()?.recv().await } => {
+                    self.dispatch_event(event).await;
+                    continue;
+                }
+                Some(devices) = async { device_scans.as_mut()?.recv().await } => {
+                    self.watch_rescanned_devices(devices).await;
+                    continue;
+                }
+                Some(_) = async { Some(watchdog_ping.as_mut()?.tick().await) } => {
+                    systemd::notify("WATCHDOG=1");
+                    continue;
+                }
+            };
+            let events = match self.inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    ready.clear_ready();
+                    continue;
+                }
+                Err(e) => return Err(e).context("Failed to read inotify events"),
+            };
+
+            // Bursts whose window has closed go out before this batch can extend them
+            let now = std::time::Instant::now();
+            for flushed in self.flush_debounced(now) {
+                self.dispatch_event(flushed).await;
+            }
 
             for event in events {
-                if let Some(watched_path) = self.watched_paths.get(&event.wd) {
-                    let security_event = self.create_security_event(watched_path, &event);
+                if event.mask.contains(inotify::EventMask::Q_OVERFLOW) {
+                    let overflow_event = Self::create_overflow_event();
+                    warn!("{}", overflow_event.details.description);
+                    self.dispatch_event(overflow_event).await;
+                    continue;
+                }
 
-                    debug!("Security event: {:?}", security_event);
+                // The kernel drops the watch itself (IN_IGNORED follows DELETE_SELF, and also comes
+                // alone when a watched file's inode goes away or its filesystem is unmounted);
+                // forget it so the descriptor isn't reused stale
+                if event.mask.intersects(inotify::EventMask::DELETE_SELF | inotify::EventMask::IGNORED) {
+                    if let Some(removed) = self.watched_paths.remove(&event.wd) {
+                        debug!("Watched path removed: {}", removed.path.display());
+                        // A device node that is recreated before the next rescan is still new to it
+                        self.known_devices.remove(&removed.path);
+                        if !removed.watch.pattern && removed.path == Path::new(&removed.watch.path) {
+                            info!("Watched path {} is gone, watching it again if it reappears", removed.path.display());
+                            self.lost_watches.push(removed.watch);
+                        }
+                    }
+                    continue;
+                }
+
+                let Some(watched) = self.watched_paths.get(&event.wd) else {
+                    continue;
+                };
+                let base_path = watched.path.clone();
+                let watch = Arc::clone(&watched.watch);
+
+                let full_path = match event.name {
+                    Some(name) => base_path.join(name),
+                    None => base_path.clone(),
+                };
+                if self.is_excluded(&watch, &full_path) {
+                    continue;
+                }
 
-                    // Check if we should skip this event due to recent similar events (deduplication)
-                    if self.should_process_event(&security_event).await {
-                        // Process triggers for this event
-                        self.process_event_triggers(&security_event).await;
+                // Follow new subdirectories of recursive watches
+                if watch.recursive
+                    && event.mask.intersects(inotify::EventMask::CREATE | inotify::EventMask::MOVED_TO)
+                    && event.mask.contains(inotify::EventMask::ISDIR)
+                {
+                    let added = self.add_recursive_watches(&full_path, &watch);
+                    debug!("Added {} watches for new directory {}", added, full_path.display());
+                }
 
-                        if let Err(e) = self.event_sender.send(security_event) {
-                            error!("Failed to send event: {}", e);
-                        }
-                    } else {
-                        debug!("Skipping duplicate event: {:?}", security_event.event_type);
+                let security_event = if event.mask.intersects(inotify::EventMask::MOVED_FROM | inotify::EventMask::MOVED_TO) {
+                    match self.pair_move_event(&base_path, &watch, &event) {
+                        Some(security_event) => security_event,
+                        None => continue,
                     }
+                } else {
+                    self.create_security_event(&base_path, &watch, &event)
+                };
+
+                let debounce_ms = watch.debounce_ms.unwrap_or(self.config.debounce_ms);
+                if debounce_ms > 0 && !self.debounce_event(&security_event, debounce_ms, now) {
+                    continue;
+                }
+
+                if let Some(cooldown_ms) = watch.event_cooldown_ms {
+                    if !self.check_path_cooldown(&watch.path, cooldown_ms, &security_event) {
+                        debug!("Suppressing event during watch cooldown: {}", security_event.path.display());
+                        continue;
+                    }
+                }
+
+                self.dispatch_event(security_event).await;
+            }
+        }
+    }
+
+    /// Pair IN_MOVED_FROM with IN_MOVED_TO by cookie into a single FileMove event. The
+    /// MOVED_FROM half is held back; if no MOVED_TO arrives in time the file left the
+    /// watched tree and a move-out event is dispatched by the inotify loop instead.
+    fn pair_move_event(&self, base_path: &Path, watch: &WatchConfig, event: &inotify::Event<&std::ffi::OsStr>) -> Option<SecurityEvent> {
+        let mut security_event = self.create_security_event(base_path, watch, event);
+        security_event.event_type = EventType::FileMove;
+        security_event.details.metadata.insert("cookie".to_string(), event.cookie.to_string());
+
+        if event.mask.contains(inotify::EventMask::MOVED_FROM) {
+            security_event.details.description = format!("Moved out of watched tree: {}", security_event.path.display());
+            security_event.details.metadata.insert("old_path".to_string(), security_event.path.display().to_string());
+            self.pending_moves.lock().unwrap().insert(event.cookie, security_event);
+
+            let pending_moves = Arc::clone(&self.pending_moves);
+            let derived_sender = self.derived_sender.clone();
+            let cookie = event.cookie;
+            tokio::spawn(async move {
+                tokio::time::sleep(MOVE_PAIR_TIMEOUT).await;
+
+                let unpaired = pending_moves.lock().unwrap().remove(&cookie);
+                if let Some(mut move_out) = unpaired {
+                    move_out.timestamp = Utc::now();
+                    if derived_sender.send(move_out).await.is_err() {
+                        error!("Failed to queue move-out event");
+                    }
+                }
+            });
+            return None;
+        }
+
+        let new_path = security_event.path.display().to_string();
+        security_event.details.metadata.insert("new_path".to_string(), new_path.clone());
+
+        let moved_from = self.pending_moves.lock().unwrap().remove(&event.cookie);
+        match moved_from {
+            Some(from) => {
+                let old_path = from.path.display().to_string();
+                // Keep whichever side was classified as more sensitive (e.g. moving a key out of ~/.ssh)
+                if from.details.severity.level() > security_event.details.severity.level() {
+                    security_event.details.severity = from.details.severity;
+                }
+                security_event.details.description = format!("Moved: {} -> {}", old_path, new_path);
+                security_event.details.metadata.insert("old_path".to_string(), old_path);
+            }
+            None => {
+                security_event.details.description = format!("Moved into watched tree: {}", new_path);
+            }
+        }
+
+        Some(security_event)
+    }
+
+    /// Excludes match either the absolute path or just the file name, so both
+    /// `/home/*/.cache/**` and `*.swp` work as expected
+    fn is_excluded(&self, watch: &WatchConfig, path: &Path) -> bool {
+        if watch.exclude.is_empty() {
+            return false;
+        }
+
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let file_name = path.file_name().map(Path::new);
+
+        watch.exclude.iter()
+            .filter_map(|exclude| self.compiled.exclude_patterns.get(exclude))
+            .any(|pattern| {
+                pattern.matches_path_with(path, options)
+                    || file_name.is_some_and(|name| pattern.matches_path_with(name, options))
+            })
+    }
+
+    /// Returns false for a repeat of the same event type on the same path within
+    /// `window_ms` of the first event of its burst, which is reported at once. Repeats are
+    /// held back and reported by `flush_debounced` once the window closes; call that with
+    /// the same `now` first so a closed window isn't mistaken for an open one.
+    fn debounce_event(&mut self, event: &SecurityEvent, window_ms: u64, now: std::time::Instant) -> bool {
+        let key = (event.path.clone(), event.event_type.name());
+
+        if let Some(entry) = self.debounce.get_mut(&key) {
+            if now.duration_since(entry.reported_at) < entry.window {
+                entry.suppressed += 1;
+                entry.last = Some(event.clone());
+                return false;
+            }
+        }
+
+        self.debounce.insert(key, DebounceEntry {
+            reported_at: now,
+            window: std::time::Duration::from_millis(window_ms),
+            suppressed: 0,
+            last: None,
+        });
+        true
+    }
+
+    /// Take the bursts whose window closed by `now`. Each is reported as its latest repeat,
+    /// with `count` covering the whole burst including the event reported when it began.
+    fn flush_debounced(&mut self, now: std::time::Instant) -> Vec<SecurityEvent> {
+        let mut flushed = Vec::new();
+        self.debounce.retain(|_, entry| {
+            if now.duration_since(entry.reported_at) < entry.window {
+                return true;
+            }
+            if let Some(mut event) = entry.last.take() {
+                event.details.metadata.insert("count".to_string(), (entry.suppressed + 1).to_string());
+                flushed.push(event);
+            }
+            false
+        });
+        flushed
+    }
+
+    /// Returns false while `event.path` is inside the cooldown of its watch. The first event
+    /// opens the window; anything suppressed during it is reported as one summary at expiry,
+    /// dispatched by the inotify loop so throttling and triggers apply to it.
+    fn check_path_cooldown(&self, watch_path: &str, cooldown_ms: u64, event: &SecurityEvent) -> bool {
+        let key = (watch_path.to_string(), event.path.clone());
+
+        {
+            let mut cooldowns = self.path_cooldowns.lock().unwrap();
+            if let Some(suppressed) = cooldowns.get_mut(&key) {
+                suppressed.count += 1;
+                suppressed.last = Some(event.clone());
+                return false;
+            }
+            cooldowns.insert(key.clone(), SuppressedEvents::default());
+        }
+
+        let path_cooldowns = Arc::clone(&self.path_cooldowns);
+        let derived_sender = self.derived_sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(cooldown_ms)).await;
+
+            let expired = path_cooldowns.lock().unwrap().remove(&key);
+            if let Some(SuppressedEvents { count, last: Some(mut summary) }) = expired {
+                summary.details.description = format!(
+                    "{} events suppressed for {} during {}ms cooldown (last: {})",
+                    count, summary.path.display(), cooldown_ms, summary.details.description
+                );
+                summary.details.metadata.insert("suppressed_count".to_string(), count.to_string());
+                summary.details.metadata.insert("cooldown_ms".to_string(), cooldown_ms.to_string());
+                summary.timestamp = Utc::now();
+
+                if derived_sender.send(summary).await.is_err() {
+                    error!("Failed to queue cooldown summary event");
                 }
             }
+        });
+
+        true
+    }
+
+    async fn dispatch_event(&self, mut security_event: SecurityEvent) {
+        debug!("Security event: {:?}", security_event);
+
+        // Thinned out before anything sees it: triggers, clients and persistence alike
+        if !self.throttle.lock().unwrap().admit(&mut security_event) {
+            debug!("Throttled event: {:?}", security_event.event_type);
+            return;
+        }
+
+        // Check if we should skip this event due to recent similar events (deduplication)
+        if self.should_process_event(&security_event).await {
+            // Process triggers for this event
+            self.process_event_triggers(&security_event).await;
+
+            if let Err(e) = self.event_sender.send(security_event) {
+                error!("Failed to send event: {}", e);
+            }
+        } else {
+            debug!("Skipping duplicate event: {:?}", security_event.event_type);
         }
     }
 
-    fn create_security_event(&self, base_path: &Path, event: &inotify::Event<&std::ffi::OsStr>) -> SecurityEvent {
+    async fn replay_events(&self, options: &ReplayOptions) -> Result<()> {
+        let recorded = replay::load_events(&options.path)?;
+        if recorded.is_empty() {
+            warn!("Replay file contains no events: {}", options.path.display());
+            return Ok(());
+        }
+
+        info!(
+            "Replaying {} events at {}x speed{}",
+            recorded.len(),
+            options.speed,
+            if options.looping { " (looping)" } else { "" }
+        );
+
+        let mut emitted = 0usize;
+        loop {
+            let mut previous: Option<DateTime<Utc>> = None;
+
+            for original in &recorded {
+                if options.count.is_some_and(|limit| emitted >= limit) {
+                    info!("Replay stopped after {} events", emitted);
+                    return Ok(());
+                }
+
+                // Preserve the original spacing between events, scaled by the replay speed
+                if let Some(previous) = previous {
+                    let gap = (original.timestamp - previous).to_std().unwrap_or_default();
+                    tokio::time::sleep(gap.div_f64(options.speed)).await;
+                }
+                previous = Some(original.timestamp);
+
+                // Each emission is a new event, so ids stay unique when the capture is replayed
+                // again or looped; the original's is kept alongside its time
+                let mut event = original.clone();
+                event.details.metadata.insert("replayed_from".to_string(), original.timestamp.to_rfc3339());
+                event.details.metadata.insert("replayed_id".to_string(), original.id.to_string());
+                event.id = Uuid::new_v4();
+                event.timestamp = Utc::now();
+
+                self.dispatch_event(event).await;
+                emitted += 1;
+            }
+
+            if !options.looping {
+                break;
+            }
+        }
+
+        info!("Replay finished after {} events", emitted);
+        Ok(())
+    }
+
+    fn create_security_event(&self, base_path: &Path, watch: &WatchConfig, event: &inotify::Event<&std::ffi::OsStr>) -> SecurityEvent {
         let full_path = if let Some(name) = event.name {
             base_path.join(name)
         } else {
             base_path.to_path_buf()
         };
 
-        let (event_type, severity, description) = self.classify_event(base_path, &full_path, event.mask);
+        let (mut event_type, mut severity, mut description) = Self::classify_event(base_path, &full_path, event.mask);
 
         let mut metadata = HashMap::new();
         metadata.insert("mask".to_string(), format!("{:?}", event.mask));
 
+        // Configured rules take precedence over the built-in heuristics; first match wins
+        let path_str = full_path.to_string_lossy();
+        if let Some(rule) = self.compiled.classification_rules.iter().find(|rule| rule.path_regex.is_match(&path_str)) {
+            if let Some(rule_type) = &rule.event_type {
+                event_type = rule_type.clone();
+            }
+            severity = rule.severity.clone();
+            if let Some(rule_description) = &rule.description {
+                description = format!("{}: {}", rule_description, full_path.display());
+            }
+            metadata.insert("classification_rule".to_string(), rule.path_regex.as_str().to_string());
+        }
+
+        // A watch's severity raises its events to at least that level; checked at load
+        if let Ok(Some(floor)) = watch.severity_floor() {
+            if floor.level() > severity.level() {
+                severity = floor;
+            }
+        }
+        metadata.insert("watch".to_string(), watch.path.clone());
+
         if let Some(name) = event.name {
             metadata.insert("filename".to_string(), name.to_string_lossy().to_string());
         }
 
+        if event.mask.contains(inotify::EventMask::ISDIR) {
+            metadata.insert("is_dir".to_string(), "true".to_string());
+        }
+
+        // PathBuf only serializes when it is valid UTF-8, and a lossy rendering can hide what
+        // is really on disk, so keep the exact bytes alongside the displayable path
+        let path = match full_path.to_str() {
+            Some(_) => full_path,
+            None => {
+                let raw: String = full_path.as_os_str().as_bytes().iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                metadata.insert("path_raw".to_string(), raw);
+                metadata.insert("path_lossy".to_string(), "true".to_string());
+                PathBuf::from(full_path.to_string_lossy().into_owned())
+            }
+        };
+
         SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
             event_type,
-            path: full_path,
+            path,
             details: EventDetails {
                 severity,
                 description,
@@ -382,7 +1638,147 @@ impl SecurityMonitor {
         }
     }
 
-    fn classify_event(&self, base_path: &Path, full_path: &Path, mask: inotify::EventMask) -> (EventType, Severity, String) {
+    /// A monitor that failed to start (or stopped): the daemon keeps running without it
+    fn create_degraded_event(subsystem: &str, error: &str) -> SecurityEvent {
+        let path = match subsystem {
+            "usb" => "/sys/bus/usb",
+            "icmp" => "/proc/net/snmp",
+            _ => "/proc/net",
+        };
+
+        let mut metadata = HashMap::new();
+        metadata.insert("subsystem".to_string(), subsystem.to_string());
+        metadata.insert("error".to_string(), error.to_string());
+
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::MonitorDegraded,
+            path: PathBuf::from(path),
+            details: EventDetails {
+                severity: Severity::Medium,
+                description: format!("{} monitoring is degraded: {}", subsystem, error),
+                metadata,
+            },
+        }
+    }
+
+    /// DaemonStarted: what this session runs with, for auditing which config was in effect
+    fn create_started_event(&self) -> SecurityEvent {
+        let config = &self.config;
+        let live = self.replay.is_none();
+        // usb and icmp have reported their status by now; network starts right after this
+        let mut subsystems: Vec<String> = Vec::new();
+        if live {
+            subsystems.push("filesystem".to_string());
+            if config.use_fanotify {
+                subsystems.push("fanotify".to_string());
+            }
+            subsystems.extend(self.metrics.subsystems().into_iter()
+                .filter(|(_, status)| matches!(status, SubsystemStatus::Running))
+                .map(|(subsystem, _)| subsystem));
+            subsystems.push("network".to_string());
+            if config.network_ids.enabled {
+                subsystems.push("network_ids".to_string());
+            }
+        } else {
+            subsystems.push("replay".to_string());
+        }
+        let sinks = [
+            ("event_log", config.event_log.is_some()),
+            ("storage", config.storage.path.is_some()),
+            ("syslog", config.syslog.enabled),
+            ("cef", config.output.cef),
+            ("dbus", config.dbus.export),
+            ("chat", config.notifier.chat.is_some()),
+            ("metrics", config.metrics.listen.is_some()),
+            ("remote", config.remote.listen.is_some()),
+        ];
+        subsystems.extend(sinks.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name.to_string()));
+
+        let config_path = self.config_path.as_deref().unwrap_or("(built-in defaults)");
+        let mut metadata = HashMap::new();
+        metadata.insert("config_path".to_string(), config_path.to_string());
+        metadata.insert("watches".to_string(), self.watched_paths.len().to_string());
+        metadata.insert("subsystems".to_string(), subsystems.join(","));
+        metadata.insert("version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+        metadata.insert("pid".to_string(), std::process::id().to_string());
+
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::DaemonStarted,
+            path: PathBuf::from(config_path),
+            details: EventDetails {
+                severity: Severity::Low,
+                description: format!(
+                    "secmon {} started with {}: {} watches, {}",
+                    env!("CARGO_PKG_VERSION"),
+                    config_path,
+                    self.watched_paths.len(),
+                    subsystems.join(", ")
+                ),
+                metadata,
+            },
+        }
+    }
+
+    /// DaemonStopped, sent on a graceful shutdown before the sinks are stopped
+    fn create_stopped_event(&self, signal: &str) -> SecurityEvent {
+        let config_path = self.config_path.as_deref().unwrap_or("(built-in defaults)");
+        let mut metadata = HashMap::new();
+        metadata.insert("config_path".to_string(), config_path.to_string());
+        metadata.insert("signal".to_string(), signal.to_string());
+        metadata.insert("version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+        metadata.insert("pid".to_string(), std::process::id().to_string());
+
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::DaemonStopped,
+            path: PathBuf::from(config_path),
+            details: EventDetails {
+                severity: Severity::Low,
+                description: format!("secmon {} stopping on {}", env!("CARGO_PKG_VERSION"), signal),
+                metadata,
+            },
+        }
+    }
+
+    /// Mark a subsystem failed for `status` and broadcast MonitorDegraded for it
+    fn report_degraded(metrics: &Metrics, event_sender: &broadcast::Sender<SecurityEvent>, subsystem: &'static str, error: String) {
+        let _ = event_sender.send(Self::create_degraded_event(subsystem, &error));
+        metrics.set_subsystem(subsystem, SubsystemStatus::Failed { error });
+    }
+
+    /// The kernel doesn't say how many events it dropped, only that its queue
+    /// (max_queued_events long) filled up before we read it
+    fn create_overflow_event() -> SecurityEvent {
+        let queue_path = PathBuf::from("/proc/sys/fs/inotify/max_queued_events");
+        let queue_size = std::fs::read_to_string(&queue_path)
+            .map(|size| size.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("max_queued_events".to_string(), queue_size.clone());
+
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::MonitorOverflow,
+            path: queue_path,
+            details: EventDetails {
+                severity: Severity::High,
+                description: format!(
+                    "inotify queue overflowed: more than {} pending events, an unknown number of filesystem events were lost",
+                    queue_size
+                ),
+                metadata,
+            },
+        }
+    }
+
+    fn classify_event(base_path: &Path, full_path: &Path, mask: inotify::EventMask) -> (EventType, Severity, String) {
         let base_str = base_path.to_string_lossy().to_lowercase();
         let path_str = full_path.to_string_lossy().to_lowercase();
 
@@ -406,6 +1802,19 @@ impl SecurityMonitor {
             );
         }
 
+        // A new directory in a watched tree is classified before the name-based checks below,
+        // so e.g. a freshly created ~/.ssh shows up as a directory rather than a file access
+        if mask.contains(inotify::EventMask::CREATE) && mask.contains(inotify::EventMask::ISDIR) {
+            let hidden = full_path.file_name()
+                .map(|name| name.to_string_lossy().starts_with('.'))
+                .unwrap_or(false);
+            return if hidden {
+                (EventType::DirectoryCreate, Severity::High, format!("Hidden directory created: {}", full_path.display()))
+            } else {
+                (EventType::DirectoryCreate, Severity::Medium, format!("Directory created: {}", full_path.display()))
+            };
+        }
+
         // Check for SSH-related access
         if base_str.contains("ssh") || path_str.contains(".ssh") || path_str.contains("authorized_keys") {
             let severity = if path_str.contains("authorized_keys") || path_str.contains("id_rsa") {
@@ -427,6 +1836,11 @@ impl SecurityMonitor {
             (EventType::FileDelete, Severity::Medium, format!("File deleted: {}", full_path.display()))
         } else if mask.contains(inotify::EventMask::MODIFY) {
             (EventType::FileModify, Severity::Low, format!("File modified: {}", full_path.display()))
+        } else if mask.contains(inotify::EventMask::ISDIR)
+            && (mask.contains(inotify::EventMask::ACCESS) || mask.contains(inotify::EventMask::OPEN))
+        {
+            // Opening or reading a directory means its contents are being listed
+            (EventType::DirectoryAccess, Severity::Low, format!("Directory accessed: {}", full_path.display()))
         } else if mask.contains(inotify::EventMask::ACCESS) || mask.contains(inotify::EventMask::OPEN) {
             (EventType::FileAccess, Severity::Low, format!("File accessed: {}", full_path.display()))
         } else {
@@ -434,15 +1848,15 @@ impl SecurityMonitor {
         }
     }
 
-    async fn handle_socket_connections(listener: UnixListener, event_sender: broadcast::Sender<SecurityEvent>) {
+    async fn handle_socket_connections(listener: UnixListener, context: Arc<ClientContext>) {
         let mut incoming = UnixListenerStream::new(listener);
 
         while let Some(stream) = incoming.next().await {
             match stream {
                 Ok(stream) => {
-                    let receiver = event_sender.subscribe();
-                    let sender_for_client = event_sender.clone();
-                    tokio::spawn(Self::handle_client(stream, receiver, sender_for_client));
+                    let receiver = context.event_sender.subscribe();
+                    let (reader, writer) = stream.into_split();
+                    tokio::spawn(Self::handle_client(reader, writer, receiver, Arc::clone(&context)));
                 }
                 Err(e) => {
                     error!("Failed to accept connection: {}", e);
@@ -451,48 +1865,102 @@ impl SecurityMonitor {
         }
     }
 
-    async fn handle_client(
-        stream: UnixStream,
+    /// `[remote] listen`: the same protocol as the Unix socket, for clients on other hosts
+    async fn handle_tcp_connections(listener: TcpListener, context: Arc<ClientContext>) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    info!("Remote client connected from {}", peer);
+                    let receiver = context.event_sender.subscribe();
+                    let (reader, writer) = stream.into_split();
+                    tokio::spawn(Self::handle_client(reader, writer, receiver, Arc::clone(&context)));
+                }
+                Err(e) => {
+                    error!("Failed to accept remote connection: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn handle_client<R, W>(
+        reader: R,
+        mut writer: W,
         mut receiver: broadcast::Receiver<SecurityEvent>,
-        sender: broadcast::Sender<SecurityEvent>
-    ) {
+        context: Arc<ClientContext>,
+    ) where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut buf_reader = BufReader::new(reader);
+
+        // Nothing is streamed or accepted until the client proves it knows the token
+        let token = context.config_updates.borrow().auth.token.clone();
+        if let Some(token) = token {
+            if let Err(reason) = Self::authenticate(&mut buf_reader, &token).await {
+                warn!("Rejected client: {}", reason);
+                tokio::time::sleep(AUTH_FAILURE_DELAY).await;
+                let response = CommandResponse {
+                    cmd: "auth".to_string(),
+                    ok: false,
+                    error: Some("Authentication failed".to_string()),
+                    data: None,
+                };
+                if let Ok(json) = serde_json::to_string(&response) {
+                    let _ = writer.write_all(format!("{}\n", json).as_bytes()).await;
+                }
+                return;
+            }
+        }
+
         info!("New client connected");
+        context.metrics.client_connected();
 
-        // Split the stream for reading and writing
-        let (reader, writer) = stream.into_split();
-        let mut buf_reader = BufReader::new(reader);
-        let mut writer = writer;
+        // Command responses are written by the write task, in between events
+        let (response_sender, mut responses) = tokio::sync::mpsc::channel::<CommandResponse>(16);
+        let (filter_sender, filter) = tokio::sync::watch::channel(ClientFilter::default());
 
         // Spawn a task to handle incoming messages from client
-        let sender_for_reader = sender.clone();
+        let context_for_reader = Arc::clone(&context);
         let read_task = tokio::spawn(async move {
             let mut line_buffer = String::new();
             loop {
                 line_buffer.clear();
-                match buf_reader.read_line(&mut line_buffer).await {
+                match Self::read_client_line(&mut buf_reader, &mut line_buffer, MAX_CLIENT_LINE).await {
                     Ok(0) => {
                         debug!("Client closed connection");
                         break;
                     }
                     Ok(_) => {
                         let trimmed_line = line_buffer.trim();
-                        if !trimmed_line.is_empty() {
-                            // Try to parse as SecurityEvent
-                            match serde_json::from_str::<SecurityEvent>(trimmed_line) {
-                                Ok(mut event) => {
-                                    // Ensure timestamp is current for received messages
-                                    event.timestamp = Utc::now();
-                                    info!("Received custom event: {:?} - {}", event.event_type, event.details.description);
-
-                                    // Broadcast the received event
-                                    if let Err(e) = sender_for_reader.send(event) {
-                                        error!("Failed to broadcast received event: {}", e);
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to parse received message as SecurityEvent: {} - Message: {}", e, trimmed_line);
+                        if trimmed_line.is_empty() {
+                            continue;
+                        }
+
+                        // Lines with a "cmd" field are commands, anything else is an event to inject
+                        let is_command = serde_json::from_str::<serde_json::Value>(trimmed_line)
+                            .is_ok_and(|value| value.get("cmd").is_some());
+                        if is_command {
+                            let response = Self::handle_command(trimmed_line, &context_for_reader, &filter_sender);
+                            if response_sender.send(response).await.is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+
+                        // Try to parse as SecurityEvent
+                        match serde_json::from_str::<SecurityEvent>(trimmed_line) {
+                            Ok(event) => {
+                                // Keep the sender's timestamp so imported and replayed events keep their original time
+                                info!("Received custom event: {:?} - {}", event.event_type, event.details.description);
+
+                                // Broadcast the received event
+                                if let Err(e) = context_for_reader.event_sender.send(event) {
+                                    error!("Failed to broadcast received event: {}", e);
                                 }
                             }
+                            Err(e) => {
+                                warn!("Failed to parse received message as SecurityEvent: {} - Message: {}", e, trimmed_line);
+                            }
                         }
                     }
                     Err(e) => {
@@ -503,31 +1971,62 @@ impl SecurityMonitor {
             }
         });
 
-        // Handle outgoing events to client
+        // Handle outgoing events and command responses to client
+        let disconnect_slow_clients = context.disconnect_slow_clients;
         let write_task = tokio::spawn(async move {
             loop {
-                match receiver.recv().await {
-                    Ok(event) => {
-                        match serde_json::to_string(&event) {
-                            Ok(json) => {
-                                let message = format!("{}\n", json);
-                                if let Err(e) = writer.write_all(message.as_bytes()).await {
-                                    debug!("Client disconnected while writing: {}", e);
-                                    break;
+                let mut disconnect = false;
+                let message = tokio::select! {
+                    Some(response) = responses.recv() => match serde_json::to_string(&response) {
+                        Ok(json) => json,
+                        Err(e) => {
+                            error!("Failed to serialize command response: {}", e);
+                            continue;
+                        }
+                    },
+                    result = receiver.recv() => match result {
+                        Ok(event) => {
+                            // Filtered before serializing so unwanted events cost nothing
+                            if !filter.borrow().matches(&event) {
+                                continue;
+                            }
+                            match serde_json::to_string(&event) {
+                                Ok(json) => json,
+                                Err(e) => {
+                                    error!("Failed to serialize event: {}", e);
+                                    continue;
                                 }
                             }
-                            Err(e) => {
-                                error!("Failed to serialize event: {}", e);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            if disconnect_slow_clients {
+                                warn!("Client lagging, {} events dropped, disconnecting it", skipped);
+                                disconnect = true;
+                            } else {
+                                warn!("Client lagging, {} events dropped", skipped);
+                            }
+                            // Tell the client so it knows its view has a gap
+                            match serde_json::to_string(&StreamNotice::Lagged { skipped }) {
+                                Ok(json) => json,
+                                Err(e) => {
+                                    error!("Failed to serialize lag notice: {}", e);
+                                    continue;
+                                }
                             }
                         }
-                    }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
-                        warn!("Client lagging, dropping events");
-                    }
-                    Err(broadcast::error::RecvError::Closed) => {
-                        debug!("Event channel closed");
-                        break;
-                    }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            debug!("Event channel closed");
+                            break;
+                        }
+                    },
+                };
+
+                if let Err(e) = writer.write_all(format!("{}\n", message).as_bytes()).await {
+                    debug!("Client disconnected while writing: {}", e);
+                    break;
+                }
+                if disconnect {
+                    break;
                 }
             }
         });
@@ -542,9 +2041,123 @@ impl SecurityMonitor {
             }
         }
 
+        context.metrics.client_disconnected();
         info!("Client disconnected");
     }
 
+    /// Read one line of at most `limit` bytes into `line`. A longer line is an error, so the
+    /// client is dropped instead of buffered without bound.
+    async fn read_client_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, line: &mut String, limit: usize) -> std::io::Result<usize> {
+        let read = reader.take(limit as u64).read_line(line).await?;
+        if read == limit && !line.ends_with('\n') {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("line longer than {} bytes", limit)));
+        }
+        Ok(read)
+    }
+
+    /// Read the client's first line and check it is {"auth":"<token>"}
+    async fn authenticate<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, token: &str) -> Result<()> {
+        let mut line = String::new();
+        tokio::time::timeout(AUTH_TIMEOUT, Self::read_client_line(reader, &mut line, MAX_AUTH_LINE))
+            .await
+            .context("no auth line in time")?
+            .context("read failed")?;
+
+        let presented = serde_json::from_str::<serde_json::Value>(line.trim())
+            .ok()
+            .and_then(|value| value.get("auth").and_then(|auth| auth.as_str()).map(str::to_string))
+            .context("first line is not {\"auth\":\"<token>\"}")?;
+
+        // Compare every byte so the time taken doesn't reveal how much of the token matched
+        let matches = presented.len() == token.len()
+            && presented.bytes().zip(token.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0;
+        if !matches {
+            anyhow::bail!("wrong token");
+        }
+        Ok(())
+    }
+
+    /// Run one client command and build its response
+    fn handle_command(
+        line: &str,
+        context: &ClientContext,
+        filter: &tokio::sync::watch::Sender<ClientFilter>,
+    ) -> CommandResponse {
+        let cmd = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|value| value.get("cmd").and_then(|cmd| cmd.as_str()).map(str::to_string))
+            .unwrap_or_default();
+        let error = |message: String| CommandResponse {
+            cmd: cmd.clone(),
+            ok: false,
+            error: Some(message),
+            data: None,
+        };
+        let ok = |data: Option<serde_json::Value>| CommandResponse {
+            cmd: cmd.clone(),
+            ok: true,
+            error: None,
+            data,
+        };
+
+        let command = match serde_json::from_str::<ClientCommand>(line) {
+            Ok(command) => command,
+            Err(e) => return error(format!("Invalid command: {}", e)),
+        };
+        debug!("Client command: {:?}", command);
+
+        match command {
+            ClientCommand::Stats => ok(Some(serde_json::json!({
+                "uptime_seconds": context.started_at.elapsed().as_secs(),
+                "clients_connected": context.metrics.clients_connected(),
+                "events_total": context.metrics.events_total(),
+                "events_by_type": context.metrics.events_by_type(),
+                "triggers_fired": context.metrics.triggers_fired(),
+            }))),
+            ClientCommand::Status => {
+                let status = DaemonStatus {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    pid: std::process::id(),
+                    uptime_seconds: context.started_at.elapsed().as_secs(),
+                    inotify_watches: context.metrics.inotify_watches(),
+                    clients_connected: context.metrics.clients_connected(),
+                    events_total: context.metrics.events_total(),
+                    subsystems: context.metrics.subsystems(),
+                };
+                ok(serde_json::to_value(status).ok())
+            }
+            ClientCommand::Reload => match &context.reloader {
+                Some(reloader) => match reloader.reload() {
+                    Ok(()) => ok(None),
+                    Err(e) => {
+                        error!("Config reload failed, keeping current configuration: {:#}", e);
+                        error(format!("{:#}", e))
+                    }
+                },
+                None => error("Reload is not available in replay mode".to_string()),
+            },
+            ClientCommand::Subscribe { min_severity, event_types, path_regex } => {
+                match ClientFilter::new(min_severity.as_deref(), &event_types, path_regex.as_deref()) {
+                    Ok(new_filter) => {
+                        filter.send_replace(new_filter);
+                        ok(None)
+                    }
+                    Err(e) => error(format!("{:#}", e)),
+                }
+            }
+            ClientCommand::Inject { event } => {
+                info!("Received custom event: {:?} - {}", event.event_type, event.details.description);
+                match context.event_sender.send(event) {
+                    Ok(_) => ok(None),
+                    Err(e) => {
+                        error!("Failed to broadcast received event: {}", e);
+                        error(format!("Failed to broadcast event: {}", e))
+                    }
+                }
+            }
+        }
+    }
+
     async fn should_process_event(&self, event: &SecurityEvent) -> bool {
         // For microphone and camera access, implement deduplication
         match event.event_type {
@@ -565,22 +2178,7 @@ impl SecurityMonitor {
             }
 
             // Check if this event type matches the trigger
-            let event_type_str = match &event.event_type {
-                EventType::CameraAccess => "CameraAccess",
-                EventType::SshAccess => "SshAccess",
-                EventType::MicrophoneAccess => "MicrophoneAccess",
-                EventType::NetworkConnection => "NetworkConnection",
-                EventType::UsbDeviceInserted => "UsbDeviceInserted",
-                EventType::NetworkDiscovery => "NetworkDiscovery",
-                EventType::PingDetected => "PingDetected",
-                EventType::PortScanDetected => "PortScanDetected",
-                EventType::FileAccess => "FileAccess",
-                EventType::FileModify => "FileModify",
-                EventType::FileCreate => "FileCreate",
-                EventType::FileDelete => "FileDelete",
-                EventType::DirectoryAccess => "DirectoryAccess",
-                EventType::CustomMessage => "CustomMessage",
-            };
+            let event_type_str = event.event_type.name();
 
             if !trigger.event_types.contains(&event_type_str.to_string()) {
                 continue;
@@ -591,6 +2189,21 @@ impl SecurityMonitor {
                 continue;
             }
 
+            // Check path condition
+            if let Some(pattern) = &trigger.path_regex {
+                let matches = self.compiled.trigger_path_patterns
+                    .get(pattern)
+                    .is_some_and(|regex| regex.is_match(&event.path.to_string_lossy()));
+                if !matches {
+                    continue;
+                }
+            }
+
+            // Check metadata conditions
+            if !self.metadata_matches(trigger, event) {
+                continue;
+            }
+
             // Check cooldown
             if !self.check_trigger_cooldown(&trigger.name, trigger.cooldown_seconds).await {
                 continue;
@@ -601,6 +2214,25 @@ impl SecurityMonitor {
         }
     }
 
+    fn metadata_matches(&self, trigger: &EventTrigger, event: &SecurityEvent) -> bool {
+        trigger.metadata_match.iter().all(|(key, expected)| {
+            // Every listed key must be present on the event
+            let Some(actual) = event.details.metadata.get(key) else {
+                return false;
+            };
+
+            if let Some(pattern) = expected.strip_prefix('~') {
+                self.compiled.metadata_patterns
+                    .get(pattern)
+                    .is_some_and(|regex| regex.is_match(actual))
+            } else if let Some(excluded) = expected.strip_prefix('!') {
+                actual != excluded
+            } else {
+                actual == expected
+            }
+        })
+    }
+
     async fn check_trigger_cooldown(&self, trigger_name: &str, cooldown_seconds: u64) -> bool {
         let mut cooldowns = self.trigger_cooldowns.lock().await;
         let now = std::time::Instant::now();
@@ -616,55 +2248,208 @@ impl SecurityMonitor {
     }
 
     fn severity_meets_minimum(&self, event_severity: &Severity, min_severity: &str) -> bool {
-        let event_level = match event_severity {
-            Severity::Low => 1,
-            Severity::Medium => 2,
-            Severity::High => 3,
-            Severity::Critical => 4,
-        };
+        // Names are matched case-insensitively, like everywhere else severities are configured
+        let min_level = Severity::from_name(min_severity)
+            .map(|severity| severity.level())
+            .unwrap_or_else(|| Severity::Medium.level()); // Default to Medium
 
-        let min_level = match min_severity {
-            "Low" => 1,
-            "Medium" => 2,
-            "High" => 3,
-            "Critical" => 4,
-            _ => 2, // Default to Medium
-        };
-
-        event_level >= min_level
+        event_severity.level() >= min_level
     }
 
     async fn execute_trigger(&self, trigger: &EventTrigger, event: &SecurityEvent) {
         debug!("Executing trigger: {}", trigger.name);
+        self.metrics.record_trigger(&trigger.name);
 
-        // Substitute variables in command args
-        let mut args = trigger.args.clone();
-        for arg in &mut args {
-            *arg = arg.replace("{path}", &event.path.to_string_lossy())
-                     .replace("{severity}", &format!("{:?}", event.details.severity))
-                     .replace("{description}", &event.details.description)
-                     .replace("{timestamp}", &event.timestamp.to_rfc3339());
+        if let Some(url) = &trigger.webhook_url {
+            let request = self.webhook_request(trigger, url, event);
+            let name = trigger.name.clone();
+            if trigger.run_async {
+                self.spawn_trigger(Self::send_webhook(name, request));
+            } else {
+                Self::send_webhook(name, request).await;
+            }
+            return;
         }
 
-        let command = trigger.command.clone();
+        // Substitute variables in command args
+        let args: Vec<String> = trigger.args.iter()
+            .map(|arg| Self::substitute_event_fields(arg, event))
+            .collect();
+
+        let mut command = tokio::process::Command::new(&trigger.command);
+        command.args(&args).envs(Self::trigger_environment(event));
+        let command_name = trigger.command.clone();
+        let timeout = trigger.timeout_seconds.map(std::time::Duration::from_secs);
 
         if trigger.run_async {
-            tokio::spawn(async move {
-                if let Err(e) = tokio::process::Command::new(&command)
-                    .args(&args)
-                    .output()
-                    .await
-                {
-                    error!("Failed to execute trigger command '{}': {}", command, e);
-                }
-            });
+            self.spawn_trigger(Self::run_trigger_command(command_name, command, timeout));
         } else {
-            if let Err(e) = tokio::process::Command::new(&command)
-                .args(&args)
-                .output()
-                .await
-            {
-                error!("Failed to execute trigger command '{}': {}", command, e);
+            Self::run_trigger_command(command_name, command, timeout).await;
+        }
+    }
+
+    /// Run an async trigger in the background, tracked so shutdown can wait for it
+    fn spawn_trigger(&self, trigger: impl std::future::Future<Output = ()> + Send + 'static) {
+        let mut tasks = self.trigger_tasks.lock().unwrap();
+        // Finished triggers stay in the set until joined
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(trigger);
+    }
+
+    /// Finish up once `start` has been abandoned for a signal: give async triggers still
+    /// running and the event store writer up to SHUTDOWN_TIMEOUT, so a trigger isn't
+    /// killed halfway and queued events aren't lost
+    pub async fn shutdown(&mut self, signal: &str) {
+        let deadline = tokio::time::Instant::now() + SHUTDOWN_TIMEOUT;
+        let _ = self.event_sender.send(self.create_stopped_event(signal));
+
+        let tasks = self.trigger_tasks.get_mut().unwrap();
+        while tasks.try_join_next().is_some() {}
+        if !tasks.is_empty() {
+            info!("Waiting for {} running trigger(s) to finish", tasks.len());
+            let finished = tokio::time::timeout_at(deadline, async {
+                while tasks.join_next().await.is_some() {}
+            })
+            .await;
+            if finished.is_err() {
+                warn!("{} trigger(s) still running after {}s, stopping them", tasks.len(), SHUTDOWN_TIMEOUT.as_secs());
+                tasks.abort_all();
+            }
+        }
+
+        let _ = self.stopping.send(true);
+        if let Some(writer) = self.storage_writer.take() {
+            let finished = tokio::time::timeout_at(deadline, tokio::task::spawn_blocking(move || writer.join())).await;
+            if finished.is_err() {
+                warn!("Event store writer did not finish within {}s, queued events may be lost", SHUTDOWN_TIMEOUT.as_secs());
+            }
+        }
+    }
+
+    /// Run a trigger command to completion (or until `timeout`, when it is killed),
+    /// logging failures with their stderr
+    async fn run_trigger_command(
+        command_name: String,
+        mut command: tokio::process::Command,
+        timeout: Option<std::time::Duration>,
+    ) {
+        // Dropping the wait future on timeout drops the child, which kills it
+        command
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+
+        let child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                error!("Failed to execute trigger command '{}': {}", command_name, e);
+                return;
+            }
+        };
+
+        let result = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, child.wait_with_output()).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!("Trigger command '{}' timed out after {}s and was killed", command_name, timeout.as_secs());
+                    return;
+                }
+            },
+            None => child.wait_with_output().await,
+        };
+
+        let output = match result {
+            Ok(output) => output,
+            Err(e) => {
+                error!("Failed to wait for trigger command '{}': {}", command_name, e);
+                return;
+            }
+        };
+
+        if !output.stdout.is_empty() {
+            debug!("Trigger command '{}' stdout: {}", command_name, truncate_output(&output.stdout));
+        }
+        if !output.status.success() {
+            warn!(
+                "Trigger command '{}' failed ({}): {}",
+                command_name,
+                output.status,
+                truncate_output(&output.stderr)
+            );
+        }
+    }
+
+    /// Replace {path}, {severity}, {description}, {timestamp}, {event_type} and
+    /// {metadata.KEY} in a trigger argument
+    fn substitute_event_fields(template: &str, event: &SecurityEvent) -> String {
+        let mut result = template.replace("{path}", &event.path.to_string_lossy())
+            .replace("{severity}", &format!("{:?}", event.details.severity))
+            .replace("{description}", &event.details.description)
+            .replace("{timestamp}", &event.timestamp.to_rfc3339())
+            .replace("{event_type}", event.event_type.name())
+            .replace("{id}", &event.id.to_string());
+
+        if result.contains("{metadata.") {
+            for (key, value) in &event.details.metadata {
+                result = result.replace(&format!("{{metadata.{}}}", key), value);
+            }
+        }
+
+        result
+    }
+
+    /// SECMON_* variables describing the event, so handler scripts don't need positional args
+    fn trigger_environment(event: &SecurityEvent) -> Vec<(String, String)> {
+        let mut environment = vec![
+            ("SECMON_PATH".to_string(), event.path.to_string_lossy().to_string()),
+            ("SECMON_SEVERITY".to_string(), format!("{:?}", event.details.severity)),
+            ("SECMON_EVENT_TYPE".to_string(), event.event_type.name().to_string()),
+            ("SECMON_DESCRIPTION".to_string(), event.details.description.clone()),
+            ("SECMON_TIMESTAMP".to_string(), event.timestamp.to_rfc3339()),
+            ("SECMON_EVENT_ID".to_string(), event.id.to_string()),
+        ];
+
+        // Metadata keys become SECMON_META_<KEY>, e.g. old_path -> SECMON_META_OLD_PATH
+        for (key, value) in &event.details.metadata {
+            let name: String = key.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            environment.push((format!("SECMON_META_{}", name), value.clone()));
+        }
+
+        environment
+    }
+
+    fn webhook_request(&self, trigger: &EventTrigger, url: &str, event: &SecurityEvent) -> reqwest::RequestBuilder {
+        // Header names and values were validated in SecurityMonitor::new
+        let mut request = self.http_client
+            .post(url)
+            .timeout(std::time::Duration::from_secs(trigger.webhook_timeout_seconds))
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        for (name, value) in &trigger.webhook_headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        match serde_json::to_vec(event) {
+            Ok(body) => request.body(body),
+            Err(e) => {
+                error!("Failed to serialize event for webhook trigger '{}': {}", trigger.name, e);
+                request
+            }
+        }
+    }
+
+    async fn send_webhook(trigger_name: String, request: reqwest::RequestBuilder) {
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!("Webhook trigger '{}' delivered ({})", trigger_name, response.status());
+            }
+            Ok(response) => {
+                warn!("Webhook trigger '{}' got HTTP {}", trigger_name, response.status().as_u16());
+            }
+            Err(e) => {
+                error!("Webhook trigger '{}' failed: {}", trigger_name, e);
             }
         }
     }
@@ -672,8 +2457,8 @@ impl SecurityMonitor {
 
 impl Drop for SecurityMonitor {
     fn drop(&mut self) {
-        // Clean up the socket file when the monitor is dropped
-        if std::path::Path::new(&self.socket_path).exists() {
+        // Clean up the socket file when the monitor is dropped (systemd owns activated sockets)
+        if self.listen_fd.is_none() && std::path::Path::new(&self.socket_path).exists() {
             if let Err(e) = std::fs::remove_file(&self.socket_path) {
                 eprintln!("Warning: Failed to clean up socket file {}: {}", self.socket_path, e);
             } else {
@@ -683,10 +2468,24 @@ impl Drop for SecurityMonitor {
     }
 }
 
+/// Trimmed, lossily decoded command output, cut at TRIGGER_OUTPUT_LOG_LIMIT bytes
+fn truncate_output(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+    let text = text.trim();
+    if text.len() <= TRIGGER_OUTPUT_LOG_LIMIT {
+        return text.to_string();
+    }
+
+    let mut end = TRIGGER_OUTPUT_LOG_LIMIT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... ({} bytes truncated)", &text[..end], text.len() - end)
+}
+
 fn daemonize(pid_file: &str, log_file: &str) -> Result<()> {
     use std::fs::File;
     use std::io::Write;
-    use std::os::unix::io::AsRawFd;
 
     // Check if daemon is already running
     if let Ok(existing_pid) = std::fs::read_to_string(pid_file) {
@@ -738,22 +2537,21 @@ fn daemonize(pid_file: &str, log_file: &str) -> Result<()> {
     // Change working directory to root
     std::env::set_current_dir("/").context("Failed to change directory to /")?;
 
-    // Close standard file descriptors
-    unsafe {
-        libc::close(0); // stdin
-        libc::close(1); // stdout
-        libc::close(2); // stderr
-    }
-
-    // Redirect stdout/stderr to log file
-    let log_fd = File::create(log_file)
-        .with_context(|| format!("Failed to create log file: {}", log_file))?
-        .as_raw_fd();
-
-    unsafe {
-        libc::dup2(log_fd, 1); // stdout
-        libc::dup2(log_fd, 2); // stderr
+    // Point stdin at /dev/null and stdout/stderr at the log file. The files must stay
+    // open until dup2 is done; dup2 replaces the old descriptors, so nothing is closed
+    // first and trigger commands always inherit a valid stdin.
+    let dev_null = File::open("/dev/null").context("Failed to open /dev/null")?;
+    let log = File::create(log_file)
+        .with_context(|| format!("Failed to create log file: {}", log_file))?;
+
+    for (source, target) in [(dev_null.as_raw_fd(), 0), (log.as_raw_fd(), 1), (log.as_raw_fd(), 2)] {
+        if unsafe { libc::dup2(source, target) } == -1 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to redirect file descriptor {}", target));
+        }
     }
+    drop(dev_null);
+    drop(log);
 
     // Write PID to file
     let mut pid_file_handle = File::create(pid_file)
@@ -767,9 +2565,9 @@ fn daemonize(pid_file: &str, log_file: &str) -> Result<()> {
     Ok(())
 }
 
-fn cleanup_on_exit(socket_path: &str, pid_file: &str, daemon_mode: bool) {
+fn cleanup_on_exit(socket_path: &str, pid_file: &str, daemon_mode: bool, remove_socket: bool) {
     // Clean up socket file
-    if std::path::Path::new(socket_path).exists() {
+    if remove_socket && std::path::Path::new(socket_path).exists() {
         if let Err(e) = std::fs::remove_file(socket_path) {
             eprintln!("Warning: Failed to clean up socket file {}: {}", socket_path, e);
         } else {
@@ -778,13 +2576,11 @@ fn cleanup_on_exit(socket_path: &str, pid_file: &str, daemon_mode: bool) {
     }
 
     // Clean up PID file if in daemon mode
-    if daemon_mode {
-        if std::path::Path::new(pid_file).exists() {
-            if let Err(e) = std::fs::remove_file(pid_file) {
-                eprintln!("Warning: Failed to remove PID file {}: {}", pid_file, e);
-            } else {
-                info!("Cleaned up PID file: {}", pid_file);
-            }
+    if daemon_mode && std::path::Path::new(pid_file).exists() {
+        if let Err(e) = std::fs::remove_file(pid_file) {
+            eprintln!("Warning: Failed to remove PID file {}: {}", pid_file, e);
+        } else {
+            info!("Cleaned up PID file: {}", pid_file);
         }
     }
 }
@@ -804,8 +2600,12 @@ fn print_help() {
     println!("    -l, --log-level <LEVEL>   Set log level [default: info]");
     println!("                              Values: error, warn, info, debug, trace");
     println!("    -d, --daemon              Run in background as daemon");
-    println!("    --pid-file <FILE>         PID file path [default: /tmp/secmon.pid]");
-    println!("    --log-file <FILE>         Log file path when running as daemon [default: /tmp/secmon.log]");
+    println!("    --pid-file <FILE>         PID file path [default: pid_file from config, /tmp/secmon.pid]");
+    println!("    --log-file <FILE>         Log file path when running as daemon [default: log_file from config, /tmp/secmon.log]");
+    println!("    --replay <FILE>           Replay a recorded NDJSON event capture instead of live monitoring");
+    println!("    --replay-speed <N>        Scale the recorded inter-event timing (2 = twice as fast) [default: 1]");
+    println!("    --replay-loop             Cycle the capture continuously, re-stamping events each pass");
+    println!("    --replay-count <N>        Stop replaying after N events in total");
     println!();
     println!("DESCRIPTION:");
     println!("    A security monitoring daemon that watches for file system events,");
@@ -817,16 +2617,20 @@ fn print_help() {
     println!("    secmon-daemon --daemon                    # Run in background as daemon");
     println!("    secmon-daemon -d --log-level debug        # Background mode with debug logging");
     println!("    secmon-daemon --pid-file /var/run/secmon.pid  # Custom PID file location");
+    println!("    secmon-daemon --replay events.ndjson --replay-loop --replay-speed 10  # Demo/soak test");
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
     let mut log_level = "info".to_string();
     let mut config_path = "/etc/secmon/config.toml".to_string();
     let mut daemon_mode = false;
-    let mut pid_file = "/tmp/secmon.pid".to_string();
-    let mut log_file = "/tmp/secmon.log".to_string();
+    let mut pid_file: Option<String> = None; // Defaults to the config's pid_file and log_file
+    let mut log_file: Option<String> = None;
+    let mut replay_path: Option<PathBuf> = None;
+    let mut replay_speed: Option<f64> = None;
+    let mut replay_loop = false;
+    let mut replay_count: Option<usize> = None;
 
     // Parse command line arguments
     let mut i = 1;
@@ -859,7 +2663,7 @@ async fn main() -> Result<()> {
             }
             "--pid-file" => {
                 if i + 1 < args.len() {
-                    pid_file = args[i + 1].clone();
+                    pid_file = Some(args[i + 1].clone());
                     i += 2;
                 } else {
                     eprintln!("Error: --pid-file requires a value");
@@ -867,12 +2671,12 @@ async fn main() -> Result<()> {
                 }
             }
             arg if arg.starts_with("--pid-file=") => {
-                pid_file = arg.split('=').nth(1).unwrap_or("/tmp/secmon.pid").to_string();
+                pid_file = arg.split('=').nth(1).map(str::to_string);
                 i += 1;
             }
             "--log-file" => {
                 if i + 1 < args.len() {
-                    log_file = args[i + 1].clone();
+                    log_file = Some(args[i + 1].clone());
                     i += 2;
                 } else {
                     eprintln!("Error: --log-file requires a value");
@@ -880,9 +2684,52 @@ async fn main() -> Result<()> {
                 }
             }
             arg if arg.starts_with("--log-file=") => {
-                log_file = arg.split('=').nth(1).unwrap_or("/tmp/secmon.log").to_string();
+                log_file = arg.split('=').nth(1).map(str::to_string);
+                i += 1;
+            }
+            "--replay" => {
+                if i + 1 < args.len() {
+                    replay_path = Some(PathBuf::from(&args[i + 1]));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --replay requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--replay-speed" => {
+                if i + 1 < args.len() {
+                    match args[i + 1].parse::<f64>() {
+                        Ok(speed) if speed > 0.0 && speed.is_finite() => replay_speed = Some(speed),
+                        _ => {
+                            eprintln!("Error: --replay-speed must be a positive number");
+                            std::process::exit(1);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("Error: --replay-speed requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--replay-loop" => {
+                replay_loop = true;
                 i += 1;
             }
+            "--replay-count" => {
+                if i + 1 < args.len() {
+                    match args[i + 1].parse::<usize>() {
+                        Ok(count) => replay_count = Some(count),
+                        Err(_) => {
+                            eprintln!("Error: --replay-count must be a non-negative integer");
+                            std::process::exit(1);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("Error: --replay-count requires a value");
+                    std::process::exit(1);
+                }
+            }
             arg if !arg.starts_with('-') => {
                 config_path = arg.to_string();
                 i += 1;
@@ -895,6 +2742,11 @@ async fn main() -> Result<()> {
         }
     }
 
+    if replay_path.is_none() && (replay_speed.is_some() || replay_loop || replay_count.is_some()) {
+        eprintln!("Error: --replay-speed, --replay-loop and --replay-count require --replay <FILE>");
+        std::process::exit(1);
+    }
+
     // Initialize logger with specified level
     env_logger::Builder::from_default_env()
         .filter_level(match log_level.to_lowercase().as_str() {
@@ -910,46 +2762,533 @@ async fn main() -> Result<()> {
         })
         .init();
 
+    // Pick up a systemd-activated socket before daemonizing changes our PID
+    let listen_fd = systemd::take_listen_fd();
+
+    // Loaded before daemonizing, which needs its pid_file and log_file (and so a broken
+    // config is reported on the terminal)
+    let config = Config::load(&config_path)
+        .context("Failed to load configuration")?;
+    let pid_file = pid_file.unwrap_or_else(|| config.pid_file.clone());
+    let log_file = log_file.unwrap_or_else(|| config.log_file.clone());
+
     // Handle daemon mode
     if daemon_mode {
         daemonize(&pid_file, &log_file)?;
     }
 
-    let config = Config::load(&config_path)
-        .context("Failed to load configuration")?;
+    // Worker threads don't survive fork(), so the runtime is only started once daemonized
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+    runtime.block_on(async {
+        info!("Starting security monitor with config: {}", config_path);
+
+        let mut monitor = SecurityMonitor::new(config)?;
+        monitor.config_path = Some(config_path.clone());
+        monitor.listen_fd = listen_fd;
+        monitor.replay = replay_path.map(|path| {
+            let mut options = ReplayOptions::new(path);
+            options.speed = replay_speed.unwrap_or(options.speed);
+            options.looping = replay_loop;
+            options.count = replay_count;
+            options
+        });
 
-    info!("Starting security monitor with config: {}", config_path);
+        // Store paths for cleanup
+        let socket_path = monitor.socket_path.clone();
+        let pid_file_clone = pid_file.clone();
+        let daemon_mode_clone = daemon_mode;
+
+        // Setup signal handlers for graceful shutdown
+        let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+        // Also handle SIGINT directly for non-daemon mode (Ctrl+C). A signal drops the
+        // event loop, so no new events are dispatched while shutting down.
+        let signal = tokio::select! {
+            result = monitor.start() => {
+                if let Err(e) = result {
+                    error!("Monitor error: {:#}", e);
+                    cleanup_on_exit(&socket_path, &pid_file_clone, daemon_mode_clone, listen_fd.is_none());
+                    std::process::exit(1);
+                }
+                None
+            }
+            _ = sigint.recv() => Some("SIGINT"),
+            _ = sigterm.recv() => Some("SIGTERM"),
+        };
+        if let Some(signal) = signal {
+            info!("Received {} signal, exiting gracefully", signal);
+            monitor.shutdown(signal).await;
+            cleanup_on_exit(&socket_path, &pid_file_clone, daemon_mode_clone, listen_fd.is_none());
+        }
 
-    let mut monitor = SecurityMonitor::new(config)?;
+        info!("Daemon shutdown complete");
+        Ok(())
+    })
+}
 
-    // Store paths for cleanup
-    let socket_path = monitor.socket_path.clone();
-    let pid_file_clone = pid_file.clone();
-    let daemon_mode_clone = daemon_mode;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Setup signal handlers for graceful shutdown
-    let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
-    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    /// A config from TOML, with the required top-level keys filled in
+    fn test_config(body: &str) -> Config {
+        toml::from_str(&format!("socket_path = \"/tmp/secmon-test.sock\"\nlog_level = \"info\"\n{}", body)).unwrap()
+    }
 
-    // Also handle SIGINT directly for non-daemon mode (Ctrl+C)
-    tokio::select! {
-        result = monitor.start() => {
-            if let Err(e) = result {
-                error!("Monitor error: {}", e);
-                cleanup_on_exit(&socket_path, &pid_file_clone, daemon_mode_clone);
-                std::process::exit(1);
+    fn test_event(event_type: EventType, path: &str, severity: Severity) -> SecurityEvent {
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type,
+            path: PathBuf::from(path),
+            details: EventDetails {
+                severity,
+                description: "test".to_string(),
+                metadata: HashMap::new(),
+            },
+        }
+    }
+
+    fn with_metadata(mut event: SecurityEvent, entries: &[(&str, &str)]) -> SecurityEvent {
+        for (key, value) in entries {
+            event.details.metadata.insert(key.to_string(), value.to_string());
+        }
+        event
+    }
+
+    const METADATA_TRIGGER: &str = r#"
+watches = []
+
+[[triggers]]
+name = "usb"
+enabled = true
+event_types = ["UsbDeviceInserted"]
+min_severity = "Low"
+command = "true"
+metadata_match = { vendor_id = "046d", device_class = "~^(03|08)$", serial = "!trusted" }
+"#;
+
+    #[test]
+    fn metadata_match_requires_every_key() {
+        let monitor = SecurityMonitor::new(test_config(METADATA_TRIGGER)).unwrap();
+        let trigger = &monitor.config.triggers[0];
+        let event = test_event(EventType::UsbDeviceInserted, "/dev/bus/usb/001/002", Severity::High);
+
+        let matching = with_metadata(event.clone(), &[("vendor_id", "046d"), ("device_class", "03"), ("serial", "X1")]);
+        assert!(monitor.metadata_matches(trigger, &matching));
+
+        let missing_serial = with_metadata(event, &[("vendor_id", "046d"), ("device_class", "03")]);
+        assert!(!monitor.metadata_matches(trigger, &missing_serial));
+    }
+
+    #[test]
+    fn metadata_match_exact_regex_and_negation() {
+        let monitor = SecurityMonitor::new(test_config(METADATA_TRIGGER)).unwrap();
+        let trigger = &monitor.config.triggers[0];
+        let event = test_event(EventType::UsbDeviceInserted, "/dev/bus/usb/001/002", Severity::High);
+        let matches = |entries: &[(&str, &str)]| monitor.metadata_matches(trigger, &with_metadata(event.clone(), entries));
+
+        assert!(matches(&[("vendor_id", "046d"), ("device_class", "08"), ("serial", "X1")]));
+        assert!(!matches(&[("vendor_id", "046e"), ("device_class", "08"), ("serial", "X1")]));
+        assert!(!matches(&[("vendor_id", "046d"), ("device_class", "0308"), ("serial", "X1")]));
+        assert!(!matches(&[("vendor_id", "046d"), ("device_class", "08"), ("serial", "trusted")]));
+    }
+
+    #[test]
+    fn metadata_match_without_conditions_matches_anything() {
+        let config = test_config(
+            "watches = []\n\n[[triggers]]\nname = \"any\"\nenabled = true\nevent_types = []\nmin_severity = \"Low\"\ncommand = \"true\"\n",
+        );
+        let monitor = SecurityMonitor::new(config).unwrap();
+        let event = test_event(EventType::FileModify, "/etc/passwd", Severity::Low);
+        assert!(monitor.metadata_matches(&monitor.config.triggers[0], &event));
+    }
+
+    #[tokio::test]
+    async fn path_cooldown_suppresses_repeats_then_summarizes() {
+        let mut monitor = SecurityMonitor::new(test_config("watches = []\n")).unwrap();
+        let mut derived = monitor.derived_events.take().unwrap();
+        let status = test_event(EventType::FileModify, "/run/app/status", Severity::Low);
+        let other = test_event(EventType::FileModify, "/run/app/other", Severity::Low);
+
+        assert!(monitor.check_path_cooldown("/run/app", 50, &status));
+        assert!(!monitor.check_path_cooldown("/run/app", 50, &status));
+        assert!(!monitor.check_path_cooldown("/run/app", 50, &status));
+        // Each path under the watch has its own window
+        assert!(monitor.check_path_cooldown("/run/app", 50, &other));
+
+        // Only the path with suppressed repeats is summarized
+        let summary = tokio::time::timeout(std::time::Duration::from_secs(2), derived.recv()).await.unwrap().unwrap();
+        assert_eq!(summary.path, PathBuf::from("/run/app/status"));
+        assert_eq!(summary.details.metadata["suppressed_count"], "2");
+        assert_eq!(summary.details.metadata["cooldown_ms"], "50");
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(derived.try_recv().is_err());
+
+        // Once the window has closed the next event goes through again
+        assert!(monitor.check_path_cooldown("/run/app", 50, &status));
+    }
+
+
+    fn test_watch(body: &str) -> WatchConfig {
+        toml::from_str(&format!("path = \"/srv/data\"\ndescription = \"test\"\nenabled = true\n{}", body)).unwrap()
+    }
+
+    #[test]
+    fn directories_and_files_get_different_watch_masks() {
+        let watch = test_watch("");
+        let directory = SecurityMonitor::watch_mask_for(&watch, true).unwrap();
+        let file = SecurityMonitor::watch_mask_for(&watch, false).unwrap();
+
+        assert!(directory.contains(WatchMask::ONLYDIR));
+        assert!(!file.contains(WatchMask::ONLYDIR));
+        assert_eq!(directory - WatchMask::ONLYDIR, file);
+        assert!(!directory.contains(WatchMask::DELETE_SELF));
+
+        let recursive = test_watch("recursive = true");
+        assert!(SecurityMonitor::watch_mask_for(&recursive, true).unwrap().contains(WatchMask::DELETE_SELF));
+        assert!(!SecurityMonitor::watch_mask_for(&recursive, false).unwrap().contains(WatchMask::DELETE_SELF));
+    }
+
+    #[test]
+    fn create_is_classified_by_the_isdir_bit() {
+        let base = Path::new("/srv/data");
+        let create = inotify::EventMask::CREATE;
+
+        let (event_type, severity, _) = SecurityMonitor::classify_event(base, Path::new("/srv/data/new"), create);
+        assert!(matches!(event_type, EventType::FileCreate));
+        assert!(matches!(severity, Severity::Medium));
+
+        let (event_type, severity, _) =
+            SecurityMonitor::classify_event(base, Path::new("/srv/data/new"), create | inotify::EventMask::ISDIR);
+        assert!(matches!(event_type, EventType::DirectoryCreate));
+        assert!(matches!(severity, Severity::Medium));
+
+        // Hidden directories are more interesting than ordinary ones
+        let (event_type, severity, _) =
+            SecurityMonitor::classify_event(base, Path::new("/srv/data/.hidden"), create | inotify::EventMask::ISDIR);
+        assert!(matches!(event_type, EventType::DirectoryCreate));
+        assert!(matches!(severity, Severity::High));
+    }
+
+
+    /// An inotify event as the kernel would report it for `name` inside a watched directory
+    fn inotify_event<'a>(monitor: &mut SecurityMonitor, mask: inotify::EventMask, name: Option<&'a std::ffi::OsStr>) -> inotify::Event<&'a std::ffi::OsStr> {
+        let wd = monitor.inotify.watches().add(std::env::temp_dir(), WatchMask::ATTRIB).unwrap();
+        inotify::Event { wd, mask, cookie: 0, name }
+    }
+
+    #[test]
+    fn non_utf8_file_names_keep_their_raw_bytes() {
+        let mut monitor = SecurityMonitor::new(test_config("watches = []\n")).unwrap();
+        let watch = test_watch("");
+        let name = std::ffi::OsStr::from_bytes(b"report\xff\xfe.pdf");
+        let event = inotify_event(&mut monitor, inotify::EventMask::CREATE, Some(name));
+
+        let security_event = monitor.create_security_event(Path::new("/srv/data"), &watch, &event);
+        let expected_raw: String = b"/srv/data/report\xff\xfe.pdf".iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(security_event.details.metadata["path_raw"], expected_raw);
+        assert_eq!(security_event.details.metadata["path_lossy"], "true");
+        assert_eq!(security_event.path, PathBuf::from("/srv/data/report\u{fffd}\u{fffd}.pdf"));
+        // The lossy path is what lets the event serialize at all
+        assert!(serde_json::to_string(&security_event).is_ok());
+
+        let valid = inotify_event(&mut monitor, inotify::EventMask::CREATE, Some(std::ffi::OsStr::new("résumé.pdf")));
+        let security_event = monitor.create_security_event(Path::new("/srv/data"), &watch, &valid);
+        assert_eq!(security_event.path, PathBuf::from("/srv/data/résumé.pdf"));
+        assert!(!security_event.details.metadata.contains_key("path_raw"));
+        assert!(!security_event.details.metadata.contains_key("path_lossy"));
+    }
+
+
+    #[tokio::test]
+    async fn moves_pair_by_cookie_and_unpaired_moves_are_dispatched() {
+        let mut monitor = SecurityMonitor::new(test_config("watches = []\n")).unwrap();
+        let mut derived = monitor.derived_events.take().unwrap();
+        let watch = test_watch("");
+
+        // A key moved out of ~/.ssh keeps the severity of its source
+        let mut from = inotify_event(&mut monitor, inotify::EventMask::MOVED_FROM, Some(std::ffi::OsStr::new("id_rsa")));
+        from.cookie = 7;
+        assert!(monitor.pair_move_event(Path::new("/home/user/.ssh"), &watch, &from).is_none());
+        let mut to = inotify_event(&mut monitor, inotify::EventMask::MOVED_TO, Some(std::ffi::OsStr::new("key")));
+        to.cookie = 7;
+        let moved = monitor.pair_move_event(Path::new("/srv/data"), &watch, &to).unwrap();
+        assert!(matches!(moved.event_type, EventType::FileMove));
+        assert!(matches!(moved.details.severity, Severity::Critical));
+        assert_eq!(moved.details.metadata["old_path"], "/home/user/.ssh/id_rsa");
+        assert_eq!(moved.details.metadata["new_path"], "/srv/data/key");
+
+        // Without a MOVED_TO the move-out is queued for the inotify loop to dispatch
+        let mut away = inotify_event(&mut monitor, inotify::EventMask::MOVED_FROM, Some(std::ffi::OsStr::new("report")));
+        away.cookie = 8;
+        assert!(monitor.pair_move_event(Path::new("/srv/data"), &watch, &away).is_none());
+        let move_out = tokio::time::timeout(std::time::Duration::from_secs(2), derived.recv()).await.unwrap().unwrap();
+        assert_eq!(move_out.path, PathBuf::from("/srv/data/report"));
+        assert!(move_out.details.description.starts_with("Moved out of watched tree"));
+    }
+
+
+    #[test]
+    fn debounce_reports_the_first_event_and_flushes_the_burst_count() {
+        let mut monitor = SecurityMonitor::new(test_config("watches = []\n")).unwrap();
+        let start = std::time::Instant::now();
+        let at = |ms: u64| start + std::time::Duration::from_millis(ms);
+        let access = test_event(EventType::FileAccess, "/srv/data/report", Severity::Low);
+        let other = test_event(EventType::FileAccess, "/srv/data/other", Severity::Low);
+
+        assert!(monitor.debounce_event(&access, 500, at(0)));
+        assert!(!monitor.debounce_event(&access, 500, at(10)));
+        assert!(!monitor.debounce_event(&access, 500, at(20)));
+        // A lone event has nothing to flush
+        assert!(monitor.debounce_event(&other, 500, at(30)));
+        assert!(monitor.flush_debounced(at(400)).is_empty());
+
+        let flushed = monitor.flush_debounced(at(500));
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].path, PathBuf::from("/srv/data/report"));
+        assert_eq!(flushed[0].details.metadata["count"], "3");
+
+        // The next event starts a new burst and carries no count of its own
+        let next = test_event(EventType::FileAccess, "/srv/data/report", Severity::Low);
+        assert!(monitor.debounce_event(&next, 500, at(600)));
+        assert!(!next.details.metadata.contains_key("count"));
+        assert!(monitor.flush_debounced(at(1200)).is_empty());
+        assert!(monitor.debounce.is_empty());
+    }
+
+
+    #[test]
+    fn classification_rules_override_the_built_in_severity() {
+        let config = test_config(
+            "watches = []\n\n[[classification]]\npath_regex = \"^/opt/secrets\"\nseverity = \"Critical\"\ndescription = \"Secret store touched\"\n",
+        );
+        let mut monitor = SecurityMonitor::new(config).unwrap();
+        let watch = toml::from_str::<WatchConfig>("path = \"/opt/secrets\"\ndescription = \"test\"\nenabled = true\n").unwrap();
+
+        let modify = inotify_event(&mut monitor, inotify::EventMask::MODIFY, Some(std::ffi::OsStr::new("db.key")));
+        let security_event = monitor.create_security_event(Path::new("/opt/secrets"), &watch, &modify);
+        assert!(matches!(security_event.details.severity, Severity::Critical));
+        // Only the severity and description are configured, so the type still comes from the mask
+        assert!(matches!(security_event.event_type, EventType::FileModify));
+        assert_eq!(security_event.details.description, "Secret store touched: /opt/secrets/db.key");
+        assert_eq!(security_event.details.metadata["classification_rule"], "^/opt/secrets");
+
+        // Paths outside the rule keep the built-in classification
+        let watch = test_watch("");
+        let security_event = monitor.create_security_event(Path::new("/srv/data"), &watch, &modify);
+        assert!(matches!(security_event.details.severity, Severity::Low));
+        assert!(!security_event.details.metadata.contains_key("classification_rule"));
+    }
+
+
+    #[test]
+    fn trigger_min_severity_is_case_insensitive() {
+        let monitor = SecurityMonitor::new(test_config("watches = []\n")).unwrap();
+
+        assert!(monitor.severity_meets_minimum(&Severity::High, "high"));
+        assert!(monitor.severity_meets_minimum(&Severity::Critical, "HIGH"));
+        assert!(!monitor.severity_meets_minimum(&Severity::Medium, "High"));
+        assert!(!monitor.severity_meets_minimum(&Severity::High, "critical"));
+        assert!(monitor.severity_meets_minimum(&Severity::Low, "low"));
+
+        // Unknown names fall back to Medium
+        assert!(monitor.severity_meets_minimum(&Severity::Medium, "urgent"));
+        assert!(!monitor.severity_meets_minimum(&Severity::Low, "urgent"));
+    }
+
+
+    /// Like the daemon's signal handling: the running monitor is dropped and then shut down
+    #[tokio::test]
+    async fn shutdown_while_idle_completes() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let socket_path = dir.join("secmon.sock");
+        let config: Config = toml::from_str(&format!(
+            "socket_path = \"{}\"\nlog_level = \"info\"\nrescan_interval_seconds = 0\n\n[[watches]]\npath = \"{}\"\ndescription = \"idle\"\nenabled = true\n",
+            socket_path.display(),
+            dir.display()
+        ))
+        .unwrap();
+        let mut monitor = SecurityMonitor::new(config).unwrap();
+        let mut events = monitor.event_sender.subscribe();
+
+        // Nothing happens under the watch, so the event loop sits waiting for inotify. On this
+        // single-threaded runtime a blocking read would starve the timer and hang the test.
+        tokio::select! {
+            result = monitor.start() => panic!("monitor stopped on its own: {:?}", result),
+            _ = tokio::time::sleep(std::time::Duration::from_millis(300)) => {}
+        }
+        assert!(socket_path.exists());
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), monitor.shutdown("SIGTERM"))
+            .await
+            .expect("shutdown did not complete");
+
+        let mut stopped = None;
+        while let Ok(event) = events.try_recv() {
+            if matches!(event.event_type, EventType::DaemonStopped) {
+                stopped = Some(event);
             }
         }
-        _ = sigint.recv() => {
-            info!("Received SIGINT signal, exiting gracefully");
-            cleanup_on_exit(&socket_path, &pid_file_clone, daemon_mode_clone);
+        assert_eq!(stopped.expect("no DaemonStopped event").details.metadata["signal"], "SIGTERM");
+    }
+
+
+    /// Watches the kernel holds on the monitor's inotify instance
+    fn kernel_watch_count(monitor: &SecurityMonitor) -> usize {
+        let fdinfo = std::fs::read_to_string(format!("/proc/self/fdinfo/{}", monitor.inotify.as_raw_fd())).unwrap();
+        fdinfo.lines().filter(|line| line.starts_with("inotify wd:")).count()
+    }
+
+    /// Run the inotify loop for `during` while it is running
+    async fn run_event_loop(monitor: &mut SecurityMonitor, during: impl std::future::Future<Output = ()>) {
+        tokio::select! {
+            result = monitor.monitor_events() => panic!("event loop stopped: {:?}", result),
+            _ = async {
+                during.await;
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            } => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn deleting_and_recreating_directories_does_not_leak_watches() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("root");
+        let sub = root.join("sub");
+        std::fs::create_dir_all(sub.join("nested")).unwrap();
+        let config = test_config(&format!(
+            "[[watches]]\npath = \"{}\"\ndescription = \"test\"\nenabled = true\nrecursive = true\n",
+            root.display()
+        ));
+        let mut monitor = SecurityMonitor::new(config).unwrap();
+        monitor.setup_watches().unwrap();
+        assert_eq!(monitor.watched_paths.len(), 3);
+        assert_eq!(kernel_watch_count(&monitor), 3);
+
+        run_event_loop(&mut monitor, async {
+            for _ in 0..5 {
+                std::fs::remove_dir_all(&sub).unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                std::fs::create_dir_all(sub.join("nested")).unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        assert_eq!(monitor.watched_paths.len(), 3);
+        assert_eq!(kernel_watch_count(&monitor), 3);
+        assert!(monitor.lost_watches.is_empty());
+
+        // The configured path itself is remembered and watched again once it is back
+        run_event_loop(&mut monitor, async { std::fs::remove_dir_all(&root).unwrap() }).await;
+        assert!(monitor.watched_paths.is_empty());
+        assert_eq!(kernel_watch_count(&monitor), 0);
+        assert_eq!(monitor.lost_watches.len(), 1);
+
+        std::fs::create_dir_all(&sub).unwrap();
+        monitor.restore_lost_watches();
+        assert!(monitor.lost_watches.is_empty());
+        assert_eq!(monitor.watched_paths.len(), 2);
+        assert_eq!(kernel_watch_count(&monitor), 2);
+    }
+
+
+    #[test]
+    fn directory_open_and_access_are_directory_access() {
+        let base = Path::new("/srv/data");
+        let listed = Path::new("/srv/data/reports");
+        for mask in [inotify::EventMask::OPEN, inotify::EventMask::ACCESS] {
+            let (event_type, severity, description) = SecurityMonitor::classify_event(base, listed, mask | inotify::EventMask::ISDIR);
+            assert!(matches!(event_type, EventType::DirectoryAccess));
+            assert!(matches!(severity, Severity::Low));
+            assert_eq!(description, "Directory accessed: /srv/data/reports");
+
+            // Without ISDIR it is still a file being read
+            let (event_type, _, _) = SecurityMonitor::classify_event(base, listed, mask);
+            assert!(matches!(event_type, EventType::FileAccess));
         }
-        _ = sigterm.recv() => {
-            info!("Received SIGTERM signal, exiting gracefully");
-            cleanup_on_exit(&socket_path, &pid_file_clone, daemon_mode_clone);
+    }
+
+
+    #[tokio::test]
+    async fn replayed_events_get_new_ids() {
+        let temp = tempfile::tempdir().unwrap();
+        let capture = temp.path().join("capture.ndjson");
+        let recorded = test_event(EventType::SshAccess, "/home/user/.ssh/id_rsa", Severity::High);
+        std::fs::write(&capture, format!("{}\n", serde_json::to_string(&recorded).unwrap())).unwrap();
+
+        let monitor = SecurityMonitor::new(test_config("watches = []\n")).unwrap();
+        let mut events = monitor.event_sender.subscribe();
+        let mut options = ReplayOptions::new(capture.clone());
+        options.looping = true;
+        options.count = Some(2);
+        monitor.replay_events(&options).await.unwrap();
+
+        let first = events.try_recv().unwrap();
+        let second = events.try_recv().unwrap();
+        for replayed in [&first, &second] {
+            assert_ne!(replayed.id, recorded.id);
+            assert_eq!(replayed.details.metadata["replayed_id"], recorded.id.to_string());
+            assert_eq!(replayed.details.metadata["replayed_from"], recorded.timestamp.to_rfc3339());
         }
+        assert_ne!(first.id, second.id);
     }
 
-    info!("Daemon shutdown complete");
-    Ok(())
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn rescanned_devices_are_watched_again_after_replugging() {
+        let dev = tempfile::tempdir().unwrap();
+        let camera = dev.path().join("video0");
+        std::fs::write(&camera, "").unwrap();
+        let config = test_config(&format!(
+            "[[watches]]\npath = \"{}/video*\"\ndescription = \"cameras\"\nenabled = true\nauto_discover = true\n",
+            dev.path().display()
+        ));
+        let mut monitor = SecurityMonitor::new(config).unwrap();
+        let mut events = monitor.event_sender.subscribe();
+
+        monitor.watch_rescanned_devices(vec![camera.clone()]).await;
+        assert_eq!(monitor.watched_paths.len(), 1);
+        let added = events.try_recv().unwrap();
+        assert_eq!(added.path, camera);
+        assert_eq!(added.details.metadata["device_kind"], "video");
+
+        // Still plugged in: nothing new
+        monitor.watch_rescanned_devices(vec![camera.clone()]).await;
+        assert_eq!(monitor.watched_paths.len(), 1);
+        assert_eq!(kernel_watch_count(&monitor), 1);
+        assert!(events.try_recv().is_err());
+
+        // Unplugged and plugged back in
+        run_event_loop(&mut monitor, async { std::fs::remove_file(&camera).unwrap() }).await;
+        assert!(monitor.watched_paths.is_empty());
+        while events.try_recv().is_ok() {}
+        std::fs::write(&camera, "").unwrap();
+        monitor.watch_rescanned_devices(vec![camera.clone()]).await;
+        assert_eq!(monitor.watched_paths.len(), 1);
+        assert_eq!(kernel_watch_count(&monitor), 1);
+        assert_eq!(events.try_recv().unwrap().details.metadata["source"], "device_rescan");
+    }
+
+
+    #[tokio::test]
+    async fn client_lines_are_capped() {
+        let fits = format!("{}\n", "a".repeat(15));
+        let mut reader = BufReader::new(fits.as_bytes());
+        let mut line = String::new();
+        assert_eq!(SecurityMonitor::read_client_line(&mut reader, &mut line, 16).await.unwrap(), 16);
+        assert_eq!(line, fits);
+
+        // One byte more, or no newline before the limit, disconnects the client
+        let long = format!("{}\n", "a".repeat(16));
+        let mut reader = BufReader::new(long.as_bytes());
+        let error = SecurityMonitor::read_client_line(&mut reader, &mut String::new(), 16).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let flood = "a".repeat(100_000);
+        let mut reader = BufReader::new(flood.as_bytes());
+        assert!(SecurityMonitor::read_client_line(&mut reader, &mut String::new(), MAX_AUTH_LINE).await.is_err());
+    }
+}
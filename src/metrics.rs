@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use crate::SecurityEvent;
+use secmon_core::SubsystemStatus;
+
+/// Requests must arrive within this long and fit in this many bytes
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_BYTES: usize = 8192;
+
+/// Counters and gauges exposed on `/metrics` in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    events: Mutex<BTreeMap<(&'static str, String), u64>>, // (event type, severity) -> count
+    triggers_fired: Mutex<BTreeMap<String, u64>>,
+    clients_connected: AtomicUsize,
+    inotify_watches: AtomicUsize,
+    subsystems: Mutex<BTreeMap<&'static str, SubsystemStatus>>,
+}
+
+impl Metrics {
+    pub fn record_event(&self, event: &SecurityEvent) {
+        let key = (event.event_type.name(), format!("{:?}", event.details.severity));
+        *self.events.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    pub fn record_trigger(&self, name: &str) {
+        *self.triggers_fired.lock().unwrap().entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn client_connected(&self) {
+        self.clients_connected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) {
+        self.clients_connected.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set_inotify_watches(&self, count: usize) {
+        self.inotify_watches.store(count, Ordering::Relaxed);
+    }
+
+    pub fn set_subsystem(&self, name: &'static str, status: SubsystemStatus) {
+        self.subsystems.lock().unwrap().insert(name, status);
+    }
+
+    pub fn inotify_watches(&self) -> usize {
+        self.inotify_watches.load(Ordering::Relaxed)
+    }
+
+    pub fn subsystems(&self) -> BTreeMap<String, SubsystemStatus> {
+        self.subsystems.lock().unwrap().iter().map(|(name, status)| (name.to_string(), status.clone())).collect()
+    }
+
+    pub fn clients_connected(&self) -> usize {
+        self.clients_connected.load(Ordering::Relaxed)
+    }
+
+    pub fn events_total(&self) -> u64 {
+        self.events.lock().unwrap().values().sum()
+    }
+
+    /// Event counts per event type, summed over severities
+    pub fn events_by_type(&self) -> BTreeMap<&'static str, u64> {
+        let mut by_type = BTreeMap::new();
+        for ((event_type, _), count) in self.events.lock().unwrap().iter() {
+            *by_type.entry(*event_type).or_insert(0) += count;
+        }
+        by_type
+    }
+
+    pub fn triggers_fired(&self) -> BTreeMap<String, u64> {
+        self.triggers_fired.lock().unwrap().clone()
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP secmon_events_total Security events broadcast by the daemon.\n");
+        out.push_str("# TYPE secmon_events_total counter\n");
+        for ((event_type, severity), count) in self.events.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "secmon_events_total{{event_type=\"{}\",severity=\"{}\"}} {}",
+                event_type, severity, count
+            );
+        }
+
+        out.push_str("# HELP secmon_triggers_fired_total Trigger executions, by trigger name.\n");
+        out.push_str("# TYPE secmon_triggers_fired_total counter\n");
+        for (name, count) in self.triggers_fired.lock().unwrap().iter() {
+            let _ = writeln!(out, "secmon_triggers_fired_total{{name=\"{}\"}} {}", escape_label(name), count);
+        }
+
+        out.push_str("# HELP secmon_clients_connected Clients connected to the event socket.\n");
+        out.push_str("# TYPE secmon_clients_connected gauge\n");
+        let _ = writeln!(out, "secmon_clients_connected {}", self.clients_connected.load(Ordering::Relaxed));
+
+        out.push_str("# HELP secmon_inotify_watches Active inotify watches.\n");
+        out.push_str("# TYPE secmon_inotify_watches gauge\n");
+        let _ = writeln!(out, "secmon_inotify_watches {}", self.inotify_watches.load(Ordering::Relaxed));
+
+        out.push_str("# HELP secmon_subsystem_up Whether a monitoring subsystem is running (0 when it failed or is disabled).\n");
+        out.push_str("# TYPE secmon_subsystem_up gauge\n");
+        for (name, status) in self.subsystems.lock().unwrap().iter() {
+            let up = u8::from(*status == SubsystemStatus::Running);
+            let _ = writeln!(out, "secmon_subsystem_up{{subsystem=\"{}\"}} {}", name, up);
+        }
+
+        out
+    }
+}
+
+/// Label values escape backslash, double quote and newline
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Count every broadcast event
+pub async fn count_events(metrics: Arc<Metrics>, mut receiver: broadcast::Receiver<SecurityEvent>) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => metrics.record_event(&event),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Metrics fell behind, {} events were not counted", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Bind the metrics endpoint, for `serve`
+pub async fn bind(listen: &str) -> Result<TcpListener> {
+    TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to bind metrics endpoint {}", listen))
+}
+
+/// Serve `/metrics` over plain HTTP/1.1, one request per connection
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>) -> Result<()> {
+    info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Failed to accept metrics connection: {}", e);
+                continue;
+            }
+        };
+        let metrics = Arc::clone(&metrics);
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(stream, &metrics).await {
+                debug!("Metrics request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_request(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    tokio::time::timeout(REQUEST_TIMEOUT, async {
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 || request.len() + read > MAX_REQUEST_BYTES {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+        Ok::<_, std::io::Error>(())
+    })
+    .await
+    .context("Timed out reading request")??;
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.lines().next().unwrap_or("").split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let (status, content_type, body) = match (method, target.split('?').next().unwrap_or("")) {
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found; metrics are at /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Only GET is supported\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -1,14 +1,24 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use log::{debug, error, info, warn};
-use std::collections::HashMap;
-use std::net::{IpAddr, SocketAddr};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 use tokio::time::interval;
+use uuid::Uuid;
 
+use crate::config::{Config, NetworkIDSConfig};
+use crate::geoip::GeoIp;
 use crate::{EventDetails, EventType, SecurityEvent, Severity};
 
+/// TCP_SYN_RECV in the `st` column of /proc/net/tcp
+const TCP_SYN_RECV: u8 = 0x03;
+/// TCP_LISTEN in the `st` column of /proc/net/tcp
+const TCP_LISTEN: u8 = 0x0A;
+
 #[derive(Debug)]
 struct ConnectionTracker {
     source_ip: IpAddr,
@@ -16,83 +26,161 @@ struct ConnectionTracker {
     first_seen: Instant,
     last_seen: Instant,
     connection_count: usize,
+    scan_alerted_at: Option<Instant>, // Last PortScanDetected for this source
+    new_connections: VecDeque<(Instant, u16)>, // When each connection first appeared, and its local port
+    flood_alerted_at: Option<Instant>, // Last ConnectionFlood for this source
 }
 
+/// A TCP connection as /proc/net/tcp identifies it: local port, remote address and port
+type ConnectionKey = (u16, IpAddr, u16);
+
+/// A row of /proc/net/tcp: local address and port, remote address and port, state
+type TcpRow = (IpAddr, u16, IpAddr, u16, u8);
+
 pub struct NetworkIDS {
     event_sender: broadcast::Sender<SecurityEvent>,
     connection_tracker: HashMap<IpAddr, ConnectionTracker>,
-    ping_tracker: HashMap<IpAddr, Instant>,
     scan_threshold: usize,
     scan_window: Duration,
-    ping_threshold: usize,
+    scan_cooldown: Duration,
+    syn_flood_threshold: usize,
+    connection_rate_threshold: usize,
+    alert_on_discovery: bool,
+    discovery_ports: Vec<u16>,
+    discovery_count: usize,
+    half_open: HashMap<IpAddr, usize>, // SYN_RECV connections per source in the current poll
+    syn_flood_alerts: HashMap<IpAddr, Instant>, // Last flood alert per source
+    known_connections: Option<HashSet<ConnectionKey>>, // Seen in the previous poll; None before the first
+    current_connections: HashSet<ConnectionKey>, // Seen so far in this poll
+    listening: HashSet<(IpAddr, u16)>, // Listening sockets in this poll; only connections to them are inbound
+    config_updates: watch::Receiver<Arc<Config>>,
+    icmp_sockets: Vec<Socket>, // Opened up front, before the daemon drops root
+    geoip: Option<Arc<GeoIp>>,
 }
 
 impl NetworkIDS {
-    pub fn new(event_sender: broadcast::Sender<SecurityEvent>, port_scan_threshold: usize, scan_window_seconds: u64, ping_threshold: usize) -> Self {
+    pub fn new(
+        event_sender: broadcast::Sender<SecurityEvent>,
+        config: &NetworkIDSConfig,
+        config_updates: watch::Receiver<Arc<Config>>,
+        icmp_sockets: Vec<Socket>,
+        geoip: Option<Arc<GeoIp>>,
+    ) -> Self {
         NetworkIDS {
             event_sender,
             connection_tracker: HashMap::new(),
-            ping_tracker: HashMap::new(),
-            scan_threshold: port_scan_threshold,
-            scan_window: Duration::from_secs(scan_window_seconds),
-            ping_threshold,
+            scan_threshold: config.port_scan_threshold,
+            scan_window: Duration::from_secs(config.scan_window_seconds),
+            scan_cooldown: Duration::from_secs(config.port_scan_cooldown_seconds),
+            syn_flood_threshold: config.syn_flood_threshold,
+            connection_rate_threshold: config.connection_rate_threshold,
+            alert_on_discovery: config.alert_on_discovery,
+            discovery_ports: config.discovery_ports.clone(),
+            discovery_count: config.discovery_count,
+            half_open: HashMap::new(),
+            syn_flood_alerts: HashMap::new(),
+            known_connections: None,
+            current_connections: HashSet::new(),
+            listening: HashSet::new(),
+            config_updates,
+            icmp_sockets,
+            geoip,
         }
     }
 
+    /// Pick up thresholds from a reloaded config
+    fn apply_config_updates(&mut self) {
+        if !self.config_updates.has_changed().unwrap_or(false) {
+            return;
+        }
+        let config = self.config_updates.borrow_and_update().network_ids.clone();
+        self.scan_threshold = config.port_scan_threshold;
+        self.scan_window = Duration::from_secs(config.scan_window_seconds);
+        self.scan_cooldown = Duration::from_secs(config.port_scan_cooldown_seconds);
+        self.syn_flood_threshold = config.syn_flood_threshold;
+        self.connection_rate_threshold = config.connection_rate_threshold;
+        self.alert_on_discovery = config.alert_on_discovery;
+        self.discovery_ports = config.discovery_ports;
+        self.discovery_count = config.discovery_count;
+        debug!("Network IDS thresholds reloaded");
+    }
+
     pub async fn start_monitoring(&mut self) -> Result<()> {
         info!("Starting network intrusion detection monitoring");
 
         // Start connection monitoring
         let mut connection_monitor = interval(Duration::from_secs(5));
 
-        // Start ICMP monitoring in a separate task (requires root for raw sockets)
-        let icmp_sender = self.event_sender.clone();
-        tokio::spawn(async move {
-            if let Err(e) = start_icmp_monitoring_task(icmp_sender).await {
-                warn!("ICMP monitoring failed (may need root privileges): {:?}", e);
-            }
-        });
+        self.spawn_icmp_monitor();
 
         loop {
             connection_monitor.tick().await;
+            self.apply_config_updates();
             if let Err(e) = self.check_network_connections().await {
                 error!("Network connection monitoring error: {}", e);
             }
         }
+    }
 
-        Ok(())
+    /// Start ICMP monitoring in a separate task (raw sockets when running as root), unless
+    /// monitor_icmp is off; like the sockets themselves that is only decided at startup
+    fn spawn_icmp_monitor(&mut self) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config_updates.borrow().network_ids.monitor_icmp {
+            info!("ICMP monitoring disabled in configuration (monitor_icmp)");
+            return None;
+        }
+
+        let icmp_monitor = IcmpMonitor {
+            event_sender: self.event_sender.clone(),
+            config_updates: self.config_updates.clone(),
+        };
+        let icmp_sockets = std::mem::take(&mut self.icmp_sockets);
+        Some(tokio::spawn(async move {
+            if let Err(e) = icmp_monitor.run(icmp_sockets).await {
+                warn!("ICMP monitoring failed: {:#}", e);
+            }
+        }))
     }
 
     async fn check_network_connections(&mut self) -> Result<()> {
         // Read network connections from /proc/net/tcp and /proc/net/tcp6
-        self.monitor_tcp_connections("/proc/net/tcp").await?;
-        self.monitor_tcp_connections("/proc/net/tcp6").await?;
+        let mut rows = self.read_tcp_connections("/proc/net/tcp").await;
+        rows.extend(self.read_tcp_connections("/proc/net/tcp6").await);
+
+        // Every listening socket first, so connections can be told apart by direction
+        self.listening = rows.iter()
+            .filter(|(_, _, _, _, state)| *state == TCP_LISTEN)
+            .map(|(local_ip, local_port, _, _, _)| (*local_ip, *local_port))
+            .collect();
+        for row in rows {
+            self.track_connection(row).await;
+        }
+        self.check_syn_floods().await;
+
+        // Connections first seen in this poll count as new in the next
+        self.known_connections = Some(std::mem::take(&mut self.current_connections));
 
         // Clean up old entries
         self.cleanup_old_connections();
-        self.cleanup_old_pings();
 
         Ok(())
     }
 
-    async fn monitor_tcp_connections(&mut self, proc_path: &str) -> Result<()> {
+    async fn read_tcp_connections(&self, proc_path: &str) -> Vec<TcpRow> {
         let content = match tokio::fs::read_to_string(proc_path).await {
             Ok(content) => content,
-            Err(_) => return Ok(()), // File might not exist, skip
+            Err(_) => return Vec::new(), // File might not exist, skip
         };
 
-        for line in content.lines().skip(1) { // Skip header
-            if let Some(connection) = self.parse_tcp_connection(line) {
-                self.track_connection(connection).await;
-            }
-        }
-
-        Ok(())
+        content.lines()
+            .skip(1) // Skip header
+            .filter_map(|line| self.parse_tcp_connection(line))
+            .collect()
     }
 
-    fn parse_tcp_connection(&self, line: &str) -> Option<(IpAddr, u16, IpAddr, u16)> {
+    fn parse_tcp_connection(&self, line: &str) -> Option<TcpRow> {
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 3 {
+        if parts.len() < 4 {
             return None;
         }
 
@@ -109,8 +197,9 @@ impl NetworkIDS {
         let local_port = u16::from_str_radix(local_parts[1], 16).ok()?;
         let remote_ip = self.parse_hex_ip(remote_parts[0])?;
         let remote_port = u16::from_str_radix(remote_parts[1], 16).ok()?;
+        let state = u8::from_str_radix(parts[3], 16).ok()?;
 
-        Some((local_ip, local_port, remote_ip, remote_port))
+        Some((local_ip, local_port, remote_ip, remote_port, state))
     }
 
     fn parse_hex_ip(&self, hex_str: &str) -> Option<IpAddr> {
@@ -134,20 +223,39 @@ impl NetworkIDS {
         None
     }
 
-    async fn track_connection(&mut self, (_local_ip, local_port, remote_ip, _remote_port): (IpAddr, u16, IpAddr, u16)) {
+    /// Whether a connection on this local address and port was accepted by one of our
+    /// listening sockets, rather than opened from an ephemeral port
+    fn is_inbound(&self, local_ip: IpAddr, local_port: u16) -> bool {
+        let any = match local_ip {
+            IpAddr::V4(_) => IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+        };
+        self.listening.contains(&(local_ip, local_port)) || self.listening.contains(&(any, local_port))
+    }
+
+    async fn track_connection(&mut self, (local_ip, local_port, remote_ip, remote_port, state): TcpRow) {
         let now = Instant::now();
 
-        // Skip localhost connections
-        if remote_ip.is_loopback() {
+        // Skip localhost connections, listening sockets (no remote end) and outbound connections
+        if remote_ip.is_loopback()
+            || state == TCP_LISTEN
+            || remote_ip.is_unspecified()
+            || !self.is_inbound(local_ip, local_port)
+        {
             return;
         }
 
-        // Track incoming connections (remote -> local)
-        let should_alert_scan;
-        let should_alert_discovery;
-        let updated_ports;
+        if state == TCP_SYN_RECV {
+            *self.half_open.entry(remote_ip).or_insert(0) += 1;
+        }
 
-        {
+        // Everything open at the first poll is a baseline
+        let key = (local_port, remote_ip, remote_port);
+        let is_new = self.current_connections.insert(key)
+            && self.known_connections.as_ref().is_some_and(|known| !known.contains(&key));
+
+        // Track incoming connections (remote -> local)
+        let (should_alert_scan, should_alert_flood, updated_ports) = {
             let tracker = self.connection_tracker.entry(remote_ip).or_insert_with(|| {
                 ConnectionTracker {
                     source_ip: remote_ip,
@@ -155,6 +263,9 @@ impl NetworkIDS {
                     first_seen: now,
                     last_seen: now,
                     connection_count: 0,
+                    scan_alerted_at: None,
+                    new_connections: VecDeque::new(),
+                    flood_alerted_at: None,
                 }
             });
 
@@ -166,54 +277,101 @@ impl NetworkIDS {
                 tracker.target_ports.push(local_port);
             }
 
-            // Check conditions for alerts
-            should_alert_scan = tracker.target_ports.len() >= self.scan_threshold
-                && now.duration_since(tracker.first_seen) <= self.scan_window;
+            // Check conditions for alerts. Once a source has been reported, further
+            // connections only alert again after the cooldown (or once the tracker ages out).
+            let cooling_down = tracker.scan_alerted_at
+                .is_some_and(|alerted_at| now.duration_since(alerted_at) < self.scan_cooldown);
+            let should_alert_scan = tracker.target_ports.len() >= self.scan_threshold
+                && now.duration_since(tracker.first_seen) <= self.scan_window
+                && !cooling_down;
+            if should_alert_scan {
+                tracker.scan_alerted_at = Some(now);
+            }
+
+            // Count new connections over the window, whichever ports they hit
+            if is_new {
+                tracker.new_connections.push_back((now, local_port));
+            }
+            while tracker.new_connections.front().is_some_and(|(seen, _)| now.duration_since(*seen) > self.scan_window) {
+                tracker.new_connections.pop_front();
+            }
+            let flood_alerted = tracker.flood_alerted_at
+                .is_some_and(|alerted_at| now.duration_since(alerted_at) < self.scan_window);
+            let should_alert_flood = is_new
+                && self.connection_rate_threshold > 0
+                && tracker.new_connections.len() > self.connection_rate_threshold
+                && !flood_alerted;
+            if should_alert_flood {
+                tracker.flood_alerted_at = Some(now);
+            }
 
             // Extract port list for discovery pattern check
-            updated_ports = tracker.target_ports.clone();
-        }
+            (should_alert_scan, should_alert_flood, tracker.target_ports.clone())
+        };
 
         // Check discovery pattern with extracted data
-        should_alert_discovery = self.is_discovery_pattern_ports(&updated_ports);
+        let should_alert_discovery = self.alert_on_discovery && self.is_discovery_pattern_ports(&updated_ports);
 
         // Generate alerts outside of the borrow scope
         if should_alert_scan {
             if let Some(tracker) = self.connection_tracker.get(&remote_ip) {
-                self.generate_port_scan_alert(&tracker).await;
+                self.generate_port_scan_alert(tracker).await;
+            }
+        }
+
+        if should_alert_flood {
+            if let Some(tracker) = self.connection_tracker.get(&remote_ip) {
+                self.generate_connection_flood_alert(tracker).await;
             }
         }
 
         if should_alert_discovery {
             if let Some(tracker) = self.connection_tracker.get(&remote_ip) {
-                self.generate_discovery_alert(&tracker).await;
+                self.generate_discovery_alert(tracker).await;
             }
         }
     }
 
-    fn is_discovery_pattern(&self, tracker: &ConnectionTracker) -> bool {
-        self.is_discovery_pattern_ports(&tracker.target_ports)
+    /// Alert on sources holding more half-open connections than `syn_flood_threshold`,
+    /// at most once per source per scan window while the flood lasts
+    async fn check_syn_floods(&mut self) {
+        let now = Instant::now();
+        let scan_window = self.scan_window;
+        self.syn_flood_alerts.retain(|_, alerted_at| now.duration_since(*alerted_at) < scan_window);
+
+        let half_open = std::mem::take(&mut self.half_open);
+        for (source_ip, count) in half_open {
+            if count <= self.syn_flood_threshold || self.syn_flood_alerts.contains_key(&source_ip) {
+                continue;
+            }
+            self.syn_flood_alerts.insert(source_ip, now);
+            self.generate_syn_flood_alert(source_ip, count).await;
+        }
     }
 
+    /// Whether a source has touched `discovery_count` or more of the configured service ports
     fn is_discovery_pattern_ports(&self, ports: &[u16]) -> bool {
-        // Common discovery ports
-        let discovery_ports = [21, 22, 23, 25, 53, 80, 110, 143, 443, 993, 995];
-
         let discovery_count = ports.iter()
-            .filter(|&&port| discovery_ports.contains(&port))
+            .filter(|&&port| self.discovery_ports.contains(&port))
             .count();
 
-        discovery_count >= 3 // 3 or more common service ports
+        discovery_count >= self.discovery_count
     }
 
     async fn generate_port_scan_alert(&self, tracker: &ConnectionTracker) {
         let mut metadata = HashMap::new();
         metadata.insert("source_ip".to_string(), tracker.source_ip.to_string());
         metadata.insert("ports_scanned".to_string(), tracker.target_ports.len().to_string());
+        metadata.insert("total_ports_at_alert".to_string(), tracker.target_ports.len().to_string());
+        metadata.insert("cooldown_seconds".to_string(), self.scan_cooldown.as_secs().to_string());
         metadata.insert("scan_duration".to_string(),
                         format!("{:.1}s", Instant::now().duration_since(tracker.first_seen).as_secs_f64()));
+        if let Some(geoip) = &self.geoip {
+            geoip.enrich(tracker.source_ip, &mut metadata);
+        }
 
         let event = SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
             event_type: EventType::PortScanDetected,
             path: std::path::PathBuf::from("/proc/net/tcp"),
@@ -233,6 +391,70 @@ impl NetworkIDS {
         }
     }
 
+    async fn generate_syn_flood_alert(&self, source_ip: IpAddr, half_open: usize) {
+        let mut metadata = HashMap::new();
+        metadata.insert("source_ip".to_string(), source_ip.to_string());
+        metadata.insert("half_open_connections".to_string(), half_open.to_string());
+        metadata.insert("threshold".to_string(), self.syn_flood_threshold.to_string());
+
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::SynFloodDetected,
+            path: std::path::PathBuf::from("/proc/net/tcp"),
+            details: EventDetails {
+                severity: Severity::Critical,
+                description: format!(
+                    "SYN flood detected from {} with {} half-open connections",
+                    source_ip, half_open
+                ),
+                metadata,
+            },
+        };
+
+        if let Err(e) = self.event_sender.send(event) {
+            error!("Failed to send SYN flood alert: {}", e);
+        }
+    }
+
+    async fn generate_connection_flood_alert(&self, tracker: &ConnectionTracker) {
+        let count = tracker.new_connections.len();
+        let window = self.scan_window.as_secs().max(1);
+        let per_minute = count as f64 * 60.0 / window as f64;
+        let ports: BTreeSet<u16> = tracker.new_connections.iter().map(|(_, port)| *port).collect();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("source_ip".to_string(), tracker.source_ip.to_string());
+        metadata.insert("connection_count".to_string(), count.to_string());
+        metadata.insert("window_seconds".to_string(), window.to_string());
+        metadata.insert("connections_per_minute".to_string(), format!("{:.1}", per_minute));
+        metadata.insert("threshold".to_string(), self.connection_rate_threshold.to_string());
+        metadata.insert("ports".to_string(),
+                        ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(","));
+        if let Some(geoip) = &self.geoip {
+            geoip.enrich(tracker.source_ip, &mut metadata);
+        }
+
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::ConnectionFlood,
+            path: std::path::PathBuf::from("/proc/net/tcp"),
+            details: EventDetails {
+                severity: Severity::High,
+                description: format!(
+                    "Connection flood from {}: {} new connections within {}s ({:.1}/min)",
+                    tracker.source_ip, count, window, per_minute
+                ),
+                metadata,
+            },
+        };
+
+        if let Err(e) = self.event_sender.send(event) {
+            error!("Failed to send connection flood alert: {}", e);
+        }
+    }
+
     async fn generate_discovery_alert(&self, tracker: &ConnectionTracker) {
         let mut metadata = HashMap::new();
         metadata.insert("source_ip".to_string(), tracker.source_ip.to_string());
@@ -240,6 +462,7 @@ impl NetworkIDS {
                         tracker.target_ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(","));
 
         let event = SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
             event_type: EventType::NetworkDiscovery,
             path: std::path::PathBuf::from("/proc/net/tcp"),
@@ -267,69 +490,188 @@ impl NetworkIDS {
             now.duration_since(tracker.last_seen) < timeout
         });
     }
+}
 
-    fn cleanup_old_pings(&mut self) {
-        let now = Instant::now();
-        let timeout = Duration::from_secs(60); // 1 minute
+/// Raw ICMPv4 and ICMPv6 sockets for [`NetworkIDS::new`], whichever can be opened.
+/// Needs CAP_NET_RAW, so this runs before the daemon drops privileges.
+pub fn open_icmp_sockets() -> Vec<Socket> {
+    [
+        (Domain::IPV4, Protocol::ICMPV4),
+        (Domain::IPV6, Protocol::ICMPV6),
+    ]
+    .into_iter()
+    .filter_map(|(domain, protocol)| match Socket::new(domain, Type::from(libc::SOCK_RAW), Some(protocol)) {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            debug!("Raw {:?} socket unavailable: {}", protocol, e);
+            None
+        }
+    })
+    .collect()
+}
 
-        self.ping_tracker.retain(|_, &mut last_ping| {
-            now.duration_since(last_ping) < timeout
-        });
+/// Echo-request (ping) detection, run as its own task.
+///
+/// With CAP_NET_RAW a raw ICMP socket sees every incoming echo request, so pings are
+/// counted per source address. Without it the only signal is the kernel's system-wide
+/// `InEchos` counter in /proc/net/snmp: alerts then carry the number of echo requests in
+/// the window but no source address, and include pings over loopback.
+struct IcmpMonitor {
+    event_sender: broadcast::Sender<SecurityEvent>,
+    config_updates: watch::Receiver<Arc<Config>>, // Thresholds are read from here so reloads apply
+}
+
+impl IcmpMonitor {
+    /// Current (ping_threshold, window)
+    fn thresholds(&self) -> (usize, Duration) {
+        let config = self.config_updates.borrow();
+        (config.network_ids.ping_threshold, Duration::from_secs(config.network_ids.scan_window_seconds))
     }
 
-    async fn monitor_system_logs_for_pings(&mut self) -> Result<()> {
-        // Monitor system logs for ping activity
-        // This is a fallback method when raw sockets aren't available
+    async fn run(self, sockets: Vec<Socket>) -> Result<()> {
+        if sockets.is_empty() {
+            info!("ICMP monitoring using /proc/net/snmp counters (raw sockets need root; source addresses unavailable)");
+            return self.watch_snmp_counters().await;
+        }
 
-        let mut interval = tokio::time::interval(Duration::from_secs(10));
+        info!("ICMP monitoring using raw sockets");
+        let monitor = std::sync::Arc::new(self);
+        let mut listeners = Vec::new();
+        for socket in sockets {
+            let monitor = monitor.clone();
+            listeners.push(tokio::spawn(async move { monitor.listen_raw(socket).await }));
+        }
+        for listener in listeners {
+            listener.await??;
+        }
+        Ok(())
+    }
+
+    /// Receive loop on one raw ICMP socket
+    async fn listen_raw(&self, socket: Socket) -> Result<()> {
+        // UdpSocket only wraps recvfrom(2) here, which works the same on raw sockets
+        socket.set_nonblocking(true).context("Failed to make raw ICMP socket non-blocking")?;
+        let socket = tokio::net::UdpSocket::from_std(std::net::UdpSocket::from(socket))
+            .context("Failed to register raw ICMP socket with the runtime")?;
+        let mut buffer = [0u8; 2048];
+        let mut sources: HashMap<IpAddr, (Instant, usize)> = HashMap::new(); // first ping in window, count
 
         loop {
-            interval.tick().await;
+            let (len, source) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e).context("Failed to read from raw ICMP socket"),
+            };
+
+            let source_ip = source.ip();
+            if !Self::is_echo_request(source_ip, &buffer[..len]) {
+                continue;
+            }
+
+            let (ping_threshold, window) = self.thresholds();
+            let now = Instant::now();
+            sources.retain(|_, (first_seen, _)| now.duration_since(*first_seen) <= window);
+            let (first_seen, count) = sources.entry(source_ip).or_insert((now, 0));
+            *count += 1;
+
+            if *count > ping_threshold {
+                let mut metadata = HashMap::new();
+                metadata.insert("source_ip".to_string(), source_ip.to_string());
+                metadata.insert("echo_requests".to_string(), count.to_string());
+                metadata.insert("window".to_string(), format!("{:.1}s", now.duration_since(*first_seen).as_secs_f64()));
+                metadata.insert("detection".to_string(), "raw_socket".to_string());
 
-            // Check netstat for ICMP statistics (this is a simplified approach)
-            if let Err(e) = self.check_icmp_activity().await {
-                debug!("ICMP monitoring error: {}", e);
+                let description = format!("ICMP ping detected from {} ({} echo requests)", source_ip, count);
+                self.send_ping_alert(description, metadata);
+
+                // Start a fresh window so a continuous ping alerts once per threshold, not per packet
+                sources.remove(&source_ip);
             }
         }
     }
 
-    async fn check_icmp_activity(&mut self) -> Result<()> {
-        // Read /proc/net/snmp for ICMP statistics
-        let content = tokio::fs::read_to_string("/proc/net/snmp").await?;
-
-        for line in content.lines() {
-            if line.starts_with("Icmp:") && !line.contains("InMsgs") {
-                self.parse_icmp_stats(line).await;
-                break;
+    /// Raw IPv4 sockets deliver the IP header before the ICMP message; raw ICMPv6
+    /// sockets start at the ICMPv6 header
+    fn is_echo_request(source_ip: IpAddr, packet: &[u8]) -> bool {
+        match source_ip {
+            IpAddr::V4(_) => {
+                let header_len = packet.first().map_or(0, |byte| (byte & 0x0f) as usize * 4);
+                // ICMP type 8 = echo request
+                header_len >= 20 && packet.get(header_len) == Some(&8)
             }
+            // ICMPv6 type 128 = echo request
+            IpAddr::V6(_) => packet.first() == Some(&128),
         }
-
-        Ok(())
     }
 
-    async fn parse_icmp_stats(&mut self, line: &str) {
-        // Parse ICMP statistics - this is a basic implementation
-        // In a production environment, you'd want more sophisticated monitoring
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() > 8 {
-            // Check for increase in ICMP echo requests (position may vary)
-            // This is a simplified detection - real implementation would track deltas
-            debug!("ICMP activity detected in system stats");
+    /// Fallback when raw sockets are unavailable: alert when the system-wide InEchos
+    /// counter grows by more than `ping_threshold` within the window
+    async fn watch_snmp_counters(&self) -> Result<()> {
+        let mut poll = tokio::time::interval(Duration::from_secs(10));
+        let mut samples: VecDeque<(Instant, u64)> = VecDeque::new();
+
+        loop {
+            poll.tick().await;
+
+            let in_echos = match Self::read_in_echos().await {
+                Ok(in_echos) => in_echos,
+                Err(e) => {
+                    debug!("ICMP monitoring error: {}", e);
+                    continue;
+                }
+            };
+
+            let (ping_threshold, window) = self.thresholds();
+            let now = Instant::now();
+            while samples.front().is_some_and(|(sampled_at, _)| now.duration_since(*sampled_at) > window) {
+                samples.pop_front();
+            }
+            samples.push_back((now, in_echos));
+
+            let (window_start, baseline) = samples[0];
+            let delta = in_echos.saturating_sub(baseline);
+            if delta as usize > ping_threshold {
+                let mut metadata = HashMap::new();
+                metadata.insert("echo_requests".to_string(), delta.to_string());
+                metadata.insert("window".to_string(), format!("{:.1}s", now.duration_since(window_start).as_secs_f64()));
+                metadata.insert("detection".to_string(), "snmp_counters".to_string());
+
+                let description = format!("ICMP ping activity: {} echo requests received (source unknown)", delta);
+                self.send_ping_alert(description, metadata);
+
+                // The counted pings have been reported; measure the next window from here
+                samples.clear();
+                samples.push_back((now, in_echos));
+            }
         }
     }
 
-    pub async fn generate_ping_alert(&self, source_ip: IpAddr) {
-        let mut metadata = HashMap::new();
-        metadata.insert("source_ip".to_string(), source_ip.to_string());
-        metadata.insert("protocol".to_string(), "ICMP".to_string());
+    /// The Icmp InEchos counter, located by name since the column order varies between kernels
+    async fn read_in_echos() -> Result<u64> {
+        let content = tokio::fs::read_to_string("/proc/net/snmp").await
+            .context("Failed to read /proc/net/snmp")?;
+        let mut icmp_lines = content.lines().filter(|line| line.starts_with("Icmp:"));
+
+        let header = icmp_lines.next().context("No Icmp header in /proc/net/snmp")?;
+        let values = icmp_lines.next().context("No Icmp values in /proc/net/snmp")?;
+        let index = header.split_whitespace().position(|name| name == "InEchos")
+            .context("No InEchos counter in /proc/net/snmp")?;
+
+        values.split_whitespace().nth(index)
+            .context("Truncated Icmp values in /proc/net/snmp")?
+            .parse::<u64>()
+            .context("Invalid InEchos counter")
+    }
 
+    fn send_ping_alert(&self, description: String, metadata: HashMap<String, String>) {
         let event = SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
             event_type: EventType::PingDetected,
             path: std::path::PathBuf::from("/proc/net/icmp"),
             details: EventDetails {
                 severity: Severity::Low,
-                description: format!("ICMP ping detected from {}", source_ip),
+                description,
                 metadata,
             },
         };
@@ -340,69 +682,107 @@ impl NetworkIDS {
     }
 }
 
-// Standalone ICMP monitoring function
-async fn start_icmp_monitoring_task(event_sender: broadcast::Sender<SecurityEvent>) -> Result<()> {
-    // Monitor system logs for ping activity
-    // This is a fallback method when raw sockets aren't available
-
-    let mut interval = tokio::time::interval(Duration::from_secs(10));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TCP_ESTABLISHED in the `st` column of /proc/net/tcp
+    const TCP_ESTABLISHED: u8 = 0x01;
+
+    fn ids_with(config: NetworkIDSConfig) -> (NetworkIDS, broadcast::Receiver<SecurityEvent>) {
+        let (event_sender, events) = broadcast::channel(256);
+        let full_config = Config { network_ids: config.clone(), ..Config::default() };
+        let (_, config_updates) = watch::channel(Arc::new(full_config));
+        let mut ids = NetworkIDS::new(event_sender, &config, config_updates, Vec::new(), None);
+        // Past the first poll, so connections count as new rather than as the baseline
+        ids.known_connections = Some(HashSet::new());
+        (ids, events)
+    }
 
-    loop {
-        interval.tick().await;
+    /// One source connecting to SSH, HTTP and HTTPS: a discovery pattern with the default
+    /// `discovery_count`, but too few ports and connections for a port scan or a flood
+    async fn sweep(ids: &mut NetworkIDS) {
+        let local_ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let source: IpAddr = "198.51.100.7".parse().unwrap();
+        let ports = [22, 80, 443];
+        ids.listening.extend(ports.iter().map(|&port| (local_ip, port)));
+        for remote_port in 40000..40006 {
+            let local_port = ports[remote_port as usize % ports.len()];
+            ids.track_connection((local_ip, local_port, source, remote_port, TCP_ESTABLISHED)).await;
+        }
+    }
 
-        // Check netstat for ICMP statistics (this is a simplified approach)
-        if let Err(e) = check_icmp_activity_standalone(&event_sender).await {
-            debug!("ICMP monitoring error: {}", e);
+    fn alert_types(events: &mut broadcast::Receiver<SecurityEvent>) -> Vec<&'static str> {
+        let mut types = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            types.push(event.event_type.name());
         }
+        types
     }
-}
 
-async fn check_icmp_activity_standalone(event_sender: &broadcast::Sender<SecurityEvent>) -> Result<()> {
-    // Read /proc/net/snmp for ICMP statistics
-    let content = tokio::fs::read_to_string("/proc/net/snmp").await?;
+    #[tokio::test]
+    async fn disabled_detections_raise_no_alerts() {
+        let config = NetworkIDSConfig {
+            alert_on_discovery: false,
+            monitor_icmp: false,
+            ..Default::default()
+        };
+        let (mut ids, mut events) = ids_with(config);
 
-    for line in content.lines() {
-        if line.starts_with("Icmp:") && !line.contains("InMsgs") {
-            parse_icmp_stats_standalone(line, event_sender).await;
-            break;
-        }
+        sweep(&mut ids).await;
+        assert!(alert_types(&mut events).is_empty());
+        assert!(ids.spawn_icmp_monitor().is_none());
     }
 
-    Ok(())
-}
+    #[tokio::test]
+    async fn enabled_detections_alert_on_the_same_traffic() {
+        let config = NetworkIDSConfig {
+            alert_on_discovery: true,
+            monitor_icmp: true,
+            ..Default::default()
+        };
+        let (mut ids, mut events) = ids_with(config);
+
+        sweep(&mut ids).await;
+        let types = alert_types(&mut events);
+        assert!(!types.is_empty());
+        assert!(types.iter().all(|name| *name == "NetworkDiscovery"));
 
-async fn parse_icmp_stats_standalone(line: &str, event_sender: &broadcast::Sender<SecurityEvent>) {
-    // Parse ICMP statistics - this is a basic implementation
-    // In a production environment, you'd want more sophisticated monitoring
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() > 8 {
-        // Check for increase in ICMP echo requests (position may vary)
-        // This is a simplified detection - real implementation would track deltas
-        debug!("ICMP activity detected in system stats");
-
-        // For demonstration, generate a ping alert
-        // In reality, you'd track changes in counters
-        generate_ping_alert_standalone(std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)), event_sender).await;
+        let icmp = ids.spawn_icmp_monitor().expect("ICMP monitoring not started");
+        icmp.abort();
     }
-}
 
-async fn generate_ping_alert_standalone(source_ip: IpAddr, event_sender: &broadcast::Sender<SecurityEvent>) {
-    let mut metadata = HashMap::new();
-    metadata.insert("source_ip".to_string(), source_ip.to_string());
-    metadata.insert("protocol".to_string(), "ICMP".to_string());
-
-    let event = SecurityEvent {
-        timestamp: Utc::now(),
-        event_type: EventType::PingDetected,
-        path: std::path::PathBuf::from("/proc/net/icmp"),
-        details: EventDetails {
-            severity: Severity::Low,
-            description: format!("ICMP ping detected from {}", source_ip),
-            metadata,
-        },
-    };
-
-    if let Err(e) = event_sender.send(event) {
-        error!("Failed to send ping alert: {}", e);
+    #[tokio::test]
+    async fn outbound_connections_are_not_a_flood() {
+        let config = NetworkIDSConfig {
+            alert_on_discovery: true,
+            connection_rate_threshold: 10,
+            port_scan_threshold: 5,
+            ..Default::default()
+        };
+        let (mut ids, mut events) = ids_with(config);
+        let local_ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let server: IpAddr = "198.51.100.7".parse().unwrap();
+        let any: IpAddr = "0.0.0.0".parse().unwrap();
+
+        // Our own listeners, as /proc/net/tcp lists them
+        for port in [22, 80] {
+            ids.listening.insert((any, port));
+            ids.track_connection((any, port, any, 0, TCP_LISTEN)).await;
+        }
+        assert!(ids.connection_tracker.is_empty());
+
+        // Many connections out to one server, each from its own ephemeral port
+        for local_port in 40000..40100 {
+            ids.track_connection((local_ip, local_port, server, 443, TCP_ESTABLISHED)).await;
+        }
+        assert!(ids.connection_tracker.is_empty());
+        assert!(alert_types(&mut events).is_empty());
+
+        // The same number coming in to a listening port is a flood
+        for remote_port in 40000..40100 {
+            ids.track_connection((local_ip, 80, server, remote_port, TCP_ESTABLISHED)).await;
+        }
+        assert_eq!(alert_types(&mut events), vec!["ConnectionFlood"]);
     }
-}
\ No newline at end of file
+}
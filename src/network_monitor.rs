@@ -1,41 +1,137 @@
 use anyhow::{Context, Result};
-use log::{debug, error, warn};
-use procfs::net::{TcpNetEntry, UdpNetEntry};
+use ipnetwork::IpNetwork;
+use log::{debug, error};
+use std::ffi::CStr;
+use procfs::net::TcpState;
+use procfs::process::FDTarget;
 use std::collections::HashSet;
-use std::net::SocketAddr;
-use tokio::sync::broadcast;
-use tokio::time::{interval, Duration};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+use tokio::time::{interval, Duration, Instant};
+use uuid::Uuid;
 
+use crate::config::{Config, NetworkMonitorConfig};
+use crate::geoip::GeoIp;
 use crate::{EventType, SecurityEvent, EventDetails, Severity};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use chrono::Utc;
 
+/// Reverse lookups are abandoned after this long so a slow resolver can't stall polling
+const DNS_LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Resolved (and failed) lookups are reused for this long
+const DNS_CACHE_TTL: Duration = Duration::from_secs(600);
+const DNS_CACHE_CAPACITY: usize = 1024;
+
 pub struct NetworkMonitor {
     event_sender: broadcast::Sender<SecurityEvent>,
     known_connections: HashSet<SocketAddr>,
+    known_listeners: HashMap<(&'static str, SocketAddr), u64>, // (protocol, local address) -> socket inode
+    report_port_closed: bool,
+    // UDP is connectionless, so flows are keyed by (local, remote) and remembered until idle
+    known_udp_flows: HashMap<(SocketAddr, SocketAddr), Instant>,
+    udp_idle_timeout: Duration,
     poll_interval: Duration,
+    allow_networks: Vec<IpNetwork>,
+    deny_networks: Vec<IpNetwork>,
+    resolve_dns: bool,
+    report_existing: bool, // baseline_mode = "none": report what is open at startup too
+    reporting_existing: bool, // Set during that first poll, to flag its events
+    dns_cache: HashMap<IpAddr, (Option<String>, Instant)>, // IP -> (host name, looked up at)
+    config_updates: watch::Receiver<Arc<Config>>,
+    geoip: Option<Arc<GeoIp>>,
 }
 
 impl NetworkMonitor {
-    pub fn new(event_sender: broadcast::Sender<SecurityEvent>) -> Self {
-        Self {
+    pub fn new(
+        event_sender: broadcast::Sender<SecurityEvent>,
+        config: &NetworkMonitorConfig,
+        config_updates: watch::Receiver<Arc<Config>>,
+        geoip: Option<Arc<GeoIp>>,
+    ) -> Result<Self> {
+        Ok(Self {
             event_sender,
             known_connections: HashSet::new(),
+            known_listeners: HashMap::new(),
+            report_port_closed: config.report_port_closed,
+            known_udp_flows: HashMap::new(),
+            udp_idle_timeout: Duration::from_secs(config.udp_idle_timeout_seconds),
             poll_interval: Duration::from_secs(2),
+            allow_networks: config.allow_networks().context("Invalid network_monitor.allow_cidrs")?,
+            deny_networks: config.deny_networks().context("Invalid network_monitor.deny_cidrs")?,
+            resolve_dns: config.resolve_dns,
+            report_existing: config.reports_existing().context("Invalid network_monitor.baseline_mode")?,
+            reporting_existing: false,
+            dns_cache: HashMap::new(),
+            config_updates,
+            geoip,
+        })
+    }
+
+    /// Pick up settings from a reloaded config. Known connections are kept, so a reload
+    /// doesn't re-report everything that is already open.
+    fn apply_config_updates(&mut self) {
+        if !self.config_updates.has_changed().unwrap_or(false) {
+            return;
+        }
+        let config = self.config_updates.borrow_and_update().network_monitor.clone();
+
+        match (config.allow_networks(), config.deny_networks()) {
+            (Ok(allow_networks), Ok(deny_networks)) => {
+                self.allow_networks = allow_networks;
+                self.deny_networks = deny_networks;
+            }
+            (Err(e), _) | (_, Err(e)) => error!("Ignoring reloaded network_monitor CIDR lists: {}", e),
+        }
+        self.udp_idle_timeout = Duration::from_secs(config.udp_idle_timeout_seconds);
+        if self.resolve_dns && !config.resolve_dns {
+            self.dns_cache.clear();
+        }
+        self.resolve_dns = config.resolve_dns;
+        self.report_port_closed = config.report_port_closed;
+        debug!("Network monitor settings reloaded");
+    }
+
+    /// IPv4 peers of dual-stack sockets show up as ::ffff:a.b.c.d; compare them as IPv4
+    fn canonical_ip(ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V6(ipv6) => ipv6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        }
+    }
+
+    fn in_networks(networks: &[IpNetwork], ip: IpAddr) -> bool {
+        let ip = Self::canonical_ip(ip);
+        networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// Loopback and allowlisted peers are not worth reporting; the denylist wins over both
+    fn is_ignored(&self, ip: IpAddr) -> bool {
+        if Self::in_networks(&self.deny_networks, ip) {
+            return false;
         }
+        Self::canonical_ip(ip).is_loopback() || Self::in_networks(&self.allow_networks, ip)
     }
 
     pub async fn start_monitoring(&mut self) -> Result<()> {
         let mut interval_timer = interval(self.poll_interval);
 
-        // Initialize with current connections to avoid spam on startup
-        if let Err(e) = self.initialize_known_connections().await {
+        if self.report_existing {
+            // Nothing is trusted for having been there first; report it all once, flagged
+            self.reporting_existing = true;
+            if let Err(e) = self.check_new_connections().await {
+                error!("Error reporting existing network connections: {}", e);
+            }
+            self.reporting_existing = false;
+        } else if let Err(e) = self.initialize_known_connections().await {
+            // Initialize with current connections to avoid spam on startup
             error!("Failed to initialize network connections: {}", e);
         }
 
         loop {
             interval_timer.tick().await;
+            self.apply_config_updates();
 
             if let Err(e) = self.check_new_connections().await {
                 error!("Error checking network connections: {}", e);
@@ -48,6 +144,9 @@ impl NetworkMonitor {
         if let Ok(tcp_entries) = procfs::net::tcp() {
             for entry in tcp_entries {
                 self.known_connections.insert(entry.remote_address);
+                if entry.state == TcpState::Listen {
+                    self.known_listeners.insert(("TCP", entry.local_address), entry.inode);
+                }
             }
         }
 
@@ -55,24 +154,48 @@ impl NetworkMonitor {
         if let Ok(tcp6_entries) = procfs::net::tcp6() {
             for entry in tcp6_entries {
                 self.known_connections.insert(entry.remote_address);
+                if entry.state == TcpState::Listen {
+                    self.known_listeners.insert(("TCP6", entry.local_address), entry.inode);
+                }
+            }
+        }
+
+        // Get current UDP flows
+        let now = Instant::now();
+        for (_, entry) in Self::udp_entries() {
+            if !entry.remote_address.ip().is_unspecified() {
+                self.known_udp_flows.insert((entry.local_address, entry.remote_address), now);
             }
         }
 
-        debug!("Initialized with {} known connections", self.known_connections.len());
+        debug!(
+            "Initialized with {} known connections, {} listening ports and {} UDP flows",
+            self.known_connections.len(),
+            self.known_listeners.len(),
+            self.known_udp_flows.len()
+        );
         Ok(())
     }
 
     async fn check_new_connections(&mut self) -> Result<()> {
         let mut current_connections = HashSet::new();
+        let mut current_listeners = HashMap::new();
+        // Socket inode -> pid, built at most once per poll and only if something new showed up
+        let mut socket_owners: Option<HashMap<u64, i32>> = None;
 
         // Check TCP connections
         if let Ok(tcp_entries) = procfs::net::tcp() {
             for entry in tcp_entries {
                 let remote_addr = entry.remote_address;
                 current_connections.insert(remote_addr);
+                if entry.state == TcpState::Listen {
+                    current_listeners.insert(("TCP", entry.local_address), entry.inode);
+                }
 
-                if !self.known_connections.contains(&remote_addr) && !remote_addr.ip().is_loopback() {
-                    self.emit_network_event(&entry, "TCP").await;
+                if self.is_new_connection(remote_addr) {
+                    let owners = socket_owners.get_or_insert_with(Self::scan_socket_owners);
+                    let state = format!("{:?}", entry.state);
+                    self.emit_network_event("TCP", entry.local_address, remote_addr, state, entry.inode, owners.get(&entry.inode).copied()).await;
                 }
             }
         }
@@ -82,36 +205,225 @@ impl NetworkMonitor {
             for entry in tcp6_entries {
                 let remote_addr = entry.remote_address;
                 current_connections.insert(remote_addr);
+                if entry.state == TcpState::Listen {
+                    current_listeners.insert(("TCP6", entry.local_address), entry.inode);
+                }
 
-                if !self.known_connections.contains(&remote_addr) && !remote_addr.ip().is_loopback() {
-                    self.emit_network_event(&entry, "TCP6").await;
+                if self.is_new_connection(remote_addr) {
+                    let owners = socket_owners.get_or_insert_with(Self::scan_socket_owners);
+                    let state = format!("{:?}", entry.state);
+                    self.emit_network_event("TCP6", entry.local_address, remote_addr, state, entry.inode, owners.get(&entry.inode).copied()).await;
                 }
             }
         }
 
+        // Check UDP flows. Unconnected sockets (servers, most resolvers) have no remote
+        // address; only connected ones identify a peer.
+        let now = Instant::now();
+        for (protocol, entry) in Self::udp_entries() {
+            let remote_addr = entry.remote_address;
+            if remote_addr.ip().is_unspecified() {
+                continue;
+            }
+
+            let flow = (entry.local_address, remote_addr);
+            let is_new = self.known_udp_flows.insert(flow, now).is_none();
+            if is_new && !self.is_ignored(remote_addr.ip()) {
+                let owners = socket_owners.get_or_insert_with(Self::scan_socket_owners);
+                let state = format!("{:?}", entry.state);
+                self.emit_network_event(protocol, entry.local_address, remote_addr, state, entry.inode, owners.get(&entry.inode).copied()).await;
+            }
+        }
+        let udp_idle_timeout = self.udp_idle_timeout;
+        self.known_udp_flows.retain(|_, last_seen| now.duration_since(*last_seen) < udp_idle_timeout);
+
+        self.check_listeners(current_listeners, &mut socket_owners);
+
         // Update known connections
         self.known_connections = current_connections;
         Ok(())
     }
 
-    async fn emit_network_event(&self, entry: &TcpNetEntry, protocol: &str) {
-        let severity = self.classify_connection_severity(&entry.remote_address.to_string());
+    /// Report TCP sockets that started listening since the last poll and, with
+    /// report_port_closed, ones that stopped
+    fn check_listeners(
+        &mut self,
+        current_listeners: HashMap<(&'static str, SocketAddr), u64>,
+        socket_owners: &mut Option<HashMap<u64, i32>>,
+    ) {
+        for (&(protocol, local_address), &inode) in &current_listeners {
+            if !self.known_listeners.contains_key(&(protocol, local_address)) {
+                let owner = socket_owners.get_or_insert_with(Self::scan_socket_owners).get(&inode).copied();
+                self.emit_port_event(EventType::PortOpened, protocol, local_address, inode, owner);
+            }
+        }
+        if self.report_port_closed {
+            for (&(protocol, local_address), &inode) in &self.known_listeners {
+                if !current_listeners.contains_key(&(protocol, local_address)) {
+                    self.emit_port_event(EventType::PortClosed, protocol, local_address, inode, None);
+                }
+            }
+        }
+        self.known_listeners = current_listeners;
+    }
+
+    /// A TCP peer worth reporting. Listening sockets (no peer yet) are part of the startup
+    /// report's noise rather than connections, so it leaves them out.
+    fn is_new_connection(&self, remote_addr: SocketAddr) -> bool {
+        if self.reporting_existing && remote_addr.ip().is_unspecified() {
+            return false;
+        }
+        !self.known_connections.contains(&remote_addr) && !self.is_ignored(remote_addr.ip())
+    }
+
+    fn udp_entries() -> Vec<(&'static str, procfs::net::UdpNetEntry)> {
+        let mut entries = Vec::new();
+        if let Ok(udp_entries) = procfs::net::udp() {
+            entries.extend(udp_entries.into_iter().map(|entry| ("UDP", entry)));
+        }
+        if let Ok(udp6_entries) = procfs::net::udp6() {
+            entries.extend(udp6_entries.into_iter().map(|entry| ("UDP6", entry)));
+        }
+        entries
+    }
+
+    /// Map socket inodes to the pid holding them by walking /proc/*/fd. Processes we
+    /// cannot inspect (other users without CAP_SYS_PTRACE) are skipped.
+    fn scan_socket_owners() -> HashMap<u64, i32> {
+        let mut owners = HashMap::new();
+
+        let processes = match procfs::process::all_processes() {
+            Ok(processes) => processes,
+            Err(e) => {
+                debug!("Failed to list processes for socket lookup: {}", e);
+                return owners;
+            }
+        };
+
+        for process in processes.flatten() {
+            let Ok(fds) = process.fd() else { continue };
+            for fd in fds.flatten() {
+                if let FDTarget::Socket(inode) = fd.target {
+                    owners.insert(inode, process.pid);
+                }
+            }
+        }
+
+        owners
+    }
+
+    /// Cached reverse-DNS name for `ip`; None when it has no PTR record or the lookup timed out
+    async fn reverse_dns(&mut self, ip: IpAddr) -> Option<String> {
+        let now = Instant::now();
+        if let Some((host, looked_up_at)) = self.dns_cache.get(&ip) {
+            if now.duration_since(*looked_up_at) < DNS_CACHE_TTL {
+                return host.clone();
+            }
+        }
+
+        let host = match tokio::time::timeout(
+            DNS_LOOKUP_TIMEOUT,
+            tokio::task::spawn_blocking(move || Self::lookup_host(ip)),
+        )
+        .await
+        {
+            Ok(Ok(host)) => host,
+            Ok(Err(e)) => {
+                debug!("Reverse DNS lookup for {} failed: {}", ip, e);
+                None
+            }
+            Err(_) => {
+                debug!("Reverse DNS lookup for {} timed out", ip);
+                None
+            }
+        };
+
+        if self.dns_cache.len() >= DNS_CACHE_CAPACITY {
+            self.dns_cache.retain(|_, (_, looked_up_at)| now.duration_since(*looked_up_at) < DNS_CACHE_TTL);
+        }
+        if self.dns_cache.len() >= DNS_CACHE_CAPACITY {
+            if let Some(oldest) = self.dns_cache.iter().min_by_key(|(_, (_, looked_up_at))| *looked_up_at).map(|(ip, _)| *ip) {
+                self.dns_cache.remove(&oldest);
+            }
+        }
+        self.dns_cache.insert(ip, (host.clone(), now));
+
+        host
+    }
+
+    /// Blocking PTR lookup through the system resolver (getnameinfo with NI_NAMEREQD)
+    fn lookup_host(ip: IpAddr) -> Option<String> {
+        let address = socket2::SockAddr::from(SocketAddr::new(ip, 0));
+        let mut host = [0 as libc::c_char; libc::NI_MAXHOST as usize];
+
+        let result = unsafe {
+            libc::getnameinfo(
+                address.as_ptr(),
+                address.len(),
+                host.as_mut_ptr(),
+                host.len() as libc::socklen_t,
+                std::ptr::null_mut(),
+                0,
+                libc::NI_NAMEREQD,
+            )
+        };
+        if result != 0 {
+            return None;
+        }
+
+        let host = unsafe { CStr::from_ptr(host.as_ptr()) };
+        Some(host.to_string_lossy().into_owned())
+    }
+
+    async fn emit_network_event(
+        &mut self,
+        protocol: &str,
+        local_address: SocketAddr,
+        remote_address: SocketAddr,
+        state: String,
+        inode: u64,
+        owner: Option<i32>,
+    ) {
+        let severity = if Self::in_networks(&self.deny_networks, remote_address.ip()) {
+            Severity::High
+        } else {
+            self.classify_connection_severity(&remote_address.to_string())
+        };
 
         let mut metadata = HashMap::new();
         metadata.insert("protocol".to_string(), protocol.to_string());
-        metadata.insert("local_address".to_string(), entry.local_address.to_string());
-        metadata.insert("remote_address".to_string(), entry.remote_address.to_string());
-        metadata.insert("state".to_string(), format!("{:?}", entry.state));
+        metadata.insert("local_address".to_string(), local_address.to_string());
+        metadata.insert("remote_address".to_string(), remote_address.to_string());
+        metadata.insert("state".to_string(), state);
+
+        metadata.insert("inode".to_string(), inode.to_string());
+
+        if self.resolve_dns {
+            if let Some(host) = self.reverse_dns(Self::canonical_ip(remote_address.ip())).await {
+                metadata.insert("remote_host".to_string(), host);
+            }
+        }
+        if let Some(geoip) = &self.geoip {
+            geoip.enrich(remote_address.ip(), &mut metadata);
+        }
+
+        let mut description = if self.reporting_existing {
+            metadata.insert("pre_existing".to_string(), "true".to_string());
+            format!("{} connection to {} established before monitor start", protocol, remote_address)
+        } else {
+            format!("New {} connection to {}", protocol, remote_address)
+        };
 
-        metadata.insert("inode".to_string(), entry.inode.to_string());
+        Self::add_owner(owner, &mut description, &mut metadata);
 
         let event = SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
             event_type: EventType::NetworkConnection,
-            path: PathBuf::from("/proc/net/tcp"),
+            path: PathBuf::from(format!("/proc/net/{}", protocol.to_lowercase())),
             details: EventDetails {
                 severity,
-                description: format!("New {} connection to {}", protocol, entry.remote_address),
+                description,
                 metadata,
             },
         };
@@ -121,6 +433,67 @@ impl NetworkMonitor {
         }
     }
 
+    /// A listening socket appearing or going away. Listeners reachable from other hosts
+    /// are what a backdoor needs, so only loopback ones stay Medium.
+    fn emit_port_event(&self, event_type: EventType, protocol: &str, local_address: SocketAddr, inode: u64, owner: Option<i32>) {
+        let mut metadata = HashMap::new();
+        metadata.insert("protocol".to_string(), protocol.to_string());
+        metadata.insert("local_address".to_string(), local_address.to_string());
+        metadata.insert("port".to_string(), local_address.port().to_string());
+        metadata.insert("inode".to_string(), inode.to_string());
+
+        let (severity, mut description) = match event_type {
+            EventType::PortClosed => (Severity::Low, format!("{} port {} closed", protocol, local_address)),
+            _ => {
+                let severity = if Self::canonical_ip(local_address.ip()).is_loopback() {
+                    Severity::Medium
+                } else {
+                    Severity::High
+                };
+                let description = if self.reporting_existing {
+                    metadata.insert("pre_existing".to_string(), "true".to_string());
+                    format!("{} port {} listening before monitor start", protocol, local_address)
+                } else {
+                    format!("New {} port listening on {}", protocol, local_address)
+                };
+                (severity, description)
+            }
+        };
+        Self::add_owner(owner, &mut description, &mut metadata);
+
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type,
+            path: PathBuf::from(format!("/proc/net/{}", protocol.to_lowercase())),
+            details: EventDetails {
+                severity,
+                description,
+                metadata,
+            },
+        };
+
+        if let Err(e) = self.event_sender.send(event) {
+            error!("Failed to send port event: {}", e);
+        }
+    }
+
+    /// Add the pid, comm and exe of a socket's owner to an event. The owner may have
+    /// exited since the scan; its fields are then left out.
+    fn add_owner(owner: Option<i32>, description: &mut String, metadata: &mut HashMap<String, String>) {
+        let Some(process) = owner.and_then(|pid| procfs::process::Process::new(pid).ok()) else {
+            return;
+        };
+        metadata.insert("pid".to_string(), process.pid.to_string());
+        if let Ok(stat) = process.stat() {
+            *description = format!("{} by {} (pid {})", description, stat.comm, process.pid);
+            metadata.insert("comm".to_string(), stat.comm);
+        }
+        if let Ok(exe) = process.exe() {
+            metadata.insert("exe".to_string(), exe.display().to_string());
+        }
+    }
+
     fn classify_connection_severity(&self, remote_addr: &str) -> Severity {
         if let Ok(socket_addr) = remote_addr.parse::<SocketAddr>() {
             let ip = socket_addr.ip();
@@ -152,4 +525,70 @@ impl NetworkMonitor {
             Severity::Low
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor_with(allow: &[&str], deny: &[&str]) -> Result<NetworkMonitor> {
+        let config = NetworkMonitorConfig {
+            allow_cidrs: allow.iter().map(|cidr| cidr.to_string()).collect(),
+            deny_cidrs: deny.iter().map(|cidr| cidr.to_string()).collect(),
+            ..Default::default()
+        };
+        let (event_sender, _) = broadcast::channel(16);
+        let (_, config_updates) = watch::channel(Arc::new(Config::default()));
+        NetworkMonitor::new(event_sender, &config, config_updates, None)
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn allow_cidrs_contain_ipv4_and_ipv6_ranges() {
+        let monitor = monitor_with(&["10.0.0.0/8", "192.168.1.5", "2001:db8::/32"], &[]).unwrap();
+
+        assert!(monitor.is_ignored(ip("10.0.0.1")));
+        assert!(monitor.is_ignored(ip("10.255.255.255")));
+        assert!(!monitor.is_ignored(ip("11.0.0.1")));
+        // A bare address is a single-host range
+        assert!(monitor.is_ignored(ip("192.168.1.5")));
+        assert!(!monitor.is_ignored(ip("192.168.1.6")));
+
+        assert!(monitor.is_ignored(ip("2001:db8::1")));
+        assert!(monitor.is_ignored(ip("2001:db8:ffff::1")));
+        assert!(!monitor.is_ignored(ip("2001:db9::1")));
+
+        // IPv4 peers of dual-stack sockets match IPv4 ranges
+        assert!(monitor.is_ignored(ip("::ffff:10.1.2.3")));
+        assert!(!monitor.is_ignored(ip("::ffff:11.1.2.3")));
+    }
+
+    #[test]
+    fn deny_cidrs_win_over_allow_cidrs_and_loopback() {
+        let monitor = monitor_with(&["10.0.0.0/8", "2001:db8::/32"], &["10.6.6.0/24", "127.0.0.2", "2001:db8:bad::/48"]).unwrap();
+
+        assert!(monitor.is_ignored(ip("10.6.5.1")));
+        assert!(!monitor.is_ignored(ip("10.6.6.1")));
+        assert!(!monitor.is_ignored(ip("::ffff:10.6.6.1")));
+        assert!(monitor.is_ignored(ip("2001:db8:bac::1")));
+        assert!(!monitor.is_ignored(ip("2001:db8:bad::1")));
+
+        // Loopback is ignored unless it is denied
+        assert!(monitor.is_ignored(ip("127.0.0.1")));
+        assert!(monitor.is_ignored(ip("::1")));
+        assert!(!monitor.is_ignored(ip("127.0.0.2")));
+
+        assert!(NetworkMonitor::in_networks(&monitor.deny_networks, ip("10.6.6.200")));
+        assert!(!NetworkMonitor::in_networks(&monitor.deny_networks, ip("10.6.7.1")));
+    }
+
+    #[test]
+    fn invalid_cidrs_are_rejected() {
+        assert!(monitor_with(&["10.0.0.0/33"], &[]).is_err());
+        assert!(monitor_with(&[], &["not-a-network"]).is_err());
+        assert!(monitor_with(&[" 10.0.0.0/8 "], &[]).is_ok());
+    }
+}
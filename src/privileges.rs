@@ -0,0 +1,87 @@
+use anyhow::{bail, Context, Result};
+use log::info;
+use std::ffi::CString;
+use std::io;
+
+/// Look up a user's (uid, primary gid) in the passwd database
+fn lookup_user(name: &CString) -> Result<(libc::uid_t, libc::gid_t)> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = unsafe {
+        libc::getpwnam_r(name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result)
+    };
+    if rc != 0 {
+        return Err(io::Error::from_raw_os_error(rc))
+            .with_context(|| format!("Failed to look up user {}", name.to_string_lossy()));
+    }
+    if result.is_null() {
+        bail!("Unknown user: {}", name.to_string_lossy());
+    }
+    Ok((passwd.pw_uid, passwd.pw_gid))
+}
+
+/// Look up a group's gid in the group database
+pub fn group_id(group: &str) -> Result<libc::gid_t> {
+    let name = CString::new(group).context("Invalid group name")?;
+    let mut entry: libc::group = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    let rc = unsafe {
+        libc::getgrnam_r(name.as_ptr(), &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result)
+    };
+    if rc != 0 {
+        return Err(io::Error::from_raw_os_error(rc)).with_context(|| format!("Failed to look up group {}", group));
+    }
+    if result.is_null() {
+        bail!("Unknown group: {}", group);
+    }
+    Ok(entry.gr_gid)
+}
+
+/// Switch the whole process to `user` and its groups for good.
+///
+/// Called once root-only resources (inotify/fanotify, the socket, raw sockets, udev) are
+/// open. Fails unless afterwards every real and effective id is the user's and root
+/// can't be regained.
+pub fn drop_to(user: &str) -> Result<()> {
+    let name = CString::new(user).context("Invalid run_as user name")?;
+    let (uid, gid) = lookup_user(&name)?;
+
+    if unsafe { libc::geteuid() } != 0 {
+        if unsafe { libc::getuid() } == uid {
+            info!("Already running as {}", user);
+            return Ok(());
+        }
+        bail!("run_as = \"{}\" requires starting the daemon as root", user);
+    }
+
+    // Groups first: after setuid there's no permission left to change them.
+    // glibc applies these to every thread in the process.
+    if unsafe { libc::initgroups(name.as_ptr(), gid) } != 0 {
+        return Err(io::Error::last_os_error()).context("Failed to set supplementary groups");
+    }
+    if unsafe { libc::setgid(gid) } != 0 {
+        return Err(io::Error::last_os_error()).with_context(|| format!("Failed to set group id {}", gid));
+    }
+    if unsafe { libc::setuid(uid) } != 0 {
+        return Err(io::Error::last_os_error()).with_context(|| format!("Failed to set user id {}", uid));
+    }
+
+    let (ruid, euid) = unsafe { (libc::getuid(), libc::geteuid()) };
+    let (rgid, egid) = unsafe { (libc::getgid(), libc::getegid()) };
+    if ruid != uid || euid != uid || rgid != gid || egid != gid {
+        bail!(
+            "Privilege drop to {} did not take effect (uid {}/{}, gid {}/{})",
+            user, ruid, euid, rgid, egid
+        );
+    }
+    if uid != 0 && unsafe { libc::setuid(0) } == 0 {
+        bail!("Privilege drop to {} is reversible, root could be regained", user);
+    }
+
+    info!("Dropped privileges to {} (uid {}, gid {})", user, uid, gid);
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use log::warn;
+use std::path::{Path, PathBuf};
+
+use crate::SecurityEvent;
+
+/// Options for replaying a recorded NDJSON event capture through the daemon
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    pub path: PathBuf,
+    pub speed: f64,           // Multiplier applied to the original inter-event timing
+    pub looping: bool,        // Cycle through the recording until stopped
+    pub count: Option<usize>, // Stop after this many events in total
+}
+
+impl ReplayOptions {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            speed: 1.0,
+            looping: false,
+            count: None,
+        }
+    }
+}
+
+/// Load a recorded capture, one `SecurityEvent` JSON object per line
+pub fn load_events(path: &Path) -> Result<Vec<SecurityEvent>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read replay file: {}", path.display()))?;
+
+    let mut events = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<SecurityEvent>(trimmed) {
+            Ok(event) => events.push(event),
+            Err(e) => {
+                warn!("Skipping invalid event on line {} of {}: {}", line_number + 1, path.display(), e);
+            }
+        }
+    }
+
+    Ok(events)
+}
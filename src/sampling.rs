@@ -0,0 +1,125 @@
+//! `[sampling]` keep-ratios, shared by the daemon's event log and event store writers.
+
+use std::collections::HashMap;
+
+use crate::config::SamplingConfig;
+use crate::SecurityEvent;
+
+/// Decides which events are persisted, using a per event type or per severity keep-ratio.
+///
+/// Sampling is deterministic: a ratio of 0.1 keeps exactly every tenth event of that kind,
+/// so extrapolated counts (`kept / sample_ratio`) stay accurate over short windows.
+pub struct Sampler {
+    config: SamplingConfig,
+    seen: HashMap<String, u64>,
+}
+
+impl Sampler {
+    pub fn new(config: SamplingConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the event should be persisted, tagging it with the ratio it was sampled at.
+    /// An event already sampled upstream (by `[throttle]`) is tagged with the combined ratio.
+    pub fn sample(&mut self, event: &mut SecurityEvent) -> bool {
+        let event_type = event.event_type.name();
+        let severity = format!("{:?}", event.details.severity);
+
+        // An event type ratio is more specific than a severity ratio
+        let (key, ratio) = match self.config.event_types.get(event_type) {
+            Some(ratio) => (event_type.to_string(), *ratio),
+            None => match self.config.severity.get(&severity) {
+                Some(ratio) => (severity, *ratio),
+                None => return true,
+            },
+        };
+
+        if ratio >= 1.0 {
+            return true;
+        }
+        if ratio <= 0.0 {
+            return false;
+        }
+
+        let seen = self.seen.entry(key).or_insert(0);
+        let before = (*seen as f64 * ratio).floor();
+        *seen += 1;
+        let after = (*seen as f64 * ratio).floor();
+
+        if after > before {
+            let upstream = event
+                .details
+                .metadata
+                .get("sample_ratio")
+                .and_then(|ratio| ratio.parse::<f64>().ok())
+                .unwrap_or(1.0);
+            event.details.metadata.insert("sampled".to_string(), "true".to_string());
+            event.details.metadata.insert("sample_ratio".to_string(), (upstream * ratio).to_string());
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventDetails, EventType, Severity};
+    use chrono::Utc;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    fn event(event_type: EventType, severity: Severity) -> SecurityEvent {
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type,
+            path: PathBuf::from("/srv/data/file"),
+            details: EventDetails { severity, description: "test".to_string(), metadata: HashMap::new() },
+        }
+    }
+
+    fn sampler(severity: &[(&str, f64)], event_types: &[(&str, f64)]) -> Sampler {
+        let ratios = |entries: &[(&str, f64)]| entries.iter().map(|(name, ratio)| (name.to_string(), *ratio)).collect();
+        Sampler::new(SamplingConfig { severity: ratios(severity), event_types: ratios(event_types) })
+    }
+
+    #[test]
+    fn keeps_exactly_the_ratio_and_tags_kept_events() {
+        let mut sampler = sampler(&[("Low", 0.5)], &[("FileAccess", 0.1)]);
+
+        let kept: Vec<SecurityEvent> = (0..20)
+            .map(|_| event(EventType::FileAccess, Severity::Low))
+            .filter_map(|mut event| sampler.sample(&mut event).then_some(event))
+            .collect();
+        assert_eq!(kept.len(), 2);
+        assert!(kept.iter().all(|event| event.details.metadata["sample_ratio"] == "0.1"));
+
+        // The severity ratio applies to types without their own
+        let kept = (0..20).filter(|_| sampler.sample(&mut event(EventType::FileModify, Severity::Low))).count();
+        assert_eq!(kept, 10);
+
+        // Unconfigured events are all kept, untagged
+        let mut high = event(EventType::FileModify, Severity::High);
+        assert!(sampler.sample(&mut high));
+        assert!(!high.details.metadata.contains_key("sampled"));
+    }
+
+    #[test]
+    fn ratios_compound_with_upstream_sampling() {
+        let mut sampler = sampler(&[], &[("FileAccess", 0.5)]);
+        let mut kept = None;
+        for _ in 0..2 {
+            let mut throttled = event(EventType::FileAccess, Severity::Low);
+            throttled.details.metadata.insert("sample_ratio".to_string(), "0.2".to_string());
+            if sampler.sample(&mut throttled) {
+                kept = Some(throttled);
+            }
+        }
+        assert_eq!(kept.unwrap().details.metadata["sample_ratio"], "0.1");
+    }
+}
@@ -0,0 +1,360 @@
+//! SQLite event history, written by the daemon and queried by `secmon-client search`
+//! and `stats`.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use log::{error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
+
+use crate::config::SamplingConfig;
+use crate::sampling::Sampler;
+use crate::{EventDetails, EventType, SecurityEvent, Severity};
+
+/// Expired rows are deleted at startup and then at most this often
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+/// Events already queued when the writer wakes up are inserted in one transaction, up to this many
+const MAX_BATCH: usize = 512;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,  -- Unix time in milliseconds
+        event_type TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        path TEXT NOT NULL,
+        description TEXT NOT NULL,
+        metadata TEXT NOT NULL,      -- JSON object
+        uuid TEXT                    -- SecurityEvent::id
+    );
+    CREATE INDEX IF NOT EXISTS events_timestamp ON events (timestamp);
+    CREATE INDEX IF NOT EXISTS events_event_type ON events (event_type, timestamp);
+";
+
+/// A random version 4 UUID in SQL, for rows stored before events had ids
+const RANDOM_UUID_SQL: &str = "lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4'
+    || substr(hex(randomblob(2)), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1)
+    || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6)))";
+
+/// How many events a stored row stands for: 1 / its `sample_ratio` when it was sampled
+const ROW_WEIGHT_SQL: &str = "1.0 / COALESCE(NULLIF(CAST(json_extract(metadata, '$.sample_ratio') AS REAL), 0.0), 1.0)";
+
+/// Whether the events table has the uuid column (databases from before event ids don't)
+fn has_uuid_column(conn: &Connection) -> Result<bool> {
+    let mut statement = conn.prepare("SELECT 1 FROM pragma_table_info('events') WHERE name = 'uuid'")?;
+    Ok(statement.exists([])?)
+}
+
+/// Filters for [`EventStore::query`]; unset fields don't filter
+#[derive(Debug, Default)]
+pub struct EventQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>, // Exclusive
+    pub event_type: Option<EventType>,
+    pub min_severity: Option<Severity>,
+}
+
+pub struct EventStore {
+    conn: Connection,
+    has_uuid: bool,
+}
+
+impl EventStore {
+    /// Open (creating if needed) the database at `path` for reading and writing
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open event store: {}", path))?;
+        // WAL lets the client query while the daemon writes
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .context("Failed to enable WAL on event store")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .context("Failed to configure event store")?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to create event store schema: {}", path))?;
+        if !has_uuid_column(&conn)? {
+            conn.execute_batch(&format!(
+                "BEGIN; ALTER TABLE events ADD COLUMN uuid TEXT; UPDATE events SET uuid = {}; COMMIT;",
+                RANDOM_UUID_SQL
+            ))
+            .with_context(|| format!("Failed to add event ids to event store: {}", path))?;
+            info!("Added event ids to event store {}", path);
+        }
+        Ok(Self { conn, has_uuid: true })
+    }
+
+    /// Open an existing database without creating or migrating it
+    pub fn open_read_only(path: &str) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open event store: {}", path))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        let has_uuid = has_uuid_column(&conn)?;
+        Ok(Self { conn, has_uuid })
+    }
+
+    pub fn insert_batch(&mut self, events: &[SecurityEvent]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO events (timestamp, event_type, severity, path, description, metadata, uuid)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for event in events {
+                insert.execute(params![
+                    event.timestamp.timestamp_millis(),
+                    event.event_type.name(),
+                    format!("{:?}", event.details.severity),
+                    event.path.to_string_lossy(),
+                    event.details.description,
+                    serde_json::to_string(&event.details.metadata)?,
+                    event.id.to_string(),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete events older than `cutoff`, returning how many were removed
+    pub fn prune(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM events WHERE timestamp < ?1", params![cutoff.timestamp_millis()])?)
+    }
+
+    /// Matching events, oldest first
+    pub fn query(&self, query: &EventQuery) -> Result<Vec<SecurityEvent>> {
+        let mut events = Vec::new();
+        self.for_each_event(query, |event| {
+            events.push(event);
+            Ok(())
+        })?;
+        Ok(events)
+    }
+
+    /// Pass matching events to `handle` one at a time, oldest first, without holding
+    /// them all in memory. Stops at the first error `handle` returns.
+    pub fn for_each_event(&self, query: &EventQuery, mut handle: impl FnMut(SecurityEvent) -> Result<()>) -> Result<()> {
+        // A database not yet opened by a daemon with event ids has no uuid column
+        let uuid_column = if self.has_uuid { "uuid" } else { "NULL" };
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT timestamp, event_type, severity, path, description, metadata, {} FROM events
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp < ?2)
+               AND (?3 IS NULL OR event_type = ?3)
+               AND (?4 IS NULL OR CASE severity WHEN 'Low' THEN 1 WHEN 'Medium' THEN 2
+                                   WHEN 'High' THEN 3 WHEN 'Critical' THEN 4 ELSE 0 END >= ?4)
+             ORDER BY timestamp, id",
+            uuid_column
+        ))?;
+        let rows = statement.query_map(
+            params![
+                query.since.map(|since| since.timestamp_millis()),
+                query.until.map(|until| until.timestamp_millis()),
+                query.event_type.as_ref().map(EventType::name),
+                query.min_severity.as_ref().map(Severity::level),
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )?;
+
+        for row in rows {
+            let (timestamp, event_type, severity, path, description, metadata, uuid) = row?;
+            // Rows written by a newer daemon may use names this build doesn't know
+            let (Some(event_type), Some(severity)) = (EventType::from_name(&event_type), Severity::from_name(&severity)) else {
+                continue;
+            };
+            handle(SecurityEvent {
+                id: uuid.and_then(|uuid| Uuid::parse_str(&uuid).ok()).unwrap_or_else(Uuid::new_v4),
+                timestamp: Utc.timestamp_millis_opt(timestamp).single().unwrap_or_default(),
+                event_type,
+                path: PathBuf::from(path),
+                details: EventDetails {
+                    severity,
+                    description,
+                    metadata: serde_json::from_str(&metadata).unwrap_or_default(),
+                },
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Event counts per (event type name, severity name) in `since <= t < until`, most
+    /// frequent type first. Sampled rows are scaled back up by their `sample_ratio`.
+    pub fn count_by_type_and_severity(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(String, String, u64)>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT event_type, severity, ROUND(SUM({weight})) AS estimated FROM events
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp < ?2)
+             GROUP BY event_type, severity
+             ORDER BY SUM(ROUND(SUM({weight}))) OVER (PARTITION BY event_type) DESC, event_type, severity",
+            weight = ROW_WEIGHT_SQL
+        ))?;
+        let counts = statement
+            .query_map(params![since.map(|since| since.timestamp_millis()), until.map(|until| until.timestamp_millis())], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)? as u64))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(counts)
+    }
+
+    /// Event counts per hour (UTC, start of the hour) in `since <= t < until`, oldest
+    /// first, scaled like [`Self::count_by_type_and_severity`]. Hours without events are left out.
+    pub fn count_by_hour(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<(DateTime<Utc>, u64)>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT timestamp / 3600000 AS hour, ROUND(SUM({})) FROM events WHERE timestamp >= ?1 AND timestamp < ?2
+             GROUP BY hour ORDER BY hour",
+            ROW_WEIGHT_SQL
+        ))?;
+        let counts = statement
+            .query_map(params![since.timestamp_millis(), until.timestamp_millis()], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(counts
+            .into_iter()
+            .filter_map(|(hour, count)| Utc.timestamp_opt(hour * 3600, 0).single().map(|hour| (hour, count)))
+            .collect())
+    }
+
+    /// Timestamp of the oldest stored event
+    pub fn oldest(&self) -> Result<Option<DateTime<Utc>>> {
+        let oldest: Option<i64> = self.conn
+            .query_row("SELECT MIN(timestamp) FROM events", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(oldest.and_then(|millis| Utc.timestamp_millis_opt(millis).single()))
+    }
+}
+
+/// Write broadcast events to `store`, opened from `path`, until the channel closes or
+/// `stop` is set, when the events still queued are written first. Blocking: run it on
+/// its own thread, with `runtime` a handle to the daemon's runtime to wait on the channels with.
+pub fn run(
+    mut store: EventStore,
+    path: String,
+    retention_days: u64,
+    sampling: SamplingConfig,
+    mut receiver: broadcast::Receiver<SecurityEvent>,
+    mut stop: watch::Receiver<bool>,
+    runtime: tokio::runtime::Handle,
+) -> Result<()> {
+    info!("Storing events in {}", path);
+
+    let retention = (retention_days > 0).then(|| chrono::Duration::days(retention_days as i64));
+    let mut last_prune: Option<Instant> = None;
+    // Same keep-ratios as the event log, so both keep the same events
+    let mut sampler = Sampler::new(sampling);
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+
+    loop {
+        if let Some(retention) = retention {
+            if last_prune.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                match store.prune(Utc::now() - retention) {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Removed {} events older than {} days from {}", deleted, retention_days, path),
+                    Err(e) => error!("Failed to prune event store {}: {:#}", path, e),
+                }
+                last_prune = Some(Instant::now());
+            }
+        }
+
+        // Queued events win over `stop`, so everything broadcast before it is stored
+        let received = runtime.block_on(async {
+            tokio::select! {
+                biased;
+                received = receiver.recv() => Some(received),
+                _ = stop.wait_for(|stop| *stop) => None,
+            }
+        });
+        match received {
+            Some(Ok(mut event)) => {
+                if sampler.sample(&mut event) {
+                    batch.push(event);
+                }
+            }
+            Some(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                warn!("Event store fell behind, {} events were not stored", skipped);
+                continue;
+            }
+            Some(Err(broadcast::error::RecvError::Closed)) | None => break,
+        }
+        while batch.len() < MAX_BATCH {
+            match receiver.try_recv() {
+                Ok(mut event) => {
+                    if sampler.sample(&mut event) {
+                        batch.push(event);
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    warn!("Event store fell behind, {} events were not stored", skipped);
+                }
+                Err(_) => break,
+            }
+        }
+
+        if let Err(e) = store.insert_batch(&batch) {
+            error!("Failed to store {} events in {}: {:#}", batch.len(), path, e);
+        }
+        batch.clear();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn event(timestamp: &str, sample_ratio: Option<&str>) -> SecurityEvent {
+        let mut metadata = HashMap::new();
+        if let Some(ratio) = sample_ratio {
+            metadata.insert("sampled".to_string(), "true".to_string());
+            metadata.insert("sample_ratio".to_string(), ratio.to_string());
+        }
+        SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc),
+            event_type: EventType::FileAccess,
+            path: PathBuf::from("/srv/data/file"),
+            details: EventDetails { severity: Severity::Low, description: "test".to_string(), metadata },
+        }
+    }
+
+    #[test]
+    fn counts_scale_sampled_rows_back_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = EventStore::open(dir.path().join("events.db").to_str().unwrap()).unwrap();
+        store
+            .insert_batch(&[
+                event("2024-01-15T08:10:00Z", Some("0.1")),
+                event("2024-01-15T08:20:00Z", Some("0.1")),
+                event("2024-01-15T09:10:00Z", None),
+                event("2024-01-15T09:20:00Z", Some("0.25")),
+            ])
+            .unwrap();
+
+        let counts = store.count_by_type_and_severity(None, None).unwrap();
+        assert_eq!(counts, [("FileAccess".to_string(), "Low".to_string(), 25)]);
+
+        let since = DateTime::parse_from_rfc3339("2024-01-15T08:00:00Z").unwrap().with_timezone(&Utc);
+        let hourly: Vec<u64> = store.count_by_hour(since, since + chrono::Duration::hours(2)).unwrap().into_iter().map(|(_, count)| count).collect();
+        assert_eq!(hourly, [20, 5]);
+
+        // Search still returns what was actually stored
+        assert_eq!(store.query(&EventQuery::default()).unwrap().len(), 4);
+    }
+}
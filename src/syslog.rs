@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use chrono::SecondsFormat;
+use log::{error, info, warn};
+use tokio::net::{UdpSocket, UnixDatagram};
+use tokio::sync::broadcast;
+
+use crate::config::SyslogConfig;
+use crate::{SecurityEvent, Severity};
+
+pub const LOCAL_SOCKET: &str = "/dev/log";
+pub const APP_NAME: &str = "secmon";
+/// Private enterprise number reserved for documentation (RFC 5612), used as the SD-ID suffix
+const SD_ID: &str = "secmon@32473";
+
+pub enum Target {
+    Local(UnixDatagram),
+    Remote(UdpSocket),
+}
+
+impl Target {
+    pub async fn connect(remote: Option<&str>) -> Result<Self> {
+        match remote {
+            Some(address) => {
+                let remote_address = tokio::net::lookup_host(address)
+                    .await
+                    .with_context(|| format!("Failed to resolve syslog server {}", address))?
+                    .next()
+                    .with_context(|| format!("No address found for syslog server {}", address))?;
+                let bind_address = if remote_address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                let socket = UdpSocket::bind(bind_address).await?;
+                socket.connect(remote_address).await
+                    .with_context(|| format!("Failed to connect to syslog server {}", address))?;
+                Ok(Target::Remote(socket))
+            }
+            None => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(LOCAL_SOCKET)
+                    .with_context(|| format!("Failed to connect to {}", LOCAL_SOCKET))?;
+                Ok(Target::Local(socket))
+            }
+        }
+    }
+
+    pub async fn send(&self, message: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Target::Local(socket) => socket.send(message).await,
+            Target::Remote(socket) => socket.send(message).await,
+        }
+    }
+}
+
+/// syslog severity for a secmon severity
+pub fn syslog_severity(severity: &Severity) -> u8 {
+    match severity {
+        Severity::Critical => 2, // crit
+        Severity::High => 3,     // err
+        Severity::Medium => 4,   // warning
+        Severity::Low => 5,      // notice
+    }
+}
+
+/// Escape `"`, `\` and `]` in an SD-PARAM value (RFC 5424 section 6.3.3)
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Format an event as an RFC 5424 message:
+/// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD] MSG`
+fn format_message(event: &SecurityEvent, facility: u8, hostname: &str) -> String {
+    let severity = format!("{:?}", event.details.severity);
+    format!(
+        "<{}>1 {} {} {} {} {} [{} id=\"{}\" severity=\"{}\" event_type=\"{}\" path=\"{}\"] {}",
+        facility as u32 * 8 + syslog_severity(&event.details.severity) as u32,
+        event.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+        hostname,
+        APP_NAME,
+        std::process::id(),
+        event.event_type.name(),
+        SD_ID,
+        event.id,
+        severity,
+        escape_param(event.event_type.name()),
+        escape_param(&event.path.to_string_lossy()),
+        event.details.description,
+    )
+}
+
+/// HOSTNAME may not contain spaces and must not be empty ("-" is the nil value)
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().replace(' ', "_"))
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Forward broadcast events to syslog over `target`, connected with `Target::connect`
+pub async fn run(config: SyslogConfig, target: Target, mut receiver: broadcast::Receiver<SecurityEvent>) -> Result<()> {
+    let facility = config.facility_code()?;
+    let hostname = hostname();
+
+    info!(
+        "Forwarding events to syslog ({}, facility {})",
+        config.remote.as_deref().unwrap_or(LOCAL_SOCKET),
+        config.facility
+    );
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                let message = format_message(&event, facility, &hostname);
+                if let Err(e) = target.send(message.as_bytes()).await {
+                    error!("Failed to send event to syslog: {}", e);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Syslog forwarding fell behind, {} events were not sent", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use log::{debug, warn};
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+/// First file descriptor passed by systemd socket activation (SD_LISTEN_FDS_START)
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Take the listening socket handed over by systemd, if this process was socket-activated.
+///
+/// Mirrors `sd_listen_fds(1)`: the `LISTEN_*` variables are only honoured when
+/// `LISTEN_PID` names this process, and they are cleared afterwards so trigger
+/// commands spawned later don't mistake themselves for activated services.
+pub fn take_listen_fd() -> Option<RawFd> {
+    let listen_pid = std::env::var("LISTEN_PID").ok()?;
+    let listen_fds = std::env::var("LISTEN_FDS").ok()?;
+
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    if listen_pid.trim().parse::<u32>().ok()? != std::process::id() {
+        debug!("Ignoring LISTEN_FDS meant for PID {}", listen_pid);
+        return None;
+    }
+
+    match listen_fds.trim().parse::<i32>() {
+        Ok(0) | Err(_) => None,
+        Ok(count) => {
+            if count > 1 {
+                warn!("systemd passed {} sockets, only the first one is used", count);
+            }
+            Some(LISTEN_FDS_START)
+        }
+    }
+}
+
+/// Send a state string such as "READY=1" to the service manager, like `sd_notify(3)`.
+///
+/// Does nothing unless systemd set `NOTIFY_SOCKET` (Type=notify services). Failures are
+/// only logged: being unable to notify shouldn't stop the daemon.
+pub fn notify(state: &str) {
+    let Ok(socket_path) = std::env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    let result = std::os::unix::net::UnixDatagram::unbound().and_then(|socket| {
+        // A leading '@' names a socket in the abstract namespace
+        if let Some(name) = socket_path.strip_prefix('@') {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)
+        } else {
+            socket.send_to(state.as_bytes(), &socket_path)
+        }
+    });
+
+    if let Err(e) = result {
+        warn!("Failed to notify systemd ({}): {}", state.trim(), e);
+    }
+}
+
+/// How often to send WATCHDOG=1, if systemd enabled the watchdog for this process
+/// (`WatchdogSec=`). Pings go out at half the timeout, as `sd_watchdog_enabled(3)` advises.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec = std::env::var("WATCHDOG_USEC").ok()?.trim().parse::<u64>().ok()?;
+
+    if let Ok(watchdog_pid) = std::env::var("WATCHDOG_PID") {
+        if watchdog_pid.trim().parse::<u32>().ok()? != std::process::id() {
+            debug!("Ignoring WATCHDOG_USEC meant for PID {}", watchdog_pid);
+            return None;
+        }
+    }
+
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::config::{SamplingConfig, ThrottleConfig};
+use crate::sampling::Sampler;
+use crate::{EventType, SecurityEvent, Severity};
+
+/// Allows `rate` events per second on average, in bursts of up to a second's worth
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate.max(1.0),
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate.max(1.0));
+        self.refilled_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Applies `[throttle]` to events before they are dispatched: per-type sampling, then
+/// per-type and global rate limits
+pub struct Throttle {
+    sampler: Sampler,
+    buckets: HashMap<String, TokenBucket>,
+    global: Option<TokenBucket>,
+}
+
+impl Throttle {
+    pub fn new(config: &ThrottleConfig) -> Self {
+        // Keyed by canonical type name, however the config spells it
+        let canonical = |name: &String| EventType::from_name(name).map(|event_type| event_type.name().to_string());
+        Self {
+            sampler: Sampler::new(SamplingConfig {
+                severity: HashMap::new(),
+                event_types: config
+                    .sample
+                    .iter()
+                    .filter_map(|(name, ratio)| Some((canonical(name)?, *ratio)))
+                    .collect(),
+            }),
+            buckets: config
+                .max_per_second
+                .iter()
+                .filter(|(_, rate)| **rate > 0.0)
+                .filter_map(|(name, rate)| Some((canonical(name)?, TokenBucket::new(*rate))))
+                .collect(),
+            global: config.global_max_per_second.filter(|rate| *rate > 0.0).map(TokenBucket::new),
+        }
+    }
+
+    /// Whether `event` should be dispatched. Critical events always are; sampled ones
+    /// that are kept get tagged like the event log's.
+    pub fn admit(&mut self, event: &mut SecurityEvent) -> bool {
+        if matches!(event.details.severity, Severity::Critical) {
+            return true;
+        }
+        if !self.sampler.sample(event) {
+            return false;
+        }
+        if let Some(bucket) = self.buckets.get_mut(event.event_type.name()) {
+            if !bucket.take() {
+                return false;
+            }
+        }
+        self.global.as_mut().is_none_or(TokenBucket::take)
+    }
+}
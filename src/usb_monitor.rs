@@ -1,41 +1,172 @@
 use anyhow::{Context, Result};
-use libudev::{Context as UdevContext, Device, Enumerator, Event, Monitor};
+use libudev::{Context as UdevContext, Device, Event, Monitor};
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use tokio::sync::broadcast;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot, watch};
 use chrono::Utc;
+use uuid::Uuid;
 
+use crate::config::Config;
+use secmon_core::SubsystemStatus;
 use crate::{EventType, SecurityEvent, EventDetails, Severity};
 
+/// Writing a device's bus id (e.g. "1-2") here detaches it from the kernel's USB core
+/// driver, which removes all of its interfaces
+const USB_UNBIND_PATH: &str = "/sys/bus/usb/drivers/usb/unbind";
+
+/// One line of /proc/self/mounts
+#[derive(Debug, Clone, PartialEq)]
+struct Mount {
+    source: String,
+    mount_point: PathBuf,
+    fs_type: String,
+    options: String,
+}
+
+/// The mount table, re-read when the kernel reports a change. /proc files don't
+/// support inotify; instead poll() flags the open file with POLLPRI once per change.
+struct MountTable {
+    file: File,
+    mounts: HashMap<PathBuf, Mount>,
+}
+
+impl MountTable {
+    fn open() -> std::io::Result<Self> {
+        let file = File::open("/proc/self/mounts")?;
+        let mounts = Self::read()?;
+        Ok(Self { file, mounts })
+    }
+
+    fn read() -> std::io::Result<HashMap<PathBuf, Mount>> {
+        let table = std::fs::read_to_string("/proc/self/mounts")?;
+        Ok(table
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let mount = Mount {
+                    source: unescape_mount_field(fields.next()?),
+                    mount_point: PathBuf::from(unescape_mount_field(fields.next()?)),
+                    fs_type: fields.next()?.to_string(),
+                    options: fields.next()?.to_string(),
+                };
+                Some((mount.mount_point.clone(), mount))
+            })
+            .collect())
+    }
+
+    /// New mounts and removed mounts since the last call, or nothing if the table
+    /// hasn't changed. Doesn't block.
+    fn changes(&mut self) -> (Vec<Mount>, Vec<Mount>) {
+        let mut poll_fd = libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLPRI, revents: 0 };
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, 0) };
+        if ready <= 0 || poll_fd.revents & (libc::POLLPRI | libc::POLLERR) == 0 {
+            return (Vec::new(), Vec::new());
+        }
+
+        let current = match Self::read() {
+            Ok(current) => current,
+            Err(e) => {
+                warn!("Failed to read mount table: {}", e);
+                return (Vec::new(), Vec::new());
+            }
+        };
+        let mounted = current
+            .values()
+            .filter(|mount| self.mounts.get(&mount.mount_point) != Some(mount))
+            .cloned()
+            .collect();
+        let unmounted = self
+            .mounts
+            .values()
+            .filter(|mount| current.get(&mount.mount_point) != Some(mount))
+            .cloned()
+            .collect();
+        self.mounts = current;
+        (mounted, unmounted)
+    }
+}
+
+/// Undo the octal escapes /proc/self/mounts uses for space, tab, newline and backslash
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|digits| digits.iter().all(|d| (b'0'..=b'7').contains(d)));
+        match (bytes[i], octal) {
+            (b'\\', Some(digits)) => {
+                out.push(digits.iter().fold(0u8, |value, d| value.wrapping_mul(8) + (d - b'0')));
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Whether a mount source is a block device on a USB bus, judged by where the device
+/// sits in sysfs (e.g. /sys/devices/pci0000:00/.../usb2/2-1/.../block/sdb/sdb1)
+fn is_usb_block_device(source: &Path) -> bool {
+    let Some(name) = source.file_name() else {
+        return false;
+    };
+    std::fs::canonicalize(Path::new("/sys/class/block").join(name))
+        .map(|sys_path| sys_path.components().any(|c| c.as_os_str().to_string_lossy().starts_with("usb")))
+        .unwrap_or(false)
+}
+
 pub struct UsbMonitor {
     event_sender: broadcast::Sender<SecurityEvent>,
     context: UdevContext,
+    config_updates: watch::Receiver<Arc<Config>>, // The allowlist is read from here so reloads apply
+    block_devices: HashMap<PathBuf, HashMap<String, String>>, // USB disks and partitions by device node
+    usb_mounts: HashMap<PathBuf, HashMap<String, String>>, // Reported StorageMounted metadata by mount point
 }
 
 impl UsbMonitor {
-    pub fn new(event_sender: broadcast::Sender<SecurityEvent>) -> Result<Self> {
+    pub fn new(
+        event_sender: broadcast::Sender<SecurityEvent>,
+        config_updates: watch::Receiver<Arc<Config>>,
+    ) -> Result<Self> {
         let context = UdevContext::new()
             .context("Failed to create udev context")?;
 
         Ok(Self {
             event_sender,
             context,
+            config_updates,
+            block_devices: HashMap::new(),
+            usb_mounts: HashMap::new(),
         })
     }
 
-    pub async fn start_monitoring(&mut self) -> Result<()> {
+    /// `ready` gets the outcome once the udev socket is listening (or monitoring gave up),
+    /// so the daemon can drop privileges after it and report it in `status`
+    pub async fn start_monitoring(&mut self, ready: oneshot::Sender<SubsystemStatus>) -> Result<()> {
+        let give_up = |ready: oneshot::Sender<SubsystemStatus>, error: String| {
+            warn!("USB monitoring disabled - {}", error);
+            let _ = ready.send(SubsystemStatus::Failed { error });
+            Ok(())
+        };
+
         let mut monitor = match Monitor::new(&self.context) {
             Ok(monitor) => monitor,
-            Err(e) => {
-                warn!("USB monitoring disabled - failed to create monitor: {} (may require root permissions)", e);
-                return Ok(());
-            }
+            Err(e) => return give_up(ready, format!("failed to create monitor: {} (may require root permissions)", e)),
         };
 
         if let Err(e) = monitor.match_subsystem("usb") {
-            warn!("USB monitoring disabled - failed to match USB subsystem: {}", e);
-            return Ok(());
+            return give_up(ready, format!("failed to match USB subsystem: {}", e));
+        }
+        // Disks and partitions, to describe USB storage when it gets mounted
+        if let Err(e) = monitor.match_subsystem("block") {
+            warn!("Failed to match block subsystem, mounted USB storage will lack device details: {}", e);
         }
 
         info!("USB monitoring started");
@@ -44,12 +175,20 @@ impl UsbMonitor {
         let mut socket = match monitor.listen() {
             Ok(socket) => socket,
             Err(e) => {
-                warn!("USB monitoring disabled - failed to listen on udev socket: {} (requires root or udev group membership)", e);
-                return Ok(());
+                return give_up(ready, format!("failed to listen on udev socket: {} (requires root or udev group membership)", e));
             }
         };
 
         debug!("USB monitor socket created successfully");
+        let _ = ready.send(SubsystemStatus::Running);
+
+        let mut mount_table = match MountTable::open() {
+            Ok(table) => Some(table),
+            Err(e) => {
+                warn!("USB storage mount detection disabled - failed to read /proc/self/mounts: {}", e);
+                None
+            }
+        };
 
         // Monitor USB events
         loop {
@@ -59,28 +198,37 @@ impl UsbMonitor {
                     self.handle_usb_event(event).await;
                 }
                 None => {
+                    if let Some(table) = mount_table.as_mut() {
+                        self.check_mounts(table).await;
+                    }
                     // No event available right now, continue polling
                     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 }
             }
         }
-
-        info!("USB monitoring stopped");
-        Ok(())
     }
 
-    async fn handle_usb_event(&self, event: Event) {
+    async fn handle_usb_event(&mut self, event: Event) {
         let device = event.device();
         let action = event.event_type();
 
+        if device.subsystem().is_some_and(|subsystem| subsystem == "block") {
+            self.handle_block_event(device, action);
+            return;
+        }
+
         debug!("USB event: {:?} for device: {:?}", action, device.syspath());
 
         match action {
             libudev::EventType::Add => {
-                self.emit_usb_insertion_event(&device).await;
+                let severity = self.emit_usb_insertion_event(device).await;
+                let block = self.config_updates.borrow().usb.block_critical;
+                if block && matches!(severity, Severity::Critical) {
+                    self.block_device(device).await;
+                }
             }
             libudev::EventType::Remove => {
-                self.emit_usb_removal_event(&device).await;
+                self.emit_usb_removal_event(device).await;
             }
             _ => {
                 // Ignore bind/unbind/change events for now
@@ -89,7 +237,103 @@ impl UsbMonitor {
         }
     }
 
-    async fn emit_usb_insertion_event(&self, device: &Device) {
+    /// Remember USB disks and partitions so their mounts can name the device
+    fn handle_block_event(&mut self, device: &Device, action: libudev::EventType) {
+        let Some(devnode) = device.devnode().map(Path::to_path_buf) else {
+            return;
+        };
+
+        match action {
+            libudev::EventType::Add | libudev::EventType::Change => {
+                if device.property_value("ID_BUS").is_none_or(|bus| bus != "usb") {
+                    return;
+                }
+                let mut metadata = HashMap::new();
+                for (property, key) in [
+                    ("ID_VENDOR_ID", "vendor_id"),
+                    ("ID_MODEL_ID", "product_id"),
+                    ("ID_VENDOR", "vendor"),
+                    ("ID_MODEL", "product"),
+                    ("ID_SERIAL_SHORT", "serial"),
+                    ("ID_FS_LABEL", "fs_label"),
+                    ("ID_FS_UUID", "fs_uuid"),
+                ] {
+                    if let Some(value) = device.property_value(property) {
+                        metadata.insert(key.to_string(), value.to_string_lossy().to_string());
+                    }
+                }
+                debug!("USB block device {}", devnode.display());
+                self.block_devices.insert(devnode, metadata);
+            }
+            libudev::EventType::Remove => {
+                self.block_devices.remove(&devnode);
+            }
+            _ => {}
+        }
+    }
+
+    /// Report mounts of USB block devices as StorageMounted, and their unmounts
+    async fn check_mounts(&mut self, table: &mut MountTable) {
+        let (mounted, unmounted) = table.changes();
+
+        for mount in unmounted {
+            let Some(metadata) = self.usb_mounts.remove(&mount.mount_point) else {
+                continue;
+            };
+            let description = format!("USB storage unmounted: {} from {}", mount.source, mount.mount_point.display());
+            self.send_storage_event(EventType::StorageUnmounted, Severity::Low, mount.mount_point, description, metadata);
+        }
+
+        for mount in mounted {
+            let device = std::fs::canonicalize(&mount.source).unwrap_or_else(|_| PathBuf::from(&mount.source));
+            let known = self.block_devices.get(&device).cloned();
+            if known.is_none() && !is_usb_block_device(&device) {
+                continue;
+            }
+
+            let mut metadata = known.unwrap_or_default();
+            metadata.insert("device".to_string(), mount.source.clone());
+            metadata.insert("mountpoint".to_string(), mount.mount_point.display().to_string());
+            metadata.insert("fs_type".to_string(), mount.fs_type.clone());
+            metadata.insert("options".to_string(), mount.options.clone());
+            let read_only = mount.options.split(',').any(|option| option == "ro");
+            metadata.insert("read_only".to_string(), read_only.to_string());
+
+            let description = format!(
+                "USB storage mounted: {} on {} ({}{})",
+                mount.source,
+                mount.mount_point.display(),
+                mount.fs_type,
+                if read_only { ", read-only" } else { "" }
+            );
+            self.usb_mounts.insert(mount.mount_point.clone(), metadata.clone());
+            self.send_storage_event(EventType::StorageMounted, Severity::Medium, mount.mount_point, description, metadata);
+        }
+    }
+
+    fn send_storage_event(
+        &self,
+        event_type: EventType,
+        severity: Severity,
+        mount_point: PathBuf,
+        description: String,
+        metadata: HashMap<String, String>,
+    ) {
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type,
+            path: mount_point,
+            details: EventDetails { severity, description, metadata },
+        };
+
+        if let Err(e) = self.event_sender.send(event) {
+            error!("Failed to send storage mount event: {}", e);
+        }
+    }
+
+    /// Identifying properties of a device; udev includes them on remove events as well
+    fn device_metadata(device: &Device) -> HashMap<String, String> {
         let mut metadata = HashMap::new();
 
         // Extract device information
@@ -121,30 +365,107 @@ impl UsbMonitor {
             metadata.insert("device_path".to_string(), devpath.to_string_lossy().to_string());
         }
 
-        let severity = self.classify_usb_device_severity(&metadata);
+        let classes = Self::interface_classes(device);
+        if !classes.is_empty() {
+            metadata.insert("interface_classes".to_string(), classes.join(","));
+        }
+
+        metadata
+    }
+
+    /// USB class codes ("03" HID, "08" mass storage, ...) of the device's interfaces.
+    /// Interface events carry bInterfaceClass; for whole devices udev lists every
+    /// interface in ID_USB_INTERFACES as ":ccsspp:ccsspp:" (class, subclass, protocol),
+    /// which is there even before the kernel has created the interface devices.
+    fn interface_classes(device: &Device) -> Vec<String> {
+        let mut classes: Vec<String> = Vec::new();
+        let mut add = |class: &str| {
+            let class = class.trim().to_lowercase();
+            if class.len() == 2 && class.chars().all(|c| c.is_ascii_hexdigit()) && !classes.contains(&class) {
+                classes.push(class);
+            }
+        };
+
+        if let Some(class) = device.attribute_value("bInterfaceClass") {
+            add(&class.to_string_lossy());
+        }
 
-        let description = if let (Some(vendor), Some(product)) = (
-            metadata.get("vendor"),
-            metadata.get("product")
-        ) {
-            format!("USB device inserted: {} {} ({}:{})",
-                vendor, product,
-                metadata.get("vendor_id").unwrap_or(&"unknown".to_string()),
-                metadata.get("product_id").unwrap_or(&"unknown".to_string())
-            )
+        if let Some(interfaces) = device.property_value("ID_USB_INTERFACES") {
+            for interface in interfaces.to_string_lossy().split(':').filter(|i| i.len() == 6) {
+                add(&interface[..2]);
+            }
+        }
+
+        // 00 (per interface), ef (miscellaneous) and ff (vendor specific) say nothing
+        // about what the device does
+        if let Some(class) = device.attribute_value("bDeviceClass") {
+            let class = class.to_string_lossy();
+            if !matches!(class.trim(), "00" | "ef" | "ff") {
+                add(&class);
+            }
+        }
+
+        classes
+    }
+
+    /// Whether the device has an interface of this class. None when udev reported no
+    /// classes, so callers can fall back to guessing from the descriptive strings.
+    fn has_interface_class(metadata: &HashMap<String, String>, class: &str) -> Option<bool> {
+        metadata
+            .get("interface_classes")
+            .map(|classes| classes.split(',').any(|c| c == class))
+    }
+
+    /// "Vendor Product (vid:pid)" when udev knows the names, otherwise just the IDs
+    fn device_label(metadata: &HashMap<String, String>) -> String {
+        let unknown = "unknown".to_string();
+        let vendor_id = metadata.get("vendor_id").unwrap_or(&unknown);
+        let product_id = metadata.get("product_id").unwrap_or(&unknown);
+
+        if let (Some(vendor), Some(product)) = (metadata.get("vendor"), metadata.get("product")) {
+            format!("{} {} ({}:{})", vendor, product, vendor_id, product_id)
         } else {
-            format!("USB device inserted: {}:{}",
-                metadata.get("vendor_id").unwrap_or(&"unknown".to_string()),
-                metadata.get("product_id").unwrap_or(&"unknown".to_string())
-            )
+            format!("{}:{}", vendor_id, product_id)
+        }
+    }
+
+    /// Whether the device matches a `[[usb_allow]]` entry
+    fn is_allowlisted(&self, metadata: &HashMap<String, String>) -> bool {
+        let (Some(vendor_id), Some(product_id)) = (metadata.get("vendor_id"), metadata.get("product_id")) else {
+            return false;
         };
+        let serial = metadata.get("serial").map(String::as_str);
+        self.config_updates
+            .borrow()
+            .usb_allow
+            .iter()
+            .any(|entry| entry.matches(vendor_id, product_id, serial))
+    }
+
+    /// Allowlisted devices skip the HID/storage escalation and are marked as trusted
+    fn insertion_severity(&self, metadata: &mut HashMap<String, String>) -> Severity {
+        if self.is_allowlisted(metadata) {
+            metadata.insert("trusted".to_string(), "true".to_string());
+            Severity::Low
+        } else {
+            self.classify_usb_device_severity(metadata)
+        }
+    }
+
+    /// Returns the severity the insertion was reported with
+    async fn emit_usb_insertion_event(&self, device: &Device) -> Severity {
+        let mut metadata = Self::device_metadata(device);
+        let severity = self.insertion_severity(&mut metadata);
+
+        let description = format!("USB device inserted: {}", Self::device_label(&metadata));
 
         let event = SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
             event_type: EventType::UsbDeviceInserted,
             path: device.syspath().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/sys/devices/usb")),
             details: EventDetails {
-                severity,
+                severity: severity.clone(),
                 description,
                 metadata,
             },
@@ -153,22 +474,76 @@ impl UsbMonitor {
         if let Err(e) = self.event_sender.send(event) {
             error!("Failed to send USB insertion event: {}", e);
         }
+        severity
     }
 
-    async fn emit_usb_removal_event(&self, device: &Device) {
-        let mut metadata = HashMap::new();
+    /// Unbind a device from the USB core driver (`[usb] block_critical`) and report the
+    /// outcome as UsbDeviceBlocked. Only whole devices are unbound, never allowlisted ones,
+    /// and only as root.
+    async fn block_device(&self, device: &Device) {
+        let mut metadata = Self::device_metadata(device);
+        if self.is_allowlisted(&metadata) {
+            return;
+        }
+        if metadata.get("device_type").map(String::as_str) != Some("usb_device") {
+            return;
+        }
+        let Some(bus_id) = device.sysname().map(|name| name.to_string_lossy().to_string()) else {
+            return;
+        };
 
-        if let Some(devtype) = device.devtype() {
-            metadata.insert("device_type".to_string(), devtype.to_string_lossy().to_string());
+        let result = if unsafe { libc::geteuid() } != 0 {
+            Err("not running as root".to_string())
+        } else {
+            std::fs::write(Path::new(USB_UNBIND_PATH), &bus_id).map_err(|e| e.to_string())
+        };
+
+        let label = Self::device_label(&metadata);
+        metadata.insert("action".to_string(), "unbind".to_string());
+        metadata.insert("bus_id".to_string(), bus_id.clone());
+        let description = match &result {
+            Ok(()) => {
+                warn!("Blocked USB device {} ({})", label, bus_id);
+                metadata.insert("blocked".to_string(), "true".to_string());
+                format!("USB device blocked: {}", label)
+            }
+            Err(e) => {
+                error!("Failed to block USB device {} ({}): {}", label, bus_id, e);
+                metadata.insert("blocked".to_string(), "false".to_string());
+                metadata.insert("error".to_string(), e.clone());
+                format!("Failed to block USB device {}: {}", label, e)
+            }
+        };
+
+        let event = SecurityEvent {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            event_type: EventType::UsbDeviceBlocked,
+            path: device.syspath().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/sys/devices/usb")),
+            details: EventDetails {
+                severity: Severity::Critical,
+                description,
+                metadata,
+            },
+        };
+
+        if let Err(e) = self.event_sender.send(event) {
+            error!("Failed to send USB block event: {}", e);
         }
+    }
+
+    async fn emit_usb_removal_event(&self, device: &Device) {
+        let metadata = Self::device_metadata(device);
+        let description = format!("USB device removed: {}", Self::device_label(&metadata));
 
         let event = SecurityEvent {
+            id: Uuid::new_v4(),
             timestamp: Utc::now(),
-            event_type: EventType::UsbDeviceInserted, // We could add UsbDeviceRemoved if needed
+            event_type: EventType::UsbDeviceRemoved,
             path: device.syspath().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/sys/devices/usb")),
             details: EventDetails {
                 severity: Severity::Low,
-                description: "USB device removed".to_string(),
+                description,
                 metadata,
             },
         };
@@ -192,10 +567,11 @@ impl UsbMonitor {
                         match (vendor_id.as_str(), product_id.as_str()) {
                             // Rubber Ducky-like devices (some common HID attack devices)
                             ("f000", _) | ("dead", _) => Severity::Critical,
+                            // HID devices (keyboards, mice) can be used for attacks. Checked before
+                            // storage so a composite device (a BadUSB stick) gets the higher rating.
+                            _ if self.is_hid_device(metadata) => Severity::High,
                             // Mass storage devices get medium severity for data exfiltration risk
                             _ if self.is_mass_storage_device(metadata) => Severity::Medium,
-                            // HID devices (keyboards, mice) can be used for attacks
-                            _ if self.is_hid_device(metadata) => Severity::High,
                             _ => Severity::Low,
                         }
                     } else {
@@ -210,7 +586,11 @@ impl UsbMonitor {
     }
 
     fn is_mass_storage_device(&self, metadata: &HashMap<String, String>) -> bool {
-        // Check if it's a mass storage device
+        if let Some(storage) = Self::has_interface_class(metadata, "08") {
+            return storage;
+        }
+
+        // No class information; guess from vendor/product strings
         metadata.values().any(|v| {
             let v_lower = v.to_lowercase();
             v_lower.contains("mass_storage") ||
@@ -220,7 +600,11 @@ impl UsbMonitor {
     }
 
     fn is_hid_device(&self, metadata: &HashMap<String, String>) -> bool {
-        // Check if it's a Human Interface Device
+        if let Some(hid) = Self::has_interface_class(metadata, "03") {
+            return hid;
+        }
+
+        // No class information; guess from vendor/product strings
         metadata.values().any(|v| {
             let v_lower = v.to_lowercase();
             v_lower.contains("hid") ||
@@ -228,4 +612,67 @@ impl UsbMonitor {
             v_lower.contains("mouse")
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor_with(config: &str) -> UsbMonitor {
+        let config: Config =
+            toml::from_str(&format!("socket_path = \"/tmp/secmon-test.sock\"\nlog_level = \"info\"\nwatches = []\n{}", config)).unwrap();
+        let (event_sender, _) = broadcast::channel(16);
+        let (_, config_updates) = watch::channel(Arc::new(config));
+        UsbMonitor::new(event_sender, config_updates).unwrap()
+    }
+
+    fn keyboard(serial: &str) -> HashMap<String, String> {
+        [
+            ("device_type", "usb_device"),
+            ("vendor_id", "046d"),
+            ("product_id", "c31c"),
+            ("serial", serial),
+            ("interface_classes", "03"),
+        ]
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn allowlisted_hid_devices_stay_low() {
+        let monitor = monitor_with("\n[[usb_allow]]\nvendor_id = \"046D\"\nproduct_id = \"c31c\"\nserial = \"KB-1\"\n");
+
+        let mut trusted = keyboard("KB-1");
+        assert!(matches!(monitor.insertion_severity(&mut trusted), Severity::Low));
+        assert_eq!(trusted["trusted"], "true");
+
+        // Another unit of the same model is an ordinary HID device
+        let mut other = keyboard("KB-2");
+        assert!(matches!(monitor.insertion_severity(&mut other), Severity::High));
+        assert!(!other.contains_key("trusted"));
+    }
+
+    #[test]
+    fn hid_devices_are_high_without_an_allowlist() {
+        let monitor = monitor_with("");
+        let mut metadata = keyboard("KB-1");
+        assert!(matches!(monitor.insertion_severity(&mut metadata), Severity::High));
+        assert!(!metadata.contains_key("trusted"));
+    }
+
+    #[test]
+    fn composite_hid_and_storage_devices_are_high() {
+        let monitor = monitor_with("");
+        let mut storage = keyboard("STICK-1");
+        storage.insert("interface_classes".to_string(), "08".to_string());
+        assert!(matches!(monitor.insertion_severity(&mut storage), Severity::Medium));
+
+        // A flash drive that also types is rated as the keyboard it can be
+        for classes in ["08,03", "03,08"] {
+            let mut composite = keyboard("STICK-1");
+            composite.insert("interface_classes".to_string(), classes.to_string());
+            assert!(matches!(monitor.insertion_severity(&mut composite), Severity::High));
+        }
+    }
+}